@@ -4,6 +4,24 @@ import { BN } from '@coral-xyz/anchor'
 import { useWallet } from '@solana/wallet-adapter-react'
 import { useEscrow } from './EscrowContext'
 
+// Mirrors MAX_ESCROWS_PER_INDEX_PAGE in the program
+const MAX_ESCROWS_PER_INDEX_PAGE = 32
+
+// Mirrors EscrowOptions::default() in the program: a plain fixed-price swap with the named taker
+export const defaultEscrowOptions = () => ({
+  oracle: null,
+  dutchAuction: null,
+  auction: null,
+  attester: null,
+  takerAllowlist: null,
+  referralFeeBps: 0,
+  requireTransferConsent: false,
+  metadata: null,
+  takerBond: null,
+  cancelPenaltyBps: 0,
+  minTakerCompleted: 0,
+})
+
 export default function CreateEscrowForm() {
   const { program, setLoading, setMessage, fetchMakerEscrows, refreshBalance } = useEscrow()
   const { publicKey } = useWallet()
@@ -36,12 +54,13 @@ export default function CreateEscrowForm() {
   const createEscrow = async () => {
     if (!program || !publicKey) return
 
-    // Validation
-    const id = parseInt(escrowId)
+    // Validation (an empty ID lets the program assign the next one)
+    const autoId = escrowId.trim() === ''
+    const id = autoId ? 0 : parseInt(escrowId)
     const aAmount = parseFloat(amountA)
     const bAmount = parseFloat(amountBExpected)
 
-    if (!id || id <= 0) {
+    if (!autoId && (!id || id <= 0)) {
       setLocalError('Invalid escrow ID')
       return
     }
@@ -95,7 +114,7 @@ export default function CreateEscrowForm() {
     // Confirmation with improved message
     const totalMinutes = hours * 60 + minutes
     const confirmMessage = `
-      Create Escrow #${id}?
+      Create Escrow ${autoId ? '(next available ID)' : `#${id}`}?
 
       You offer: ${aAmount} SOL
       You expect: ${bAmount} SOL
@@ -118,13 +137,37 @@ export default function CreateEscrowForm() {
       const bAmountLamports = Math.floor(bAmount * LAMPORTS_PER_SOL)
       const expiry = Math.floor(Date.now() / 1000) + (hours * 3600) + (minutes * 60)
 
+      // Read the maker's counter so we can derive the PDA the program will use
+      let resolvedId = id
+      if (autoId) {
+        const [makerProfilePda] = PublicKey.findProgramAddressSync(
+          [Buffer.from('maker_profile'), publicKey.toBuffer()],
+          program.programId
+        )
+        const profile = await program.account.makerProfile.fetchNullable(makerProfilePda)
+        resolvedId = profile ? profile.nextEscrowId.toNumber() : 0
+      }
+
       const [escrowPda] = PublicKey.findProgramAddressSync(
-        [Buffer.from('escrow'), publicKey.toBuffer(), new BN(id).toArrayLike(Buffer, 'le', 8)],
+        [Buffer.from('escrow'), publicKey.toBuffer(), new BN(resolvedId).toArrayLike(Buffer, 'le', 8)],
         program.programId
       )
 
+      // List the escrow on the first of the taker's index pages that has room
+      const taker = new PublicKey(takerPubkey)
+      let indexPage = 0
+      for (;;) {
+        const [indexPda] = PublicKey.findProgramAddressSync(
+          [Buffer.from('escrow_index'), taker.toBuffer(), new BN(indexPage).toArrayLike(Buffer, 'le', 4)],
+          program.programId
+        )
+        const index = await program.account.escrowIndex.fetchNullable(indexPda)
+        if (!index || index.escrows.length < MAX_ESCROWS_PER_INDEX_PAGE) break
+        indexPage++
+      }
+
       console.log('=== Creating Escrow ===')
-      console.log('Escrow ID:', id)
+      console.log('Escrow ID:', resolvedId)
       console.log('Maker:', publicKey.toBase58())
       console.log('Taker:', takerPubkey)
       console.log('Escrow PDA:', escrowPda.toBase58())
@@ -133,10 +176,11 @@ export default function CreateEscrowForm() {
       console.log('Expiry timestamp:', expiry)
 
       const tx = await program.methods
-        .createEscrow(new BN(id), new BN(aAmountLamports), new BN(bAmountLamports), new BN(expiry), new PublicKey(takerPubkey))
+        .createEscrow(autoId ? null : new BN(id), new BN(aAmountLamports), new BN(bAmountLamports), new BN(expiry), taker, new BN(0), indexPage, defaultEscrowOptions())
         .accounts({
           escrow: escrowPda,
           maker: publicKey,
+          rentPayer: publicKey,
           systemProgram: SystemProgram.programId,
         })
         .rpc({ skipPreflight: false })
@@ -216,7 +260,7 @@ export default function CreateEscrowForm() {
             value={escrowId}
             onChange={(e) => setEscrowId(e.target.value)}
             className="w-full px-3 py-2 bg-gray-700 border border-gray-600 rounded-md text-white placeholder-gray-400"
-            placeholder="Enter unique ID"
+            placeholder="Leave empty to auto-assign"
           />
         </div>
 
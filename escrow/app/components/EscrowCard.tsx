@@ -1,9 +1,13 @@
 import { useState, useEffect } from 'react'
 import { PublicKey, SystemProgram } from '@solana/web3.js'
+import { BN } from '@coral-xyz/anchor'
 import { useWallet } from '@solana/wallet-adapter-react'
 import { useEscrow } from './EscrowContext'
 import TimeDisplay from './TimeDisplay'
 
+// This deployment's wallet for referral fees on escrows that offer one
+const REFERRER = process.env.NEXT_PUBLIC_REFERRER ? new PublicKey(process.env.NEXT_PUBLIC_REFERRER) : null
+
 interface EscrowCardProps {
   escrow: any
   onUpdate?: () => void
@@ -15,6 +19,7 @@ export default function EscrowCard({ escrow, onUpdate }: EscrowCardProps) {
   const [expanded, setExpanded] = useState(false)
   const [localError, setLocalError] = useState<string>('')
   const [localSuccess, setLocalSuccess] = useState<string>('')
+  const [makerStats, setMakerStats] = useState<any>(null)
 
   /**
    * Auto-clear Local Success Messages Effect
@@ -32,15 +37,49 @@ export default function EscrowCard({ escrow, onUpdate }: EscrowCardProps) {
     }
   }, [localSuccess])
 
+  /**
+   * Maker Track Record Effect
+   *
+   * Loads the maker's UserStats when the card is expanded. Wallets with no
+   * settled, cancelled or expired escrow yet have none.
+   */
+  useEffect(() => {
+    if (!expanded || !program) return
+    const [statsPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from('user_stats'), escrow.maker.toBuffer()],
+      program.programId
+    )
+    program.account.userStats.fetchNullable(statsPda).then(setMakerStats).catch(() => setMakerStats(null))
+  }, [expanded, program, escrow.maker])
+
   const now = Date.now() / 1000
   const isExpired = now >= escrow.expiryTs
   const isMaker = escrow.maker.equals(publicKey)
   const isTaker = escrow.taker?.equals(publicKey)
   const canFund = !escrow.isFunded && !isExpired && isTaker
-  const canComplete = escrow.isFunded && !escrow.isCompleted && isTaker
-  const canCancel = !escrow.isFunded && !isExpired && isMaker
+  // Attestation-gated escrows are settled by their attester, not the taker
+  const canComplete = escrow.isFunded && !escrow.isCompleted && isTaker && !escrow.attester
+  // Funded escrows can only be cancelled if the maker agreed to compensate the taker
+  const canCancel = (!escrow.isFunded || escrow.cancelPenaltyBps > 0) && !isExpired && isMaker
   const canRefund = isExpired && !escrow.isCompleted && isMaker
 
+  // Discovery index page listing this escrow (settling or unwinding unlists it)
+  const escrowIndexPda = program && escrow.indexTaker
+    ? PublicKey.findProgramAddressSync(
+        [Buffer.from('escrow_index'), escrow.indexTaker.toBuffer(), new BN(escrow.indexPage ?? 0).toArrayLike(Buffer, 'le', 4)],
+        program.programId
+      )[0]
+    : null
+
+  // NFT escrows settle through their own instructions, which also move the token.
+  // Every finished escrow (and NFT vault) closes back to whoever paid its rent
+  const nftAccounts = escrow.nftMint
+    ? { nftMint: escrow.nftMint, rentPayer: escrow.rentPayer }
+    : { rentPayer: escrow.rentPayer }
+
+  // SOL escrows pay their referral fee to whoever was recorded at funding, if anyone
+  const settleAccounts = escrow.nftMint ? nftAccounts : { ...nftAccounts, referrer: escrow.referrer ?? null }
+
   const truncateAddress = (address: string) => {
     return `${address.slice(0, 4)}...${address.slice(-4)}`
   }
@@ -84,6 +123,7 @@ export default function EscrowCard({ escrow, onUpdate }: EscrowCardProps) {
   const getStatusText = () => {
     if (escrow.isCompleted) return 'Completed'
     if (isExpired) return 'Expired'
+    if (escrow.isFunded && escrow.attester) return 'Funded - Awaiting Attestation'
     if (escrow.isFunded) return 'Funded - Ready to Complete'
     return 'Waiting for Taker'
   }
@@ -115,11 +155,21 @@ export default function EscrowCard({ escrow, onUpdate }: EscrowCardProps) {
 
     try {
       const tx = await program.methods
-        .fundEscrow()
+        // No allowlist proof: this UI funds escrows addressed to a single taker.
+        // Never pay more than the price shown (a Dutch auction's start price); oracle prices aren't shown
+        .fundEscrow([], escrow.oracle ? null : escrow.amountBExpected)
         .accounts({
           escrow: escrow.escrowPda,
           taker: publicKey,
           maker: escrow.maker,
+          // Oracle-priced escrows compute the payment from their price feed
+          priceFeed: escrow.oracle ? escrow.oracle.priceFeed : null,
+          // The program refuses a taker referring themselves
+          referrer: REFERRER && !REFERRER.equals(publicKey) ? REFERRER : null,
+          // Reputation-gated escrows check the taker's settled swap count
+          takerStats: escrow.minTakerCompleted > 0 && program
+            ? PublicKey.findProgramAddressSync([Buffer.from('user_stats'), publicKey.toBuffer()], program.programId)[0]
+            : null,
           systemProgram: SystemProgram.programId,
         })
         .rpc({ skipPreflight: false })
@@ -159,12 +209,14 @@ export default function EscrowCard({ escrow, onUpdate }: EscrowCardProps) {
     setLocalSuccess('')
 
     try {
-      const tx = await program.methods
-        .completeSwap()
+      const method = escrow.nftMint ? program.methods.completeNftSwap() : program.methods.completeSwap()
+      const tx = await method
         .accounts({
           escrow: escrow.escrowPda,
+          escrowIndex: escrowIndexPda,
           taker: publicKey,
           maker: escrow.maker,
+          ...settleAccounts,
           systemProgram: SystemProgram.programId,
         })
         .rpc({ skipPreflight: false })
@@ -204,11 +256,15 @@ export default function EscrowCard({ escrow, onUpdate }: EscrowCardProps) {
     setLocalSuccess('')
 
     try {
-      const tx = await program.methods
-        .cancelEscrow()
+      const method = escrow.nftMint ? program.methods.cancelNftEscrow() : program.methods.cancelEscrow()
+      const tx = await method
         .accounts({
           escrow: escrow.escrowPda,
+          escrowIndex: escrowIndexPda,
           maker: publicKey,
+          authority: publicKey,
+          ...(escrow.isFunded ? { taker: escrow.taker } : {}),
+          ...nftAccounts,
           systemProgram: SystemProgram.programId,
         })
         .rpc({ skipPreflight: false })
@@ -248,11 +304,15 @@ export default function EscrowCard({ escrow, onUpdate }: EscrowCardProps) {
     setLocalSuccess('')
 
     try {
-      const tx = await program.methods
-        .refundAfterExpiry()
+      // cancel_nft_escrow also covers expired NFT escrows
+      const method = escrow.nftMint ? program.methods.cancelNftEscrow() : program.methods.refundAfterExpiry()
+      const tx = await method
         .accounts({
           escrow: escrow.escrowPda,
+          escrowIndex: escrowIndexPda,
           maker: publicKey,
+          authority: publicKey,
+          ...nftAccounts,
           systemProgram: SystemProgram.programId,
         })
         .rpc({ skipPreflight: false })
@@ -304,13 +364,17 @@ export default function EscrowCard({ escrow, onUpdate }: EscrowCardProps) {
         <div>
           <p className="text-sm text-gray-300">You offer:</p>
           <p className="font-semibold text-white">
-            {((typeof escrow.amountA === 'number' ? escrow.amountA : escrow.amountA?.toNumber?.() || 0) / 1e9).toFixed(4)} SOL
+            {escrow.nftMint
+              ? `NFT ${truncateAddress(escrow.nftMint.toBase58())}`
+              : `${((typeof escrow.amountA === 'number' ? escrow.amountA : escrow.amountA?.toNumber?.() || 0) / 1e9).toFixed(4)} SOL`}
           </p>
         </div>
         <div>
           <p className="text-sm text-gray-300">You expect:</p>
           <p className="font-semibold text-white">
-            {((typeof escrow.amountBExpected === 'number' ? escrow.amountBExpected : escrow.amountBExpected?.toNumber?.() || 0) / 1e9).toFixed(4)} SOL
+            {escrow.oracle && !escrow.isFunded && !escrow.isCompleted
+              ? 'Oracle price at funding'
+              : `${((typeof escrow.amountBExpected === 'number' ? escrow.amountBExpected : escrow.amountBExpected?.toNumber?.() || 0) / 1e9).toFixed(4)} SOL`}
           </p>
         </div>
       </div>
@@ -320,6 +384,11 @@ export default function EscrowCard({ escrow, onUpdate }: EscrowCardProps) {
           <div>
             <p className="text-sm text-gray-300">Maker:</p>
             <p className="font-mono text-sm text-white">{truncateAddress(escrow.maker.toBase58())}</p>
+            <p className="text-xs text-gray-400">
+              {makerStats
+                ? `${makerStats.completed} completed, ${makerStats.cancelled} cancelled, ${makerStats.expired} expired, ${makerStats.disputesLost} disputes lost`
+                : 'No track record yet'}
+            </p>
           </div>
           {escrow.taker && (
             <div>
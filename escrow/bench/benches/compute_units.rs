@@ -0,0 +1,1327 @@
+//! # Compute Units per Instruction
+//!
+//! Runs every escrow instruction in each mode it can run in and checks the compute units
+//! against `budgets.toml`. See the `escrow_bench` crate docs for how to run it.
+//!
+//! Scenarios share one SVM and run in order. Each phase creates the escrows it needs and
+//! settles them before moving the clock past their expiry, so the next phase starts clean.
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::hash::hashv;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program_option::COption;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::system_program;
+use anchor_spl::associated_token::{self, get_associated_token_address};
+use anchor_spl::token::spl_token;
+use escrow::{
+    accounts, instruction, AuctionKind, AuctionTerms, BatchEscrowTerms, DutchAuctionTerms,
+    EscrowMetadata, EscrowOptions, OracleTerms, ProposalAction, ReceiptSide, TakerBond,
+    OPERATOR_CAN_AMEND, OPERATOR_CAN_CANCEL,
+};
+use escrow_bench::{escrow_ix, pda, Bench, BenchError, Budgets, Report};
+use solana_sdk::signature::Keypair;
+use solana_sdk::signer::Signer;
+
+const AMOUNT_A: u64 = 1_000_000;
+const AMOUNT_B: u64 = 500_000;
+const HOUR: i64 = 3600;
+
+/// The Pyth price account `Anchor.toml` loads for the oracle tests
+const PRICE_FEED_FIXTURE: &str = "../tests/fixtures/mock_price_feed.json";
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Runs the scenarios and reports them; `Ok(false)` means some went over budget
+fn run() -> Result<bool, BenchError> {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let program_path = env::var_os("ESCROW_SO")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| manifest_dir.join("../target/deploy/escrow.so"));
+    let program = std::fs::read(&program_path).map_err(BenchError::io(&program_path))?;
+
+    let mut bench = Bench::new(&program)?;
+    let mut parties = Parties::new(&mut bench)?;
+    admin(&mut bench, &parties)?;
+    lifecycle(&mut bench, &mut parties)?;
+    pricing(&mut bench, &mut parties)?;
+    takers(&mut bench, &mut parties)?;
+    settlement(&mut bench, &mut parties)?;
+    operators(&mut bench, &mut parties)?;
+    batch(&mut bench, &mut parties)?;
+    auctions(&mut bench, &mut parties)?;
+    multisig(&mut bench, &parties)?;
+    subscriptions(&mut bench, &parties)?;
+    nft(&mut bench, &mut parties)?;
+
+    let budgets_path = manifest_dir.join("budgets.toml");
+    let budgets = Budgets::load(&budgets_path)?;
+    if env::var_os("BENCH_UPDATE_BUDGETS").is_some() {
+        budgets.write_measured(&budgets_path, bench.measurements())?;
+        println!("updated {}", budgets_path.display());
+        return Ok(true);
+    }
+
+    let report = Report::new(bench.measurements(), &budgets);
+    print!("{}", report.table());
+    let report_path = env::var_os("CU_REPORT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| manifest_dir.join("../target/compute-units.json"));
+    report.write(&report_path)?;
+    if !report.unbudgeted.is_empty() {
+        eprintln!(
+            "{} scenarios have no budget of their own; regenerate budgets.toml with BENCH_UPDATE_BUDGETS=1",
+            report.unbudgeted.len()
+        );
+    }
+    if !report.within_budget() {
+        eprintln!("over budget: {}", report.over_budget.join(", "));
+    }
+    Ok(report.within_budget())
+}
+
+/// Wallets the scenarios trade between
+struct Parties {
+    maker: Keypair,
+    taker: Keypair,
+
+    /// Third party: attester, referrer, operator, rival bidder or new owner of rights
+    other: Keypair,
+
+    keeper: Keypair,
+
+    /// Next escrow ID of `maker`'s profile, tracked here to derive the PDA the program's
+    /// counter will pick
+    next_escrow_id: u64,
+}
+
+impl Parties {
+    fn new(bench: &mut Bench) -> Result<Self, BenchError> {
+        Ok(Self {
+            maker: bench.wallet()?,
+            taker: bench.wallet()?,
+            other: bench.wallet()?,
+            keeper: bench.wallet()?,
+            next_escrow_id: 0,
+        })
+    }
+
+    /// Takes the next escrow ID, returning it with the escrow PDA it gives
+    fn next_escrow(&mut self) -> (u64, Pubkey) {
+        let escrow_id = self.next_escrow_id;
+        self.next_escrow_id += 1;
+        (escrow_id, pda::escrow(&self.maker.pubkey(), escrow_id))
+    }
+}
+
+/// Measures `ix` as `scenario`, or just runs it as setup named `setup` when `scenario` is `None`
+fn run_ix(
+    bench: &mut Bench,
+    scenario: Option<&str>,
+    setup: &str,
+    ix: Instruction,
+    signers: &[&Keypair],
+) -> Result<(), BenchError> {
+    match scenario {
+        Some(scenario) => bench.measure(scenario, ix, signers).map(drop),
+        None => bench.execute(setup, &[ix], signers),
+    }
+}
+
+// ============================================================================
+// Instruction builders
+// ============================================================================
+
+/// `create_escrow` arguments for a fixed-price escrow from the maker to the taker, expiring in
+/// an hour; each scenario changes the fields its mode is about
+fn terms(bench: &Bench, p: &Parties) -> instruction::CreateEscrow {
+    instruction::CreateEscrow {
+        escrow_id: None,
+        amount_a: AMOUNT_A,
+        amount_b_expected: AMOUNT_B,
+        expiry_ts: bench.now() + HOUR,
+        taker_pubkey: p.taker.pubkey(),
+        keeper_tip: 0,
+        index_page: 0,
+        options: EscrowOptions::default(),
+    }
+}
+
+fn create_escrow_ix(p: &Parties, escrow: Pubkey, args: instruction::CreateEscrow) -> Instruction {
+    let maker = p.maker.pubkey();
+    escrow_ix(
+        accounts::CreateEscrow {
+            maker_profile: pda::maker_profile(&maker),
+            escrow,
+            escrow_index: pda::escrow_index(&args.taker_pubkey, args.index_page),
+            maker,
+            rent_payer: maker,
+            config: pda::config(),
+            system_program: system_program::ID,
+            event_authority: pda::event_authority(),
+            program: escrow::ID,
+        },
+        args,
+    )
+}
+
+/// Creates an escrow from [`terms`] as changed by `configure`, under the ID the maker
+/// profile's counter assigns, measured as `scenario` if given
+fn create_escrow(
+    bench: &mut Bench,
+    p: &mut Parties,
+    scenario: Option<&str>,
+    configure: impl FnOnce(&mut instruction::CreateEscrow),
+) -> Result<Pubkey, BenchError> {
+    let mut args = terms(bench, p);
+    configure(&mut args);
+    let (_, escrow) = p.next_escrow();
+    let ix = create_escrow_ix(p, escrow, args);
+    run_ix(bench, scenario, "create_escrow", ix, &[&p.maker])?;
+    Ok(escrow)
+}
+
+fn fund_accounts(escrow: Pubkey, maker: Pubkey, taker: Pubkey) -> accounts::FundEscrow {
+    accounts::FundEscrow {
+        escrow,
+        taker,
+        maker,
+        price_feed: None,
+        referrer: None,
+        taker_stats: None,
+        config: pda::config(),
+        system_program: system_program::ID,
+        event_authority: pda::event_authority(),
+        program: escrow::ID,
+    }
+}
+
+fn fund_args() -> instruction::FundEscrow {
+    instruction::FundEscrow {
+        allowlist_proof: Vec::new(),
+        max_amount_b: None,
+    }
+}
+
+/// Funds `escrow` by the taker with no extras, measured as `scenario` if given
+fn fund_escrow(
+    bench: &mut Bench,
+    p: &Parties,
+    scenario: Option<&str>,
+    escrow: Pubkey,
+) -> Result<(), BenchError> {
+    let accounts = fund_accounts(escrow, p.maker.pubkey(), p.taker.pubkey());
+    let ix = escrow_ix(accounts, fund_args());
+    run_ix(bench, scenario, "fund_escrow", ix, &[&p.taker])
+}
+
+/// `complete_swap` accounts for an escrow listed on page 0 of `taker`'s index
+fn complete_accounts(escrow: Pubkey, maker: Pubkey, taker: Pubkey) -> accounts::CompleteSwap {
+    accounts::CompleteSwap {
+        escrow,
+        escrow_index: pda::escrow_index(&taker, 0),
+        taker,
+        maker,
+        rent_payer: maker,
+        referrer: None,
+        memo_program: None,
+        maker_stats: pda::user_stats(&maker),
+        taker_stats: pda::user_stats(&taker),
+        system_program: system_program::ID,
+        event_authority: pda::event_authority(),
+        program: escrow::ID,
+    }
+}
+
+/// Completes `escrow` by the taker, measured as `scenario` if given
+fn complete_swap(
+    bench: &mut Bench,
+    p: &Parties,
+    scenario: Option<&str>,
+    escrow: Pubkey,
+) -> Result<(), BenchError> {
+    let accounts = complete_accounts(escrow, p.maker.pubkey(), p.taker.pubkey());
+    let ix = escrow_ix(accounts, instruction::CompleteSwap {});
+    run_ix(bench, scenario, "complete_swap", ix, &[&p.taker])
+}
+
+/// `cancel_escrow` by the maker, for an escrow listed on page 0 of `index_taker`'s index
+fn cancel_accounts(p: &Parties, escrow: Pubkey, index_taker: &Pubkey) -> accounts::CancelEscrow {
+    let maker = p.maker.pubkey();
+    accounts::CancelEscrow {
+        escrow,
+        escrow_index: pda::escrow_index(index_taker, 0),
+        maker,
+        rent_payer: maker,
+        taker: None,
+        authority: maker,
+        maker_stats: pda::user_stats(&maker),
+        system_program: system_program::ID,
+        event_authority: pda::event_authority(),
+        program: escrow::ID,
+    }
+}
+
+fn attest_accounts(p: &Parties, escrow: Pubkey) -> accounts::Attest {
+    let (maker, taker) = (p.maker.pubkey(), p.taker.pubkey());
+    accounts::Attest {
+        escrow,
+        escrow_index: pda::escrow_index(&taker, 0),
+        attester: p.other.pubkey(),
+        taker,
+        maker,
+        rent_payer: maker,
+        referrer: None,
+        memo_program: None,
+        maker_stats: pda::user_stats(&maker),
+        taker_stats: pda::user_stats(&taker),
+        system_program: system_program::ID,
+        event_authority: pda::event_authority(),
+        program: escrow::ID,
+    }
+}
+
+fn crank_accounts(p: &Parties, escrow: Pubkey) -> accounts::CrankExpired {
+    let maker = p.maker.pubkey();
+    accounts::CrankExpired {
+        escrow,
+        escrow_index: pda::escrow_index(&p.taker.pubkey(), 0),
+        maker,
+        rent_payer: maker,
+        keeper: p.keeper.pubkey(),
+        maker_stats: pda::user_stats(&maker),
+        system_program: system_program::ID,
+        event_authority: pda::event_authority(),
+        program: escrow::ID,
+    }
+}
+
+fn set_operator_accounts(p: &Parties, escrow: Pubkey) -> accounts::SetOperator {
+    accounts::SetOperator {
+        escrow,
+        maker: p.maker.pubkey(),
+        event_authority: pda::event_authority(),
+        program: escrow::ID,
+    }
+}
+
+fn amend_accounts(escrow: Pubkey, authority: Pubkey) -> accounts::AmendEscrow {
+    accounts::AmendEscrow {
+        escrow,
+        authority,
+        event_authority: pda::event_authority(),
+        program: escrow::ID,
+    }
+}
+
+fn mint_receipt_ix(escrow: Pubkey, depositor: Pubkey, side: ReceiptSide) -> Instruction {
+    let receipt_mint = pda::receipt_mint(&escrow, side as u8);
+    escrow_ix(
+        accounts::MintReceipt {
+            escrow,
+            receipt_mint,
+            depositor_receipt_account: get_associated_token_address(&depositor, &receipt_mint),
+            depositor,
+            token_program: spl_token::ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+            event_authority: pda::event_authority(),
+            program: escrow::ID,
+        },
+        instruction::MintReceipt { side },
+    )
+}
+
+fn redeem_receipt_ix(
+    escrow: Pubkey,
+    holder: Pubkey,
+    rent_payer: Pubkey,
+    side: ReceiptSide,
+) -> Instruction {
+    let receipt_mint = pda::receipt_mint(&escrow, side as u8);
+    escrow_ix(
+        accounts::RedeemReceipt {
+            escrow,
+            receipt_mint,
+            holder_receipt_account: get_associated_token_address(&holder, &receipt_mint),
+            holder,
+            rent_payer,
+            token_program: spl_token::ID,
+            event_authority: pda::event_authority(),
+            program: escrow::ID,
+        },
+        instruction::RedeemReceipt { side },
+    )
+}
+
+/// Pairs each escrow with its index page, the layout `batch_create_escrows` expects;
+/// `batch_cancel_escrows` also takes each escrow's `rent_payer`
+fn batch_remaining_accounts(
+    escrows: &[Pubkey],
+    escrow_index: Pubkey,
+    rent_payer: Option<Pubkey>,
+) -> Vec<AccountMeta> {
+    escrows
+        .iter()
+        .flat_map(|escrow| {
+            [
+                AccountMeta::new(*escrow, false),
+                AccountMeta::new(escrow_index, false),
+            ]
+            .into_iter()
+            .chain(rent_payer.map(|rent_payer| AccountMeta::new(rent_payer, false)))
+        })
+        .collect()
+}
+
+fn place_bid_ix(
+    escrow: Pubkey,
+    bidder: Pubkey,
+    amount: u64,
+    previous_bidder: Option<Pubkey>,
+) -> Instruction {
+    escrow_ix(
+        accounts::PlaceBid {
+            escrow,
+            bid: pda::bid(&escrow, &bidder),
+            bidder,
+            previous_bid: previous_bidder.map(|previous| pda::bid(&escrow, &previous)),
+            previous_bidder,
+            config: pda::config(),
+            system_program: system_program::ID,
+            event_authority: pda::event_authority(),
+            program: escrow::ID,
+        },
+        instruction::PlaceBid { amount },
+    )
+}
+
+/// The commitment `reveal_bid` checks: `sha256(amount_le || salt || bidder)`
+fn bid_commitment(amount: u64, salt: &[u8; 32], bidder: &Pubkey) -> [u8; 32] {
+    hashv(&[&amount.to_le_bytes(), salt, bidder.as_ref()]).to_bytes()
+}
+
+fn commit_bid_ix(
+    escrow: Pubkey,
+    bidder: Pubkey,
+    commitment: [u8; 32],
+    deposit: u64,
+) -> Instruction {
+    escrow_ix(
+        accounts::CommitBid {
+            escrow,
+            bid: pda::bid(&escrow, &bidder),
+            bidder,
+            config: pda::config(),
+            system_program: system_program::ID,
+            event_authority: pda::event_authority(),
+            program: escrow::ID,
+        },
+        instruction::CommitBid {
+            commitment,
+            deposit,
+        },
+    )
+}
+
+fn reveal_bid_ix(escrow: Pubkey, bidder: Pubkey, amount: u64, salt: [u8; 32]) -> Instruction {
+    escrow_ix(
+        accounts::RevealBid {
+            escrow,
+            bid: pda::bid(&escrow, &bidder),
+            bidder,
+            event_authority: pda::event_authority(),
+            program: escrow::ID,
+        },
+        instruction::RevealBid { amount, salt },
+    )
+}
+
+/// `settle_auction` by the keeper, for an auction listed on page 0 of the taker's index
+fn settle_auction_ix(p: &Parties, escrow: Pubkey, winner: Pubkey) -> Instruction {
+    let maker = p.maker.pubkey();
+    escrow_ix(
+        accounts::SettleAuction {
+            escrow,
+            escrow_index: pda::escrow_index(&p.taker.pubkey(), 0),
+            winning_bid: pda::bid(&escrow, &winner),
+            winner,
+            maker,
+            rent_payer: maker,
+            settler: p.keeper.pubkey(),
+            memo_program: None,
+            maker_stats: pda::user_stats(&maker),
+            winner_stats: pda::user_stats(&winner),
+            system_program: system_program::ID,
+            event_authority: pda::event_authority(),
+            program: escrow::ID,
+        },
+        instruction::SettleAuction {},
+    )
+}
+
+fn propose_ix(
+    multisig: Pubkey,
+    index: u64,
+    escrow: Option<Pubkey>,
+    proposer: Pubkey,
+    action: ProposalAction,
+) -> Instruction {
+    escrow_ix(
+        accounts::Propose {
+            multisig,
+            proposal: pda::proposal(&multisig, index),
+            escrow,
+            proposer,
+            system_program: system_program::ID,
+        },
+        instruction::Propose { action },
+    )
+}
+
+fn approve_proposal_ix(
+    multisig: Pubkey,
+    index: u64,
+    escrow: Option<Pubkey>,
+    owner: Pubkey,
+) -> Instruction {
+    escrow_ix(
+        accounts::ApproveProposal {
+            multisig,
+            proposal: pda::proposal(&multisig, index),
+            escrow,
+            owner,
+        },
+        instruction::ApproveProposal {},
+    )
+}
+
+/// `execute_proposal` by its proposer; `escrow_index`, `rent_payer` (and with it the
+/// multisig's stats) and `destination` as the action needs
+fn execute_proposal_ix(
+    multisig: Pubkey,
+    index: u64,
+    proposer: Pubkey,
+    escrow: Option<Pubkey>,
+    escrow_index: Option<Pubkey>,
+    rent_payer: Option<Pubkey>,
+    destination: Option<Pubkey>,
+) -> Instruction {
+    escrow_ix(
+        accounts::ExecuteProposal {
+            multisig,
+            proposal: pda::proposal(&multisig, index),
+            escrow,
+            escrow_index,
+            rent_payer,
+            maker_stats: rent_payer.map(|_| pda::user_stats(&multisig)),
+            destination,
+            proposer,
+            executor: proposer,
+            system_program: system_program::ID,
+            event_authority: pda::event_authority(),
+            program: escrow::ID,
+        },
+        instruction::ExecuteProposal {},
+    )
+}
+
+/// `open_user_stats` for `wallet`, paid by the wallet itself
+fn open_user_stats_ix(wallet: Pubkey) -> Instruction {
+    escrow_ix(
+        accounts::OpenUserStats {
+            user_stats: pda::user_stats(&wallet),
+            wallet,
+            payer: wallet,
+            system_program: system_program::ID,
+        },
+        instruction::OpenUserStats {},
+    )
+}
+
+/// Mints a one-of-one token with no mint authority into `owner`'s associated token
+/// account, as if minted elsewhere and the authority then revoked
+fn mint_nft(bench: &mut Bench, owner: &Pubkey) -> Result<(Pubkey, Pubkey), BenchError> {
+    let mint = Pubkey::new_unique();
+    let mut data = vec![0; spl_token::state::Mint::LEN];
+    spl_token::state::Mint {
+        mint_authority: COption::None,
+        supply: 1,
+        decimals: 0,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    }
+    .pack_into_slice(&mut data);
+    bench.set_account(mint, spl_token::ID, data, false)?;
+
+    let token_account = get_associated_token_address(owner, &mint);
+    let mut data = vec![0; spl_token::state::Account::LEN];
+    spl_token::state::Account {
+        mint,
+        owner: *owner,
+        amount: 1,
+        delegate: COption::None,
+        state: spl_token::state::AccountState::Initialized,
+        is_native: COption::None,
+        delegated_amount: 0,
+        close_authority: COption::None,
+    }
+    .pack_into_slice(&mut data);
+    bench.set_account(token_account, spl_token::ID, data, false)?;
+    Ok((mint, token_account))
+}
+
+/// Creates an NFT escrow of `nft_mint` under the ID the maker profile's counter assigns,
+/// measured as `scenario` if given
+fn create_nft_escrow(
+    bench: &mut Bench,
+    p: &mut Parties,
+    scenario: Option<&str>,
+    nft_mint: Pubkey,
+    maker_nft_account: Pubkey,
+) -> Result<Pubkey, BenchError> {
+    let (_, escrow) = p.next_escrow();
+    let (maker, taker) = (p.maker.pubkey(), p.taker.pubkey());
+    let ix = escrow_ix(
+        accounts::CreateNftEscrow {
+            maker_profile: pda::maker_profile(&maker),
+            escrow,
+            escrow_index: pda::escrow_index(&taker, 0),
+            nft_mint,
+            maker_nft_account,
+            vault: get_associated_token_address(&escrow, &nft_mint),
+            nft_metadata: None,
+            maker,
+            rent_payer: maker,
+            config: pda::config(),
+            token_program: spl_token::ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+            event_authority: pda::event_authority(),
+            program: escrow::ID,
+        },
+        instruction::CreateNftEscrow {
+            escrow_id: None,
+            amount_b_expected: AMOUNT_B,
+            expiry_ts: bench.now() + HOUR,
+            taker_pubkey: taker,
+            collection: None,
+            index_page: 0,
+            dutch_auction: None,
+        },
+    );
+    run_ix(bench, scenario, "create_nft_escrow", ix, &[&p.maker])?;
+    Ok(escrow)
+}
+
+// ============================================================================
+// Scenarios
+// ============================================================================
+
+/// Program setup and the emergency pause
+fn admin(bench: &mut Bench, p: &Parties) -> Result<(), BenchError> {
+    let admin = bench.upgrade_authority();
+    let ix = escrow_ix(
+        accounts::InitializeConfig {
+            config: pda::config(),
+            escrow_program: escrow::ID,
+            program_data: pda::program_data(),
+            admin: admin.pubkey(),
+            system_program: system_program::ID,
+        },
+        instruction::InitializeConfig {},
+    );
+    bench.measure("initialize_config/once", ix, &[&admin])?;
+
+    let set_paused = || accounts::SetPaused {
+        config: pda::config(),
+        admin: admin.pubkey(),
+        event_authority: pda::event_authority(),
+        program: escrow::ID,
+    };
+    let ix = escrow_ix(set_paused(), instruction::PauseProgram {});
+    bench.measure("pause_program/admin", ix, &[&admin])?;
+    let ix = escrow_ix(set_paused(), instruction::UnpauseProgram {});
+    bench.measure("unpause_program/admin", ix, &[&admin])?;
+    Ok(())
+}
+
+/// Fixed-price escrows from creation to every way they can end
+fn lifecycle(bench: &mut Bench, p: &mut Parties) -> Result<(), BenchError> {
+    // The maker's first escrow also creates their profile and the taker's index page
+    let first = create_escrow(bench, p, Some("create_escrow/first_for_maker"), |_| {})?;
+    let fixed = create_escrow(bench, p, Some("create_escrow/fixed"), |_| {})?;
+
+    // Clients may still pick the ID themselves; the counter skips past it
+    let (escrow_id, explicit_id) = p.next_escrow();
+    let mut args = terms(bench, p);
+    args.escrow_id = Some(escrow_id);
+    let ix = create_escrow_ix(p, explicit_id, args);
+    bench.measure("create_escrow/explicit_id", ix, &[&p.maker])?;
+
+    // The maker opens their own record; the first swap creates the taker's
+    let ix = open_user_stats_ix(p.maker.pubkey());
+    bench.measure("open_user_stats/own", ix, &[&p.maker])?;
+    fund_escrow(bench, p, Some("fund_escrow/fixed"), first)?;
+    complete_swap(bench, p, Some("complete_swap/creates_user_stats"), first)?;
+    fund_escrow(bench, p, None, fixed)?;
+    complete_swap(bench, p, Some("complete_swap/fixed"), fixed)?;
+
+    let ix = escrow_ix(
+        cancel_accounts(p, explicit_id, &p.taker.pubkey()),
+        instruction::CancelEscrow {},
+    );
+    bench.measure("cancel_escrow/unfunded", ix, &[&p.maker])?;
+
+    let tipped = create_escrow(bench, p, Some("create_escrow/keeper_tip"), |args| {
+        args.keeper_tip = 10_000;
+    })?;
+    let untipped = create_escrow(bench, p, None, |_| {})?;
+    let refunded = create_escrow(bench, p, None, |_| {})?;
+    bench.warp(HOUR + 1);
+
+    let maker = p.maker.pubkey();
+    let ix = escrow_ix(
+        accounts::RefundAfterExpiry {
+            escrow: refunded,
+            escrow_index: pda::escrow_index(&p.taker.pubkey(), 0),
+            maker,
+            rent_payer: maker,
+            authority: maker,
+            maker_stats: pda::user_stats(&maker),
+            system_program: system_program::ID,
+            event_authority: pda::event_authority(),
+            program: escrow::ID,
+        },
+        instruction::RefundAfterExpiry {},
+    );
+    bench.measure("refund_after_expiry/unfunded", ix, &[&p.maker])?;
+
+    let ix = escrow_ix(crank_accounts(p, tipped), instruction::CrankExpired {});
+    bench.measure("crank_expired/keeper_tip", ix, &[&p.keeper])?;
+    let ix = escrow_ix(crank_accounts(p, untipped), instruction::CrankExpired {});
+    bench.measure("crank_expired/no_tip", ix, &[&p.keeper])?;
+    Ok(())
+}
+
+/// Escrows priced at funding time
+fn pricing(bench: &mut Bench, p: &mut Parties) -> Result<(), BenchError> {
+    let now = bench.now();
+    let dutch = create_escrow(bench, p, Some("create_escrow/dutch_auction"), |args| {
+        args.options.dutch_auction = Some(DutchAuctionTerms {
+            start_price: 2 * AMOUNT_B,
+            floor_price: AMOUNT_B,
+            start_ts: now,
+            step_secs: 60,
+        });
+    })?;
+    bench.warp(600);
+    let accounts = fund_accounts(dutch, p.maker.pubkey(), p.taker.pubkey());
+    let args = instruction::FundEscrow {
+        max_amount_b: Some(2 * AMOUNT_B),
+        ..fund_args()
+    };
+    bench.measure(
+        "fund_escrow/dutch_auction",
+        escrow_ix(accounts, args),
+        &[&p.taker],
+    )?;
+    complete_swap(bench, p, None, dutch)?;
+
+    let price_feed = bench
+        .load_account_fixture(&Path::new(env!("CARGO_MANIFEST_DIR")).join(PRICE_FEED_FIXTURE))?;
+    let oracle = create_escrow(bench, p, Some("create_escrow/oracle"), |args| {
+        args.amount_b_expected = 0;
+        args.options.oracle = Some(OracleTerms {
+            price_feed,
+            min_price: 40_000_000,
+            max_price: 60_000_000,
+            // The fixture's publish time never moves
+            max_price_age: u32::MAX,
+            max_confidence_bps: 100,
+        });
+    })?;
+    let accounts = accounts::FundEscrow {
+        price_feed: Some(price_feed),
+        ..fund_accounts(oracle, p.maker.pubkey(), p.taker.pubkey())
+    };
+    bench.measure(
+        "fund_escrow/oracle",
+        escrow_ix(accounts, fund_args()),
+        &[&p.taker],
+    )?;
+    complete_swap(bench, p, None, oracle)?;
+    Ok(())
+}
+
+/// Who may take an escrow, who routed them, and handing the rights on
+fn takers(bench: &mut Bench, p: &mut Parties) -> Result<(), BenchError> {
+    let (maker, taker, other) = (p.maker.pubkey(), p.taker.pubkey(), p.other.pubkey());
+
+    // A two-leaf allowlist: the taker's proof is the other leaf
+    let leaf = |wallet: &Pubkey| hashv(&[&[0], wallet.as_ref()]).to_bytes();
+    let (taker_leaf, other_leaf) = (leaf(&taker), leaf(&other));
+    let (lo, hi) = if taker_leaf <= other_leaf {
+        (taker_leaf, other_leaf)
+    } else {
+        (other_leaf, taker_leaf)
+    };
+    let root = hashv(&[&[1], &lo, &hi]).to_bytes();
+    let allowlisted = create_escrow(bench, p, Some("create_escrow/allowlist"), |args| {
+        args.options.taker_allowlist = Some(root);
+    })?;
+    let args = instruction::FundEscrow {
+        allowlist_proof: vec![other_leaf],
+        ..fund_args()
+    };
+    let ix = escrow_ix(fund_accounts(allowlisted, maker, taker), args);
+    bench.measure("fund_escrow/allowlist", ix, &[&p.taker])?;
+    complete_swap(bench, p, None, allowlisted)?;
+
+    // The taker has completed swaps by now
+    let gated = create_escrow(
+        bench,
+        p,
+        Some("create_escrow/min_taker_completed"),
+        |args| {
+            args.options.min_taker_completed = 1;
+        },
+    )?;
+    let accounts = accounts::FundEscrow {
+        taker_stats: Some(pda::user_stats(&taker)),
+        ..fund_accounts(gated, maker, taker)
+    };
+    bench.measure(
+        "fund_escrow/min_taker_completed",
+        escrow_ix(accounts, fund_args()),
+        &[&p.taker],
+    )?;
+    complete_swap(bench, p, None, gated)?;
+
+    let referred = create_escrow(bench, p, Some("create_escrow/referral_fee"), |args| {
+        args.options.referral_fee_bps = 100;
+    })?;
+    let accounts = accounts::FundEscrow {
+        referrer: Some(other),
+        ..fund_accounts(referred, maker, taker)
+    };
+    bench.measure(
+        "fund_escrow/referrer",
+        escrow_ix(accounts, fund_args()),
+        &[&p.taker],
+    )?;
+    let accounts = accounts::CompleteSwap {
+        referrer: Some(other),
+        ..complete_accounts(referred, maker, taker)
+    };
+    let ix = escrow_ix(accounts, instruction::CompleteSwap {});
+    bench.measure("complete_swap/referral", ix, &[&p.taker])?;
+
+    // With consent, the taker's rights pass to `other` and then the maker's to the keeper
+    let consented = create_escrow(bench, p, None, |args| {
+        args.options.require_transfer_consent = true;
+    })?;
+    fund_escrow(bench, p, None, consented)?;
+    let transfer_taker_rights = |escrow, new_taker: Pubkey, counterparty: Option<Pubkey>| {
+        escrow_ix(
+            accounts::TransferTakerRights {
+                escrow,
+                escrow_index: pda::escrow_index(&taker, 0),
+                new_escrow_index: pda::escrow_index(&new_taker, 0),
+                taker,
+                counterparty,
+                new_taker_stats: None,
+                system_program: system_program::ID,
+                event_authority: pda::event_authority(),
+                program: escrow::ID,
+            },
+            instruction::TransferTakerRights {
+                new_taker,
+                index_page: 0,
+            },
+        )
+    };
+    let ix = transfer_taker_rights(consented, other, Some(maker));
+    bench.measure(
+        "transfer_taker_rights/with_consent",
+        ix,
+        &[&p.taker, &p.maker],
+    )?;
+    let keeper = p.keeper.pubkey();
+    let ix = escrow_ix(
+        accounts::TransferMakerRights {
+            escrow: consented,
+            maker,
+            counterparty: Some(other),
+            event_authority: pda::event_authority(),
+            program: escrow::ID,
+        },
+        instruction::TransferMakerRights { new_maker: keeper },
+    );
+    bench.measure(
+        "transfer_maker_rights/with_consent",
+        ix,
+        &[&p.maker, &p.other],
+    )?;
+    let ix = escrow_ix(
+        complete_accounts(consented, keeper, other),
+        instruction::CompleteSwap {},
+    );
+    bench.execute("complete_swap", &[ix], &[&p.other])?;
+
+    // Without a consent requirement the taker hands on an unfunded escrow alone
+    let unilateral = create_escrow(bench, p, None, |_| {})?;
+    let ix = transfer_taker_rights(unilateral, other, None);
+    bench.measure("transfer_taker_rights/unilateral", ix, &[&p.taker])?;
+    let ix = escrow_ix(
+        cancel_accounts(p, unilateral, &other),
+        instruction::CancelEscrow {},
+    );
+    bench.execute("cancel_escrow", &[ix], &[&p.maker])?;
+    Ok(())
+}
+
+/// Settlement terms: attesters, bonds, penalties, memos and receipts
+fn settlement(bench: &mut Bench, p: &mut Parties) -> Result<(), BenchError> {
+    let (maker, taker) = (p.maker.pubkey(), p.taker.pubkey());
+    let attester = p.other.pubkey();
+    let released = create_escrow(bench, p, Some("create_escrow/attester"), |args| {
+        args.options.attester = Some(attester)
+    })?;
+    fund_escrow(bench, p, None, released)?;
+    let ix = escrow_ix(attest_accounts(p, released), instruction::AttestRelease {});
+    bench.measure("attest_release/basic", ix, &[&p.other])?;
+    let refunded = create_escrow(bench, p, None, |args| {
+        args.options.attester = Some(attester)
+    })?;
+    fund_escrow(bench, p, None, refunded)?;
+    let ix = escrow_ix(attest_accounts(p, refunded), instruction::AttestRefund {});
+    bench.measure("attest_refund/basic", ix, &[&p.other])?;
+
+    let bonded = create_escrow(bench, p, Some("create_escrow/taker_bond"), |args| {
+        args.options.taker_bond = Some(TakerBond {
+            amount: 100_000,
+            settle_secs: 60,
+        });
+    })?;
+    fund_escrow(bench, p, Some("fund_escrow/taker_bond"), bonded)?;
+    bench.warp(61);
+    let ix = escrow_ix(
+        accounts::SlashTakerBond {
+            escrow: bonded,
+            escrow_index: pda::escrow_index(&taker, 0),
+            maker,
+            taker,
+            rent_payer: maker,
+            authority: maker,
+            memo_program: None,
+            taker_stats: pda::user_stats(&taker),
+            system_program: system_program::ID,
+            event_authority: pda::event_authority(),
+            program: escrow::ID,
+        },
+        instruction::SlashTakerBond {},
+    );
+    bench.measure("slash_taker_bond/basic", ix, &[&p.maker])?;
+
+    let penalized = create_escrow(bench, p, Some("create_escrow/cancel_penalty"), |args| {
+        args.options.cancel_penalty_bps = 500;
+    })?;
+    fund_escrow(bench, p, None, penalized)?;
+    let accounts = accounts::CancelEscrow {
+        taker: Some(taker),
+        ..cancel_accounts(p, penalized, &taker)
+    };
+    let ix = escrow_ix(accounts, instruction::CancelEscrow {});
+    bench.measure("cancel_escrow/funded_penalty", ix, &[&p.maker])?;
+
+    let memo = create_escrow(bench, p, Some("create_escrow/metadata"), |args| {
+        args.options.metadata = Some(EscrowMetadata {
+            content_hash: hashv(&[b"invoice #1"]).to_bytes(),
+            uri: Some("https://example.com/invoices/1".to_string()),
+        });
+    })?;
+    fund_escrow(bench, p, None, memo)?;
+    let accounts = accounts::CompleteSwap {
+        memo_program: Some(anchor_spl::memo::ID),
+        ..complete_accounts(memo, maker, taker)
+    };
+    let ix = escrow_ix(accounts, instruction::CompleteSwap {});
+    bench.measure("complete_swap/memo", ix, &[&p.taker])?;
+
+    // Receipts turn both payouts into claims, redeemed by whoever holds them
+    let receipted = create_escrow(bench, p, None, |_| {})?;
+    let ix = mint_receipt_ix(receipted, maker, ReceiptSide::Maker);
+    bench.measure("mint_receipt/maker", ix, &[&p.maker])?;
+    fund_escrow(bench, p, None, receipted)?;
+    let ix = mint_receipt_ix(receipted, taker, ReceiptSide::Taker);
+    bench.measure("mint_receipt/taker", ix, &[&p.taker])?;
+    complete_swap(bench, p, Some("complete_swap/receipts"), receipted)?;
+    let ix = redeem_receipt_ix(receipted, maker, maker, ReceiptSide::Maker);
+    bench.measure("redeem_receipt/maker", ix, &[&p.maker])?;
+    let ix = redeem_receipt_ix(receipted, taker, maker, ReceiptSide::Taker);
+    bench.measure("redeem_receipt/taker", ix, &[&p.taker])?;
+    Ok(())
+}
+
+/// Delegating amendments to an operator
+fn operators(bench: &mut Bench, p: &mut Parties) -> Result<(), BenchError> {
+    let escrow = create_escrow(bench, p, None, |_| {})?;
+    let ix = escrow_ix(
+        set_operator_accounts(p, escrow),
+        instruction::SetOperator {
+            operator: p.other.pubkey(),
+            permissions: OPERATOR_CAN_CANCEL | OPERATOR_CAN_AMEND,
+        },
+    );
+    bench.measure("set_operator/basic", ix, &[&p.maker])?;
+
+    let amend = |bench: &Bench, amount_b_expected| instruction::AmendEscrow {
+        amount_b_expected,
+        expiry_ts: bench.now() + HOUR,
+    };
+    let ix = escrow_ix(
+        amend_accounts(escrow, p.maker.pubkey()),
+        amend(bench, AMOUNT_B + 1),
+    );
+    bench.measure("amend_escrow/maker", ix, &[&p.maker])?;
+    let ix = escrow_ix(
+        amend_accounts(escrow, p.other.pubkey()),
+        amend(bench, AMOUNT_B + 2),
+    );
+    bench.measure("amend_escrow/operator", ix, &[&p.other])?;
+
+    let ix = escrow_ix(
+        set_operator_accounts(p, escrow),
+        instruction::RevokeOperator {},
+    );
+    bench.measure("revoke_operator/basic", ix, &[&p.maker])?;
+    let ix = escrow_ix(
+        cancel_accounts(p, escrow, &p.taker.pubkey()),
+        instruction::CancelEscrow {},
+    );
+    bench.execute("cancel_escrow", &[ix], &[&p.maker])?;
+    Ok(())
+}
+
+/// Three escrows at once, listed on page 1 of the taker's index
+fn batch(bench: &mut Bench, p: &mut Parties) -> Result<(), BenchError> {
+    const BATCH: usize = 3;
+    let maker = p.maker.pubkey();
+    let escrow_index = pda::escrow_index(&p.taker.pubkey(), 1);
+    let mut escrows = Vec::with_capacity(BATCH);
+    let mut terms = Vec::with_capacity(BATCH);
+    for _ in 0..BATCH {
+        let (escrow_id, escrow) = p.next_escrow();
+        escrows.push(escrow);
+        terms.push(BatchEscrowTerms {
+            escrow_id: Some(escrow_id),
+            amount_a: AMOUNT_A,
+            amount_b_expected: AMOUNT_B,
+            expiry_ts: bench.now() + HOUR,
+            taker_pubkey: p.taker.pubkey(),
+            keeper_tip: 0,
+            index_page: 1,
+            referral_fee_bps: 0,
+        });
+    }
+
+    let mut ix = escrow_ix(
+        accounts::BatchCreateEscrows {
+            maker_profile: pda::maker_profile(&maker),
+            maker,
+            rent_payer: maker,
+            config: pda::config(),
+            system_program: system_program::ID,
+            event_authority: pda::event_authority(),
+            program: escrow::ID,
+        },
+        instruction::BatchCreateEscrows { terms },
+    );
+    ix.accounts
+        .extend(batch_remaining_accounts(&escrows, escrow_index, None));
+    bench.measure("batch_create_escrows/3", ix, &[&p.maker])?;
+
+    let mut ix = escrow_ix(
+        accounts::BatchCancelEscrows {
+            maker,
+            authority: maker,
+            maker_stats: pda::user_stats(&maker),
+            system_program: system_program::ID,
+            event_authority: pda::event_authority(),
+            program: escrow::ID,
+        },
+        instruction::BatchCancelEscrows {},
+    );
+    ix.accounts.extend(batch_remaining_accounts(
+        &escrows,
+        escrow_index,
+        Some(maker),
+    ));
+    bench.measure("batch_cancel_escrows/3", ix, &[&p.maker])?;
+    Ok(())
+}
+
+/// An English and a sealed-bid auction, run side by side until both settle
+fn auctions(bench: &mut Bench, p: &mut Parties) -> Result<(), BenchError> {
+    const REVEAL_SECS: u32 = 600;
+    let (taker, other) = (p.taker.pubkey(), p.other.pubkey());
+    let auction = |kind, reveal_secs| AuctionTerms {
+        kind,
+        reserve_price: AMOUNT_B,
+        min_increment: 10_000,
+        reveal_secs,
+    };
+
+    let english = create_escrow(bench, p, Some("create_escrow/english_auction"), |args| {
+        args.options.auction = Some(auction(AuctionKind::English, 0));
+    })?;
+    let ix = place_bid_ix(english, taker, AMOUNT_B, None);
+    bench.measure("place_bid/first", ix, &[&p.taker])?;
+    let ix = place_bid_ix(english, other, AMOUNT_B + 10_000, Some(taker));
+    bench.measure("place_bid/outbid", ix, &[&p.other])?;
+
+    let sealed = create_escrow(bench, p, Some("create_escrow/sealed_bid_auction"), |args| {
+        args.options.auction = Some(auction(AuctionKind::SealedBid, REVEAL_SECS));
+    })?;
+    let (taker_salt, other_salt) = ([1; 32], [2; 32]);
+    let (taker_bid, other_bid) = (AMOUNT_B, AMOUNT_B + 50_000);
+    let ix = commit_bid_ix(
+        sealed,
+        taker,
+        bid_commitment(taker_bid, &taker_salt, &taker),
+        2 * AMOUNT_B,
+    );
+    bench.measure("commit_bid/basic", ix, &[&p.taker])?;
+    let ix = commit_bid_ix(
+        sealed,
+        other,
+        bid_commitment(other_bid, &other_salt, &other),
+        2 * AMOUNT_B,
+    );
+    bench.execute("commit_bid", &[ix], &[&p.other])?;
+
+    bench.warp(HOUR + 1);
+    let ix = settle_auction_ix(p, english, other);
+    bench.measure("settle_auction/english", ix, &[&p.keeper])?;
+
+    let ix = reveal_bid_ix(sealed, taker, taker_bid, taker_salt);
+    bench.measure("reveal_bid/leading", ix, &[&p.taker])?;
+    let ix = reveal_bid_ix(sealed, other, other_bid, other_salt);
+    bench.measure("reveal_bid/outbids_leader", ix, &[&p.other])?;
+    let ix = escrow_ix(
+        accounts::WithdrawBid {
+            escrow: sealed,
+            bid: pda::bid(&sealed, &taker),
+            bidder: taker,
+            rent_payer: p.maker.pubkey(),
+            event_authority: pda::event_authority(),
+            program: escrow::ID,
+        },
+        instruction::WithdrawBid {},
+    );
+    bench.measure("withdraw_bid/outbid", ix, &[&p.taker])?;
+
+    bench.warp(i64::from(REVEAL_SECS));
+    let ix = settle_auction_ix(p, sealed, other);
+    bench.measure("settle_auction/sealed_bid", ix, &[&p.keeper])?;
+    Ok(())
+}
+
+/// A 2-of-3 multisig maker cancelling its escrow, then withdrawing the refund
+fn multisig(bench: &mut Bench, p: &Parties) -> Result<(), BenchError> {
+    let (maker, taker) = (p.maker.pubkey(), p.taker.pubkey());
+    let create_key = Keypair::new();
+    let multisig = pda::multisig(&create_key.pubkey());
+    let ix = escrow_ix(
+        accounts::CreateMultisig {
+            multisig,
+            create_key: create_key.pubkey(),
+            payer: maker,
+            system_program: system_program::ID,
+        },
+        instruction::CreateMultisig {
+            owners: vec![maker, taker, p.other.pubkey()],
+            threshold: 2,
+        },
+    );
+    bench.measure("create_multisig/2_of_3", ix, &[&create_key, &p.maker])?;
+
+    let escrow = pda::escrow(&multisig, 0);
+    let escrow_index = pda::escrow_index(&taker, 0);
+    let ix = escrow_ix(
+        accounts::CreateMultisigEscrow {
+            multisig,
+            maker_profile: pda::maker_profile(&multisig),
+            escrow,
+            escrow_index,
+            owner: maker,
+            config: pda::config(),
+            system_program: system_program::ID,
+            event_authority: pda::event_authority(),
+            program: escrow::ID,
+        },
+        instruction::CreateMultisigEscrow {
+            escrow_id: Some(0),
+            amount_a: AMOUNT_A,
+            amount_b_expected: AMOUNT_B,
+            expiry_ts: bench.now() + HOUR,
+            taker_pubkey: taker,
+            keeper_tip: 0,
+            index_page: 0,
+        },
+    );
+    bench.measure("create_multisig_escrow/basic", ix, &[&p.maker])?;
+
+    let ix = propose_ix(multisig, 0, Some(escrow), maker, ProposalAction::Cancel);
+    bench.measure("propose/cancel", ix, &[&p.maker])?;
+    let ix = approve_proposal_ix(multisig, 0, Some(escrow), taker);
+    bench.measure("approve_proposal/cancel", ix, &[&p.taker])?;
+    let ix = execute_proposal_ix(
+        multisig,
+        0,
+        maker,
+        Some(escrow),
+        Some(escrow_index),
+        Some(maker),
+        None,
+    );
+    bench.measure("execute_proposal/cancel", ix, &[&p.maker])?;
+
+    // The cancelled escrow refunded the multisig's treasury
+    let destination = p.other.pubkey();
+    let withdraw = ProposalAction::Withdraw {
+        destination,
+        lamports: AMOUNT_A,
+    };
+    let ix = propose_ix(multisig, 1, None, maker, withdraw);
+    bench.measure("propose/withdraw", ix, &[&p.maker])?;
+    let ix = approve_proposal_ix(multisig, 1, None, taker);
+    bench.measure("approve_proposal/withdraw", ix, &[&p.taker])?;
+    let ix = execute_proposal_ix(multisig, 1, maker, None, None, None, Some(destination));
+    bench.measure("execute_proposal/withdraw", ix, &[&p.maker])?;
+    Ok(())
+}
+
+/// A three-period subscription, collected once and then cancelled
+fn subscriptions(bench: &mut Bench, p: &Parties) -> Result<(), BenchError> {
+    const PERIOD_SECS: i64 = 60;
+    let (payer, payee) = (p.maker.pubkey(), p.other.pubkey());
+    let subscription = pda::subscription(&payer, 0);
+    let ix = escrow_ix(
+        accounts::CreateSubscription {
+            subscription,
+            payer,
+            config: pda::config(),
+            system_program: system_program::ID,
+            event_authority: pda::event_authority(),
+            program: escrow::ID,
+        },
+        instruction::CreateSubscription {
+            subscription_id: 0,
+            payee,
+            amount_per_period: 100_000,
+            period_secs: PERIOD_SECS,
+            periods: 3,
+        },
+    );
+    bench.measure("create_subscription/basic", ix, &[&p.maker])?;
+
+    bench.warp(PERIOD_SECS);
+    let ix = escrow_ix(
+        accounts::CollectPeriod {
+            subscription,
+            payee,
+            payer,
+            event_authority: pda::event_authority(),
+            program: escrow::ID,
+        },
+        instruction::CollectPeriod {},
+    );
+    bench.measure("collect_period/basic", ix, &[&p.other])?;
+
+    let ix = escrow_ix(
+        accounts::CancelSubscription {
+            subscription,
+            payer,
+            payee,
+            event_authority: pda::event_authority(),
+            program: escrow::ID,
+        },
+        instruction::CancelSubscription {},
+    );
+    bench.measure("cancel_subscription/basic", ix, &[&p.maker])?;
+    Ok(())
+}
+
+/// NFT escrows, without a collection check
+fn nft(bench: &mut Bench, p: &mut Parties) -> Result<(), BenchError> {
+    let (maker, taker) = (p.maker.pubkey(), p.taker.pubkey());
+    let (nft_mint, maker_nft_account) = mint_nft(bench, &maker)?;
+    let escrow = create_nft_escrow(
+        bench,
+        p,
+        Some("create_nft_escrow/plain"),
+        nft_mint,
+        maker_nft_account,
+    )?;
+    fund_escrow(bench, p, Some("fund_escrow/nft"), escrow)?;
+    let ix = escrow_ix(
+        accounts::CompleteNftSwap {
+            escrow,
+            escrow_index: pda::escrow_index(&taker, 0),
+            nft_mint,
+            vault: get_associated_token_address(&escrow, &nft_mint),
+            taker_nft_account: get_associated_token_address(&taker, &nft_mint),
+            taker,
+            maker,
+            rent_payer: maker,
+            memo_program: None,
+            maker_stats: pda::user_stats(&maker),
+            taker_stats: pda::user_stats(&taker),
+            token_program: spl_token::ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+            event_authority: pda::event_authority(),
+            program: escrow::ID,
+        },
+        instruction::CompleteNftSwap {},
+    );
+    bench.measure("complete_nft_swap/basic", ix, &[&p.taker])?;
+
+    let (nft_mint, maker_nft_account) = mint_nft(bench, &maker)?;
+    let escrow = create_nft_escrow(bench, p, None, nft_mint, maker_nft_account)?;
+    let ix = escrow_ix(
+        accounts::CancelNftEscrow {
+            escrow,
+            escrow_index: pda::escrow_index(&taker, 0),
+            nft_mint,
+            vault: get_associated_token_address(&escrow, &nft_mint),
+            maker_nft_account,
+            maker,
+            rent_payer: maker,
+            maker_stats: pda::user_stats(&maker),
+            token_program: spl_token::ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+            event_authority: pda::event_authority(),
+            program: escrow::ID,
+        },
+        instruction::CancelNftEscrow {},
+    );
+    bench.measure("cancel_nft_escrow/basic", ix, &[&p.maker])?;
+    Ok(())
+}
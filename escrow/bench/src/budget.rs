@@ -0,0 +1,190 @@
+//! Compute-unit budgets and the report checked against them
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::BenchError;
+
+/// Written above the budgets when `BENCH_UPDATE_BUDGETS=1` regenerates the file
+const BUDGETS_HEADER: &str = "\
+# Compute-unit budgets for `cargo bench`, one per `<instruction>/<mode>` scenario.
+# A run fails if any scenario consumes more than its budget; scenarios not listed
+# here fall back to `default`, which is kept tight so they can fail too.
+# `BENCH_UPDATE_BUDGETS=1 cargo bench` rewrites the table from a run, adding
+# `headroom_percent` to each measurement.
+";
+
+/// One scenario's compute units
+#[derive(Debug, Clone, Serialize)]
+pub struct Measurement {
+    /// `<instruction>/<mode>`, e.g. `fund_escrow/oracle`
+    pub scenario: String,
+    pub compute_units: u64,
+}
+
+/// # Compute-Unit Budgets
+///
+/// The contents of `budgets.toml`.
+#[derive(Debug, Deserialize)]
+pub struct Budgets {
+    /// Budget for scenarios without their own entry
+    pub default: u64,
+
+    /// Added to each measurement when the budgets are regenerated
+    #[serde(default)]
+    pub headroom_percent: u64,
+
+    /// Budget per scenario
+    #[serde(default)]
+    pub budgets: BTreeMap<String, u64>,
+}
+
+impl Budgets {
+    pub fn load(path: &Path) -> Result<Self, BenchError> {
+        Ok(toml::from_str(
+            &fs::read_to_string(path).map_err(BenchError::io(path))?,
+        )?)
+    }
+
+    pub fn for_scenario(&self, scenario: &str) -> u64 {
+        self.budgets.get(scenario).copied().unwrap_or(self.default)
+    }
+
+    /// Rewrites `path` with a budget per measurement, `headroom_percent` above it
+    pub fn write_measured(
+        &self,
+        path: &Path,
+        measurements: &[Measurement],
+    ) -> Result<(), BenchError> {
+        let mut toml = format!(
+            "{BUDGETS_HEADER}\ndefault = {}\nheadroom_percent = {}\n\n[budgets]\n",
+            self.default, self.headroom_percent
+        );
+        let measured: BTreeMap<_, _> = measurements
+            .iter()
+            .map(|m| (&m.scenario, m.compute_units))
+            .collect();
+        for (scenario, compute_units) in measured {
+            let budget = compute_units + compute_units * self.headroom_percent / 100;
+            writeln!(toml, "\"{scenario}\" = {budget}").expect("writing to a String");
+        }
+        fs::write(path, toml).map_err(BenchError::io(path))
+    }
+}
+
+/// A scenario's measurement next to its budget
+#[derive(Debug, Serialize)]
+pub struct ReportEntry {
+    pub scenario: String,
+    pub instruction: String,
+    pub mode: String,
+    pub compute_units: u64,
+    pub budget: u64,
+    pub over_budget: bool,
+    /// No entry in `budgets.toml`, so held to `default`
+    pub unbudgeted: bool,
+}
+
+/// # Benchmark Report
+///
+/// Serialized to JSON for CI and fee tooling, with one entry per measured scenario:
+///
+/// ```text
+/// {
+///   "entries": [
+///     { "scenario": "create_escrow/fixed", "instruction": "create_escrow", "mode": "fixed",
+///       "compute_units": <measured>, "budget": <from budgets.toml>, "over_budget": false,
+///       "unbudgeted": false }
+///   ],
+///   "over_budget": [],
+///   "unbudgeted": []
+/// }
+/// ```
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub entries: Vec<ReportEntry>,
+
+    /// Scenarios that exceeded their budget
+    pub over_budget: Vec<String>,
+
+    /// Scenarios `budgets.toml` has no entry for yet
+    pub unbudgeted: Vec<String>,
+}
+
+impl Report {
+    pub fn new(measurements: &[Measurement], budgets: &Budgets) -> Self {
+        let entries: Vec<ReportEntry> = measurements
+            .iter()
+            .map(|m| {
+                let (instruction, mode) = m.scenario.split_once('/').unwrap_or((&m.scenario, ""));
+                let budget = budgets.for_scenario(&m.scenario);
+                ReportEntry {
+                    scenario: m.scenario.clone(),
+                    instruction: instruction.to_string(),
+                    mode: mode.to_string(),
+                    compute_units: m.compute_units,
+                    budget,
+                    over_budget: m.compute_units > budget,
+                    unbudgeted: !budgets.budgets.contains_key(&m.scenario),
+                }
+            })
+            .collect();
+        let over_budget = entries
+            .iter()
+            .filter(|entry| entry.over_budget)
+            .map(|entry| entry.scenario.clone())
+            .collect();
+        let unbudgeted = entries
+            .iter()
+            .filter(|entry| entry.unbudgeted)
+            .map(|entry| entry.scenario.clone())
+            .collect();
+        Self {
+            entries,
+            over_budget,
+            unbudgeted,
+        }
+    }
+
+    pub fn within_budget(&self) -> bool {
+        self.over_budget.is_empty()
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), BenchError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(BenchError::io(dir))?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?).map_err(BenchError::io(path))
+    }
+
+    /// A table of every scenario, flagging those over budget or held to `default`
+    pub fn table(&self) -> String {
+        let width = self
+            .entries
+            .iter()
+            .map(|entry| entry.scenario.len())
+            .max()
+            .unwrap_or(0);
+        let mut table = format!("{:width$}  {:>9}  {:>9}\n", "scenario", "CU", "budget");
+        for entry in &self.entries {
+            let flag = if entry.over_budget {
+                "  OVER BUDGET"
+            } else if entry.unbudgeted {
+                "  (default)"
+            } else {
+                ""
+            };
+            writeln!(
+                table,
+                "{:width$}  {:>9}  {:>9}{flag}",
+                entry.scenario, entry.compute_units, entry.budget
+            )
+            .expect("writing to a String");
+        }
+        table
+    }
+}
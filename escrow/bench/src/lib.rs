@@ -0,0 +1,302 @@
+//! # Escrow Compute-Unit Benchmarks
+//!
+//! Runs the escrow program's instructions on an **in-process SVM** ([LiteSVM]) and records
+//! the compute units each one consumes, so priority fees can be budgeted from measurements.
+//!
+//! ## How It Works
+//!
+//! 1. **Load the program**: The SBF build from `anchor build`, deployed as upgradeable with
+//!    [`Bench::upgrade_authority`] as its authority, so `initialize_config` can run
+//! 2. **Run scenarios**: `benches/compute_units.rs` drives every instruction in each escrow
+//!    mode, naming each measurement `<instruction>/<mode>`
+//! 3. **Report**: Writes a JSON [`Report`] and checks each measurement against `budgets.toml`
+//!
+//! ## Running
+//!
+//! ```text
+//! anchor build
+//! cd bench && cargo bench
+//! ```
+//!
+//! The run fails if any measurement exceeds its budget, and warns about scenarios held to
+//! the `default` budget for want of their own. With `BENCH_UPDATE_BUDGETS=1` it rewrites
+//! `budgets.toml` from the measurements instead. `ESCROW_SO` and `CU_REPORT`
+//! override where the program is read from and the report is written to.
+//!
+//! The crate is excluded from the workspace, so `cargo test --workspace` doesn't need an
+//! SBF build.
+//!
+//! [LiteSVM]: https://github.com/LiteSVM/litesvm
+
+pub mod budget;
+pub mod pda;
+
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use anchor_lang::prelude::{Clock, Pubkey};
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use base64::Engine;
+use litesvm::types::{FailedTransactionMetadata, TransactionMetadata};
+use litesvm::LiteSVM;
+use solana_loader_v3_interface::state::UpgradeableLoaderState;
+use solana_sdk::account::Account;
+use solana_sdk::signature::Keypair;
+use solana_sdk::signer::Signer;
+use solana_sdk::transaction::Transaction;
+use solana_sdk_ids::bpf_loader_upgradeable;
+
+pub use budget::{Budgets, Measurement, Report};
+
+/// Unix time the SVM's clock starts at
+pub const START_TS: i64 = 1_700_000_000;
+
+/// Lamports every wallet from [`Bench::wallet`] starts with
+pub const WALLET_LAMPORTS: u64 = 100_000_000_000;
+
+/// # Errors the Benchmarks Can Return
+#[derive(Debug, thiserror::Error)]
+pub enum BenchError {
+    /// A file couldn't be read or written
+    #[error("{path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+
+    /// `budgets.toml` wasn't valid
+    #[error("invalid budgets file: {0}")]
+    Budgets(#[from] toml::de::Error),
+
+    /// An account fixture wasn't a `solana account --output json` dump
+    #[error("invalid account fixture {path}: {reason}")]
+    Fixture { path: String, reason: &'static str },
+
+    /// The SVM refused to store an account
+    #[error("failed to set account {pubkey}: {reason}")]
+    SetAccount { pubkey: Pubkey, reason: String },
+
+    /// A transaction failed, so the scenarios after it can't run
+    #[error("{name} failed: {err}\n{logs}")]
+    Transaction {
+        name: String,
+        err: String,
+        logs: String,
+    },
+
+    /// The report couldn't be serialized
+    #[error("failed to serialize report: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+impl BenchError {
+    /// Wraps an I/O error with the path it happened on
+    pub fn io(path: &Path) -> impl FnOnce(std::io::Error) -> Self + '_ {
+        move |source| Self::Io {
+            path: path.display().to_string(),
+            source,
+        }
+    }
+
+    fn transaction(name: &str, failed: FailedTransactionMetadata) -> Self {
+        Self::Transaction {
+            name: name.to_string(),
+            err: failed.err.to_string(),
+            logs: failed.meta.logs.join("\n"),
+        }
+    }
+}
+
+/// Builds an escrow program instruction from Anchor's generated client types
+pub fn escrow_ix(accounts: impl ToAccountMetas, args: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: escrow::ID,
+        accounts: accounts.to_account_metas(None),
+        data: args.data(),
+    }
+}
+
+/// # Benchmark Session
+///
+/// One SVM with the escrow program deployed, plus the measurements taken so far.
+/// Every transaction is paid for by a dedicated fee payer, so instruction signers only
+/// sign for what the instruction itself needs.
+pub struct Bench {
+    svm: LiteSVM,
+    payer: Keypair,
+    upgrade_authority: Keypair,
+    measurements: Vec<Measurement>,
+}
+
+impl Bench {
+    /// Deploys `program` (the escrow program's SBF build) and starts the clock at [`START_TS`]
+    pub fn new(program: &[u8]) -> Result<Self, BenchError> {
+        let mut bench = Self {
+            svm: LiteSVM::new(),
+            payer: Keypair::new(),
+            upgrade_authority: Keypair::new(),
+            measurements: Vec::new(),
+        };
+        bench.airdrop(&bench.payer.pubkey(), WALLET_LAMPORTS)?;
+        bench.airdrop(&bench.upgrade_authority.pubkey(), WALLET_LAMPORTS)?;
+        bench.deploy_upgradeable(program)?;
+
+        let mut clock: Clock = bench.svm.get_sysvar();
+        clock.unix_timestamp = START_TS;
+        bench.svm.set_sysvar(&clock);
+        Ok(bench)
+    }
+
+    /// Writes the program and program data accounts `solana program deploy` would create
+    fn deploy_upgradeable(&mut self, program: &[u8]) -> Result<(), BenchError> {
+        let program_data = pda::program_data();
+        let mut data = bincode::serialize(&UpgradeableLoaderState::ProgramData {
+            slot: 0,
+            upgrade_authority_address: Some(self.upgrade_authority.pubkey()),
+        })
+        .expect("loader state serializes");
+        data.resize(UpgradeableLoaderState::size_of_programdata_metadata(), 0);
+        data.extend_from_slice(program);
+        self.set_account(program_data, bpf_loader_upgradeable::id(), data, false)?;
+
+        let data = bincode::serialize(&UpgradeableLoaderState::Program {
+            programdata_address: program_data,
+        })
+        .expect("loader state serializes");
+        self.set_account(escrow::ID, bpf_loader_upgradeable::id(), data, true)
+    }
+
+    /// A copy of the program's upgrade authority, who can initialize the config
+    pub fn upgrade_authority(&self) -> Keypair {
+        self.upgrade_authority.insecure_clone()
+    }
+
+    /// Current Unix time on the SVM's clock
+    pub fn now(&self) -> i64 {
+        self.svm.get_sysvar::<Clock>().unix_timestamp
+    }
+
+    /// Moves the clock forward by `secs`
+    pub fn warp(&mut self, secs: i64) {
+        let mut clock: Clock = self.svm.get_sysvar();
+        clock.unix_timestamp += secs;
+        self.svm.set_sysvar(&clock);
+    }
+
+    /// A new wallet holding [`WALLET_LAMPORTS`]
+    pub fn wallet(&mut self) -> Result<Keypair, BenchError> {
+        let wallet = Keypair::new();
+        self.airdrop(&wallet.pubkey(), WALLET_LAMPORTS)?;
+        Ok(wallet)
+    }
+
+    pub fn airdrop(&mut self, to: &Pubkey, lamports: u64) -> Result<(), BenchError> {
+        self.svm
+            .airdrop(to, lamports)
+            .map(drop)
+            .map_err(|failed| BenchError::transaction("airdrop", failed))
+    }
+
+    /// Stores a rent-exempt account with `data`, as if some earlier transaction created it
+    pub fn set_account(
+        &mut self,
+        pubkey: Pubkey,
+        owner: Pubkey,
+        data: Vec<u8>,
+        executable: bool,
+    ) -> Result<(), BenchError> {
+        let account = Account {
+            lamports: self.svm.minimum_balance_for_rent_exemption(data.len()),
+            data,
+            owner,
+            executable,
+            rent_epoch: 0,
+        };
+        self.svm
+            .set_account(pubkey, account)
+            .map_err(|err| BenchError::SetAccount {
+                pubkey,
+                reason: format!("{err:?}"),
+            })
+    }
+
+    /// Loads an account from a `solana account --output json` dump, like the
+    /// `[[test.validator.account]]` fixtures in `Anchor.toml`, and returns its address
+    pub fn load_account_fixture(&mut self, path: &Path) -> Result<Pubkey, BenchError> {
+        let invalid = |reason| BenchError::Fixture {
+            path: path.display().to_string(),
+            reason,
+        };
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(path).map_err(BenchError::io(path))?)?;
+        let pubkey = json["pubkey"]
+            .as_str()
+            .and_then(|key| Pubkey::from_str(key).ok())
+            .ok_or_else(|| invalid("missing pubkey"))?;
+        let account = &json["account"];
+        let owner = account["owner"]
+            .as_str()
+            .and_then(|key| Pubkey::from_str(key).ok())
+            .ok_or_else(|| invalid("missing owner"))?;
+        let data = account["data"][0]
+            .as_str()
+            .and_then(|data| base64::engine::general_purpose::STANDARD.decode(data).ok())
+            .ok_or_else(|| invalid("data is not base64"))?;
+        self.set_account(pubkey, owner, data, false)?;
+        Ok(pubkey)
+    }
+
+    /// Runs `instruction` in a transaction of its own and records its compute units
+    /// under `scenario` (`<instruction>/<mode>`)
+    pub fn measure(
+        &mut self,
+        scenario: &str,
+        instruction: Instruction,
+        signers: &[&Keypair],
+    ) -> Result<u64, BenchError> {
+        let meta = self.send(scenario, &[instruction], signers)?;
+        self.measurements.push(Measurement {
+            scenario: scenario.to_string(),
+            compute_units: meta.compute_units_consumed,
+        });
+        Ok(meta.compute_units_consumed)
+    }
+
+    /// Runs setup `instructions` without recording them
+    pub fn execute(
+        &mut self,
+        name: &str,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), BenchError> {
+        self.send(name, instructions, signers).map(drop)
+    }
+
+    fn send(
+        &mut self,
+        name: &str,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<TransactionMetadata, BenchError> {
+        let payer = self.payer.pubkey();
+        let mut keypairs = vec![&self.payer];
+        keypairs.extend(signers.iter().filter(|signer| signer.pubkey() != payer));
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&payer),
+            &keypairs,
+            self.svm.latest_blockhash(),
+        );
+        let result = self.svm.send_transaction(transaction);
+        // Repeating an identical transaction would otherwise be rejected as already processed
+        self.svm.expire_blockhash();
+        result.map_err(|failed| BenchError::transaction(name, failed))
+    }
+
+    /// The measurements taken so far, in the order they ran
+    pub fn measurements(&self) -> &[Measurement] {
+        &self.measurements
+    }
+}
@@ -0,0 +1,64 @@
+//! Addresses of the escrow program's accounts, with the same seeds as the program
+
+use anchor_lang::prelude::Pubkey;
+use solana_loader_v3_interface::get_program_data_address;
+
+fn find(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &escrow::ID).0
+}
+
+/// The program's upgradeable loader data account
+pub fn program_data() -> Pubkey {
+    get_program_data_address(&escrow::ID)
+}
+
+/// `emit_cpi!`'s signer, which every `#[event_cpi]` instruction takes
+pub fn event_authority() -> Pubkey {
+    find(&[b"__event_authority"])
+}
+
+pub fn config() -> Pubkey {
+    find(&[b"config"])
+}
+
+pub fn maker_profile(maker: &Pubkey) -> Pubkey {
+    find(&[b"maker_profile", maker.as_ref()])
+}
+
+/// `creator` is the maker the escrow was created by (a multisig for multisig escrows)
+pub fn escrow(creator: &Pubkey, escrow_id: u64) -> Pubkey {
+    find(&[b"escrow", creator.as_ref(), &escrow_id.to_le_bytes()])
+}
+
+pub fn escrow_index(taker: &Pubkey, page: u32) -> Pubkey {
+    find(&[b"escrow_index", taker.as_ref(), &page.to_le_bytes()])
+}
+
+pub fn user_stats(wallet: &Pubkey) -> Pubkey {
+    find(&[b"user_stats", wallet.as_ref()])
+}
+
+pub fn bid(escrow: &Pubkey, bidder: &Pubkey) -> Pubkey {
+    find(&[b"bid", escrow.as_ref(), bidder.as_ref()])
+}
+
+/// `side` is `ReceiptSide as u8`
+pub fn receipt_mint(escrow: &Pubkey, side: u8) -> Pubkey {
+    find(&[b"receipt", escrow.as_ref(), &[side]])
+}
+
+pub fn multisig(create_key: &Pubkey) -> Pubkey {
+    find(&[b"multisig", create_key.as_ref()])
+}
+
+pub fn proposal(multisig: &Pubkey, index: u64) -> Pubkey {
+    find(&[b"proposal", multisig.as_ref(), &index.to_le_bytes()])
+}
+
+pub fn subscription(payer: &Pubkey, subscription_id: u64) -> Pubkey {
+    find(&[
+        b"subscription",
+        payer.as_ref(),
+        &subscription_id.to_le_bytes(),
+    ])
+}
@@ -0,0 +1,558 @@
+//! # Fixture Generator
+//!
+//! Writes `tests/fixtures/transactions.jsonl`: synthetic `getTransaction` results whose
+//! event self-CPIs are built from the program's own event types, so the fixture can't
+//! drift from what the program emits. They are not recorded from a cluster; signatures,
+//! keys and timestamps are made up.
+//!
+//! Regenerate after changing an event with:
+//!
+//! ```text
+//! cd indexer
+//! cargo run --example generate_fixture > tests/fixtures/transactions.jsonl
+//! ```
+
+use anchor_lang::event::EVENT_IX_TAG_LE;
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::Event;
+use escrow::{
+    AuctionKind, AuctionTerms, BidPlaced, EscrowAmended, EscrowCancelled, EscrowCompleted,
+    EscrowCreated, EscrowFunded, MakerRightsTransferred, OperatorChanged, OraclePrice, OracleTerms,
+    Paused, PeriodCollected, ReceiptMinted, ReceiptSide, SubscriptionCancelled,
+    SubscriptionCreated, TakerBond, TakerBondSlashed, TakerRightsTransferred, Unpaused,
+    EVENT_SEQ_BLOCK,
+};
+use serde_json::{json, Value};
+
+/// First sequence number of the `n`th escrow a maker creates
+fn block(n: u64) -> u64 {
+    n * EVENT_SEQ_BLOCK
+}
+
+/// Account index of the escrow program in every transaction's `accountKeys`
+const ESCROW_PROGRAM: usize = 2;
+/// Account index of the system program
+const SYSTEM_PROGRAM: usize = 4;
+/// Account index of an unrelated program
+const OTHER_PROGRAM: usize = 5;
+
+fn key(n: u8) -> Pubkey {
+    Pubkey::new_from_array([n; 32])
+}
+
+/// Inner instruction data of an `emit_cpi!` self-invocation carrying `event`
+fn cpi<E: Event>(event: &E) -> String {
+    let mut data = EVENT_IX_TAG_LE.to_vec();
+    data.extend(event.data());
+    bs58::encode(data).into_string()
+}
+
+/// An inner system transfer of 1000 lamports, which the indexer must skip
+fn transfer() -> (usize, String) {
+    let data = [2u8, 0, 0, 0, 232, 3, 0, 0, 0, 0, 0, 0];
+    (SYSTEM_PROGRAM, bs58::encode(data).into_string())
+}
+
+/// A plain fixed-price `EscrowCreated` expiring an hour after `ts`
+fn created(
+    seq: u64,
+    escrow: Pubkey,
+    maker: Pubkey,
+    taker: Pubkey,
+    escrow_id: u64,
+    (amount_a, amount_b_expected): (u64, u64),
+    ts: i64,
+) -> EscrowCreated {
+    EscrowCreated {
+        seq,
+        escrow,
+        maker,
+        taker,
+        escrow_id,
+        amount_a,
+        amount_b_expected,
+        expiry_ts: ts + 3600,
+        keeper_tip: 0,
+        nft_mint: None,
+        oracle: None,
+        dutch_auction: None,
+        auction: None,
+        attester: None,
+        taker_allowlist: None,
+        metadata: None,
+        rent_payer: maker,
+        taker_bond: None,
+        cancel_penalty_bps: 0,
+        min_taker_completed: 0,
+        locked_lamports: amount_a,
+        ts,
+    }
+}
+
+/// The escrow program's `emit_cpi!` self-invocation carrying `event`
+fn event<E: Event>(event: &E) -> (usize, String) {
+    (ESCROW_PROGRAM, cpi(event))
+}
+
+/// `event`'s bytes sent to an unrelated program, which the indexer must not trust
+fn spoofed<E: Event>(event: &E) -> (usize, String) {
+    (OTHER_PROGRAM, cpi(event))
+}
+
+/// A transaction at `slot` signed by `signer`, whose only instruction (`outer`,
+/// arbitrary bytes) invoked the escrow program on `escrow`, with `inner` as its inner
+/// instructions
+///
+/// Account keys: 0 signer, 1 escrow, 2 escrow program, 3 event authority,
+/// 4 system program, 5 an unrelated program. Signatures are numbered from slot 10.
+fn transaction(
+    slot: u64,
+    signer: Pubkey,
+    escrow: Pubkey,
+    outer: &str,
+    inner: Vec<(usize, String)>,
+) -> Value {
+    let (event_authority, _) = Pubkey::find_program_address(&[b"__event_authority"], &escrow::ID);
+    let keys = vec![
+        signer.to_string(),
+        escrow.to_string(),
+        escrow::ID.to_string(),
+        event_authority.to_string(),
+        "11111111111111111111111111111111".to_string(),
+        key(5).to_string(),
+    ];
+    let inner: Vec<_> = inner
+        .into_iter()
+        .map(|(program, data)| {
+            let accounts = if program == ESCROW_PROGRAM {
+                vec![3]
+            } else {
+                vec![0, 1]
+            };
+            json!({"programIdIndex": program, "accounts": accounts, "data": data, "stackHeight": 2})
+        })
+        .collect();
+    let signature = [(slot - 9) as u8; 64];
+    json!({
+        "slot": slot,
+        "blockTime": 1_700_000_000 + slot as i64,
+        "meta": {
+            "err": Value::Null,
+            "fee": 5000,
+            "innerInstructions": [{"index": 0, "instructions": inner}],
+            "loadedAddresses": {"writable": [], "readonly": []},
+            "logMessages": [],
+        },
+        "transaction": {
+            "message": {
+                "accountKeys": keys,
+                "header": {
+                    "numRequiredSignatures": 1,
+                    "numReadonlySignedAccounts": 0,
+                    "numReadonlyUnsignedAccounts": 4,
+                },
+                "instructions": [{
+                    "programIdIndex": ESCROW_PROGRAM,
+                    "accounts": [1, 0, 4, 3, 2],
+                    "data": bs58::encode(outer.as_bytes()).into_string(),
+                }],
+                "recentBlockhash": bs58::encode([9u8; 32]).into_string(),
+            },
+            "signatures": [bs58::encode(signature).into_string()],
+        },
+        "version": "legacy",
+    })
+}
+
+/// Prints a successful [`transaction`]
+fn tx(slot: u64, signer: Pubkey, escrow: Pubkey, outer: &str, inner: Vec<(usize, String)>) {
+    println!("{}", transaction(slot, signer, escrow, outer, inner));
+}
+
+/// Prints a [`transaction`] whose instruction failed, so none of its events happened
+fn failed_tx(slot: u64, signer: Pubkey, escrow: Pubkey, outer: &str, inner: Vec<(usize, String)>) {
+    let mut transaction = transaction(slot, signer, escrow, outer, inner);
+    transaction["meta"]["err"] = json!({"InstructionError": [0, {"Custom": 6002}]});
+    println!("{transaction}");
+}
+
+fn main() {
+    let (m1, m2, t1, t2) = (key(1), key(2), key(3), key(4));
+    let (e1, e2, e3) = (key(11), key(12), key(13));
+
+    // Three fixed-price escrows: the first completes with a referral fee, the third is cancelled
+    let create1 = created(block(0), e1, m1, t1, 1, (1000, 500), 1_700_000_000);
+    let create2 = created(block(1), e2, m1, t1, 2, (2000, 800), 1_700_000_010);
+    let create3 = created(block(0), e3, m2, t2, 1, (300, 100), 1_700_000_020);
+    tx(10, m1, e1, "create1", vec![transfer(), event(&create1)]);
+    tx(11, m1, e2, "create2", vec![transfer(), event(&create2)]);
+    tx(12, m2, e3, "create3", vec![transfer(), event(&create3)]);
+    let fund1 = EscrowFunded {
+        seq: 1,
+        escrow: e1,
+        taker: t1,
+        amount_a: 1000,
+        amount_b: 500,
+        oracle_price: None,
+        taker_bond: 0,
+        settle_by_ts: None,
+        locked_lamports: 1500,
+        ts: 1_700_000_100,
+    };
+    tx(13, t1, e1, "fund1", vec![transfer(), event(&fund1)]);
+    let complete1 = EscrowCompleted {
+        seq: 2,
+        escrow: e1,
+        maker: m1,
+        taker: t1,
+        amount_a: 1000,
+        amount_b_expected: 500,
+        keeper_tip: 0,
+        attester: None,
+        referrer: Some(key(8)),
+        referral_fee: 10,
+        locked_lamports: 0,
+        ts: 1_700_000_200,
+    };
+    tx(14, t1, e1, "complete1", vec![event(&complete1)]);
+    let cancel3 = EscrowCancelled {
+        seq: 1,
+        escrow: e3,
+        maker: m2,
+        amount_a: 300,
+        amount_b_expected: 100,
+        keeper_tip: 0,
+        penalty: 0,
+        locked_lamports: 0,
+        ts: 1_700_000_300,
+    };
+    tx(15, m2, e3, "cancel3", vec![event(&cancel3)]);
+
+    // A failed transaction's event, and escrow-shaped data from another program, are both ignored
+    let fund2 = EscrowFunded {
+        seq: block(1) + 1,
+        escrow: e2,
+        taker: t1,
+        amount_a: 2000,
+        amount_b: 800,
+        oracle_price: None,
+        taker_bond: 0,
+        settle_by_ts: None,
+        locked_lamports: 2800,
+        ts: 1_700_000_400,
+    };
+    failed_tx(16, t1, e2, "fund2", vec![transfer(), event(&fund2)]);
+    let spoof2 = EscrowFunded {
+        ts: 1_700_000_500,
+        ..fund2
+    };
+    tx(17, t1, e2, "spoof2", vec![spoofed(&spoof2)]);
+
+    // NFT-for-SOL escrow: amount_a is one token, not lamports
+    let e4 = key(14);
+    let createnft4 = EscrowCreated {
+        expiry_ts: 1_700_003_600,
+        nft_mint: Some(key(20)),
+        locked_lamports: 0,
+        ..created(block(1), e4, m2, t2, 2, (1, 5000), 1_700_000_600)
+    };
+    tx(18, m2, e4, "createnft4", vec![event(&createnft4)]);
+    let fund4 = EscrowFunded {
+        seq: block(1) + 1,
+        escrow: e4,
+        taker: t2,
+        amount_a: 1,
+        amount_b: 5000,
+        oracle_price: None,
+        taker_bond: 0,
+        settle_by_ts: None,
+        locked_lamports: 5000,
+        ts: 1_700_000_700,
+    };
+    tx(19, t2, e4, "fund4", vec![transfer(), event(&fund4)]);
+    let completenft4 = EscrowCompleted {
+        seq: block(1) + 2,
+        escrow: e4,
+        maker: m2,
+        taker: t2,
+        amount_a: 1,
+        amount_b_expected: 5000,
+        keeper_tip: 0,
+        attester: None,
+        referrer: None,
+        referral_fee: 0,
+        locked_lamports: 0,
+        ts: 1_700_000_800,
+    };
+    tx(20, t2, e4, "completenft4", vec![event(&completenft4)]);
+
+    // Oracle-priced escrow: the payment is only known once funded
+    let (e5, t3) = (key(15), key(6));
+    let terms = OracleTerms {
+        price_feed: key(21),
+        min_price: 40_000_000,
+        max_price: 60_000_000,
+        max_price_age: 60,
+        max_confidence_bps: 100,
+    };
+    let createoracle5 = EscrowCreated {
+        oracle: Some(terms),
+        ..created(block(2), e5, m1, t3, 3, (1500, 0), 1_700_001_000)
+    };
+    tx(
+        21,
+        m1,
+        e5,
+        "createoracle5",
+        vec![transfer(), event(&createoracle5)],
+    );
+    let fundoracle5 = EscrowFunded {
+        seq: block(2) + 1,
+        escrow: e5,
+        taker: t3,
+        amount_a: 1500,
+        amount_b: 750,
+        oracle_price: Some(OraclePrice {
+            price: 50_000_000,
+            conf: 10_000,
+            expo: -8,
+            publish_time: 1_700_001_090,
+        }),
+        taker_bond: 0,
+        settle_by_ts: None,
+        locked_lamports: 2250,
+        ts: 1_700_001_100,
+    };
+    tx(
+        22,
+        t3,
+        e5,
+        "fundoracle5",
+        vec![transfer(), event(&fundoracle5)],
+    );
+
+    // The maker raises the price of the still-open escrow 2 and extends it
+    let amend2 = EscrowAmended {
+        seq: block(1) + 1,
+        escrow: e2,
+        maker: m1,
+        amount_b_expected: 900,
+        expiry_ts: 1_700_007_210,
+        terms_version: 1,
+        locked_lamports: 2000,
+        ts: 1_700_001_200,
+    };
+    tx(23, m1, e2, "amend2", vec![event(&amend2)]);
+
+    // The maker hands escrow 2 to a bot that may cancel and amend it
+    let operator2 = OperatorChanged {
+        seq: block(1) + 2,
+        escrow: e2,
+        maker: m1,
+        operator: Some(key(7)),
+        permissions: 3,
+        locked_lamports: 2000,
+        ts: 1_700_001_300,
+    };
+    tx(24, m1, e2, "operator2", vec![event(&operator2)]);
+
+    // The admin pauses deposits for a while; program-wide events are keyed and numbered by
+    // the config account
+    let (config, admin) = (key(30), key(31));
+    let pause = Paused {
+        seq: 0,
+        config,
+        admin,
+        ts: 1_700_001_400,
+    };
+    let unpause = Unpaused {
+        seq: 1,
+        config,
+        admin,
+        ts: 1_700_001_500,
+    };
+    tx(25, admin, config, "pause", vec![event(&pause)]);
+    tx(26, admin, config, "unpause", vec![event(&unpause)]);
+
+    // The funded oracle escrow changes hands on both sides
+    let transfermaker5 = MakerRightsTransferred {
+        seq: block(2) + 2,
+        escrow: e5,
+        old_maker: m1,
+        new_maker: m2,
+        counterparty_consent: false,
+        locked_lamports: 2250,
+        ts: 1_700_001_600,
+    };
+    tx(27, m1, e5, "transfermaker5", vec![event(&transfermaker5)]);
+    let transfertaker5 = TakerRightsTransferred {
+        seq: block(2) + 3,
+        escrow: e5,
+        old_taker: t3,
+        new_taker: t2,
+        counterparty_consent: true,
+        locked_lamports: 2250,
+        ts: 1_700_001_700,
+    };
+    tx(28, t3, e5, "transfertaker5", vec![event(&transfertaker5)]);
+
+    // The maker of escrow 2 tokenizes their payout
+    let receipt2 = ReceiptMinted {
+        seq: block(1) + 3,
+        escrow: e2,
+        side: ReceiptSide::Maker,
+        mint: key(22),
+        owner: m1,
+        locked_lamports: 2000,
+        ts: 1_700_001_800,
+    };
+    tx(29, m1, e2, "receipt2", vec![event(&receipt2)]);
+
+    // Subscription events use the subscription PDA in place of an escrow, numbering from 0
+    let sub = key(23);
+    let subscribe = SubscriptionCreated {
+        seq: 0,
+        subscription: sub,
+        payer: m1,
+        payee: t1,
+        subscription_id: 0,
+        amount_per_period: 100,
+        period_secs: 86_400,
+        periods: 3,
+        locked_lamports: 300,
+        ts: 1_700_001_900,
+    };
+    tx(
+        30,
+        m1,
+        sub,
+        "subscribe",
+        vec![transfer(), event(&subscribe)],
+    );
+    let collect = PeriodCollected {
+        seq: 1,
+        subscription: sub,
+        payee: t1,
+        period: 0,
+        amount: 100,
+        locked_lamports: 200,
+        ts: 1_700_088_300,
+    };
+    tx(31, t1, sub, "collect", vec![event(&collect)]);
+    let unsubscribe = SubscriptionCancelled {
+        seq: 2,
+        subscription: sub,
+        payer: m1,
+        paid_to_payee: 0,
+        refunded: 200,
+        locked_lamports: 0,
+        ts: 1_700_088_400,
+    };
+    tx(32, m1, sub, "unsubscribe", vec![event(&unsubscribe)]);
+
+    // English auction of 400 lamports: the second bid refunds the first, then settles
+    let (e6, listing, b1, b2) = (key(24), key(25), key(26), key(27));
+    let auction = AuctionTerms {
+        kind: AuctionKind::English,
+        reserve_price: 500,
+        min_increment: 50,
+        reveal_secs: 0,
+    };
+    let createauction6 = EscrowCreated {
+        expiry_ts: 1_700_092_000,
+        auction: Some(auction),
+        ..created(block(2), e6, m2, listing, 4, (400, 500), 1_700_088_500)
+    };
+    tx(
+        33,
+        m2,
+        e6,
+        "createauction6",
+        vec![transfer(), event(&createauction6)],
+    );
+    let bid6a = BidPlaced {
+        seq: block(2) + 1,
+        escrow: e6,
+        bidder: b1,
+        amount: 500,
+        sealed: false,
+        refunded: None,
+        locked_lamports: 400,
+        ts: 1_700_088_600,
+    };
+    let bid6b = BidPlaced {
+        seq: block(2) + 2,
+        bidder: b2,
+        amount: 600,
+        refunded: Some(b1),
+        ts: 1_700_088_700,
+        ..bid6a
+    };
+    tx(34, b1, e6, "bid6a", vec![transfer(), event(&bid6a)]);
+    tx(35, b2, e6, "bid6b", vec![transfer(), event(&bid6b)]);
+    let settle6 = EscrowCompleted {
+        seq: block(2) + 3,
+        escrow: e6,
+        maker: m2,
+        taker: b2,
+        amount_a: 400,
+        amount_b_expected: 600,
+        keeper_tip: 0,
+        attester: None,
+        referrer: None,
+        referral_fee: 0,
+        locked_lamports: 0,
+        ts: 1_700_092_100,
+    };
+    tx(36, b1, e6, "settle6", vec![event(&settle6)]);
+
+    // Bonded escrow whose taker never completes: the maker slashes the bond
+    let e7 = key(28);
+    let bond = TakerBond {
+        amount: 200,
+        settle_secs: 600,
+    };
+    let createbonded7 = EscrowCreated {
+        expiry_ts: 1_700_096_000,
+        taker_bond: Some(bond),
+        ..created(block(3), e7, m1, t1, 4, (700, 300), 1_700_092_200)
+    };
+    tx(
+        37,
+        m1,
+        e7,
+        "createbonded7",
+        vec![transfer(), event(&createbonded7)],
+    );
+    let fundbonded7 = EscrowFunded {
+        seq: block(3) + 1,
+        escrow: e7,
+        taker: t1,
+        amount_a: 700,
+        amount_b: 300,
+        oracle_price: None,
+        taker_bond: 200,
+        settle_by_ts: Some(1_700_092_900),
+        locked_lamports: 1200,
+        ts: 1_700_092_300,
+    };
+    tx(
+        38,
+        t1,
+        e7,
+        "fundbonded7",
+        vec![transfer(), event(&fundbonded7)],
+    );
+    let slash7 = TakerBondSlashed {
+        seq: block(3) + 2,
+        escrow: e7,
+        maker: m1,
+        taker: t1,
+        amount_a: 700,
+        amount_b_expected: 300,
+        bond: 200,
+        locked_lamports: 0,
+        ts: 1_700_093_000,
+    };
+    tx(39, m1, e7, "slash7", vec![event(&slash7)]);
+}
@@ -0,0 +1,301 @@
+//! # Event Decoding
+//!
+//! The program emits events with `emit_cpi!`: a self-invocation whose instruction data
+//! is Anchor's 8-byte event tag, the event's 8-byte discriminator, then the Borsh-serialized
+//! event. The event types are reused straight from the program crate so the two can never
+//! drift apart.
+
+use anchor_lang::event::EVENT_IX_TAG_LE;
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AnchorDeserialize, Discriminator, Event};
+use escrow::{
+    BidPlaced, BidRevealed, BidWithdrawn, EscrowAmended, EscrowCancelled, EscrowCompleted,
+    EscrowCreated, EscrowFunded, EscrowRefunded, KeeperCranked, MakerRightsTransferred,
+    OperatorChanged, Paused, PeriodCollected, ReceiptMinted, ReceiptRedeemed,
+    SubscriptionCancelled, SubscriptionCreated, TakerBondSlashed, TakerRightsTransferred, Unpaused,
+};
+
+use crate::{IndexerError, Result};
+
+/// Every event the escrow program emits
+pub enum EscrowEvent {
+    /// Boxed: with its optional terms it's several times larger than any other event
+    Created(Box<EscrowCreated>),
+    Funded(EscrowFunded),
+    Completed(EscrowCompleted),
+    Cancelled(EscrowCancelled),
+    Refunded(EscrowRefunded),
+    Amended(EscrowAmended),
+    OperatorChanged(OperatorChanged),
+    MakerTransferred(MakerRightsTransferred),
+    TakerTransferred(TakerRightsTransferred),
+    ReceiptMinted(ReceiptMinted),
+    ReceiptRedeemed(ReceiptRedeemed),
+    KeeperCranked(KeeperCranked),
+    BondSlashed(TakerBondSlashed),
+    BidPlaced(BidPlaced),
+    BidRevealed(BidRevealed),
+    BidWithdrawn(BidWithdrawn),
+    SubscriptionCreated(SubscriptionCreated),
+    PeriodCollected(PeriodCollected),
+    SubscriptionCancelled(SubscriptionCancelled),
+    Paused(Paused),
+    Unpaused(Unpaused),
+}
+
+impl EscrowEvent {
+    /// Decodes the data of a self-CPI instruction sent to the escrow program
+    ///
+    /// Returns `Ok(None)` for instructions that aren't escrow events, so ordinary
+    /// instructions or newer event kinds can be skipped rather than failing.
+    pub fn from_cpi_data(data: &[u8]) -> Result<Option<Self>> {
+        match data.strip_prefix(EVENT_IX_TAG_LE) {
+            Some(event) => Self::decode(event),
+            None => Ok(None),
+        }
+    }
+
+    /// Decodes raw event bytes (discriminator followed by the Borsh body)
+    pub fn decode(data: &[u8]) -> Result<Option<Self>> {
+        if let Some(event) = decode_as::<EscrowCreated>(data, "EscrowCreated")? {
+            return Ok(Some(Self::Created(Box::new(event))));
+        }
+        if let Some(event) = decode_as::<EscrowFunded>(data, "EscrowFunded")? {
+            return Ok(Some(Self::Funded(event)));
+        }
+        if let Some(event) = decode_as::<EscrowCompleted>(data, "EscrowCompleted")? {
+            return Ok(Some(Self::Completed(event)));
+        }
+        if let Some(event) = decode_as::<EscrowCancelled>(data, "EscrowCancelled")? {
+            return Ok(Some(Self::Cancelled(event)));
+        }
+        if let Some(event) = decode_as::<EscrowRefunded>(data, "EscrowRefunded")? {
+            return Ok(Some(Self::Refunded(event)));
+        }
+        if let Some(event) = decode_as::<EscrowAmended>(data, "EscrowAmended")? {
+            return Ok(Some(Self::Amended(event)));
+        }
+        if let Some(event) = decode_as::<OperatorChanged>(data, "OperatorChanged")? {
+            return Ok(Some(Self::OperatorChanged(event)));
+        }
+        if let Some(event) = decode_as::<MakerRightsTransferred>(data, "MakerRightsTransferred")? {
+            return Ok(Some(Self::MakerTransferred(event)));
+        }
+        if let Some(event) = decode_as::<TakerRightsTransferred>(data, "TakerRightsTransferred")? {
+            return Ok(Some(Self::TakerTransferred(event)));
+        }
+        if let Some(event) = decode_as::<ReceiptMinted>(data, "ReceiptMinted")? {
+            return Ok(Some(Self::ReceiptMinted(event)));
+        }
+        if let Some(event) = decode_as::<ReceiptRedeemed>(data, "ReceiptRedeemed")? {
+            return Ok(Some(Self::ReceiptRedeemed(event)));
+        }
+        if let Some(event) = decode_as::<KeeperCranked>(data, "KeeperCranked")? {
+            return Ok(Some(Self::KeeperCranked(event)));
+        }
+        if let Some(event) = decode_as::<TakerBondSlashed>(data, "TakerBondSlashed")? {
+            return Ok(Some(Self::BondSlashed(event)));
+        }
+        if let Some(event) = decode_as::<BidPlaced>(data, "BidPlaced")? {
+            return Ok(Some(Self::BidPlaced(event)));
+        }
+        if let Some(event) = decode_as::<BidRevealed>(data, "BidRevealed")? {
+            return Ok(Some(Self::BidRevealed(event)));
+        }
+        if let Some(event) = decode_as::<BidWithdrawn>(data, "BidWithdrawn")? {
+            return Ok(Some(Self::BidWithdrawn(event)));
+        }
+        if let Some(event) = decode_as::<SubscriptionCreated>(data, "SubscriptionCreated")? {
+            return Ok(Some(Self::SubscriptionCreated(event)));
+        }
+        if let Some(event) = decode_as::<PeriodCollected>(data, "PeriodCollected")? {
+            return Ok(Some(Self::PeriodCollected(event)));
+        }
+        if let Some(event) = decode_as::<SubscriptionCancelled>(data, "SubscriptionCancelled")? {
+            return Ok(Some(Self::SubscriptionCancelled(event)));
+        }
+        if let Some(event) = decode_as::<Paused>(data, "Paused")? {
+            return Ok(Some(Self::Paused(event)));
+        }
+        if let Some(event) = decode_as::<Unpaused>(data, "Unpaused")? {
+            return Ok(Some(Self::Unpaused(event)));
+        }
+        Ok(None)
+    }
+
+    /// Raw event bytes (discriminator followed by the Borsh body), as [`Self::decode`] reads them
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            Self::Created(event) => event.data(),
+            Self::Funded(event) => event.data(),
+            Self::Completed(event) => event.data(),
+            Self::Cancelled(event) => event.data(),
+            Self::Refunded(event) => event.data(),
+            Self::Amended(event) => event.data(),
+            Self::OperatorChanged(event) => event.data(),
+            Self::MakerTransferred(event) => event.data(),
+            Self::TakerTransferred(event) => event.data(),
+            Self::ReceiptMinted(event) => event.data(),
+            Self::ReceiptRedeemed(event) => event.data(),
+            Self::KeeperCranked(event) => event.data(),
+            Self::BondSlashed(event) => event.data(),
+            Self::BidPlaced(event) => event.data(),
+            Self::BidRevealed(event) => event.data(),
+            Self::BidWithdrawn(event) => event.data(),
+            Self::SubscriptionCreated(event) => event.data(),
+            Self::PeriodCollected(event) => event.data(),
+            Self::SubscriptionCancelled(event) => event.data(),
+            Self::Paused(event) => event.data(),
+            Self::Unpaused(event) => event.data(),
+        }
+    }
+
+    /// Sequence number counted by the escrow, subscription or config the event is about
+    pub fn seq(&self) -> u64 {
+        match self {
+            Self::Created(event) => event.seq,
+            Self::Funded(event) => event.seq,
+            Self::Completed(event) => event.seq,
+            Self::Cancelled(event) => event.seq,
+            Self::Refunded(event) => event.seq,
+            Self::Amended(event) => event.seq,
+            Self::OperatorChanged(event) => event.seq,
+            Self::MakerTransferred(event) => event.seq,
+            Self::TakerTransferred(event) => event.seq,
+            Self::ReceiptMinted(event) => event.seq,
+            Self::ReceiptRedeemed(event) => event.seq,
+            Self::KeeperCranked(event) => event.seq,
+            Self::BondSlashed(event) => event.seq,
+            Self::BidPlaced(event) => event.seq,
+            Self::BidRevealed(event) => event.seq,
+            Self::BidWithdrawn(event) => event.seq,
+            Self::SubscriptionCreated(event) => event.seq,
+            Self::PeriodCollected(event) => event.seq,
+            Self::SubscriptionCancelled(event) => event.seq,
+            Self::Paused(event) => event.seq,
+            Self::Unpaused(event) => event.seq,
+        }
+    }
+
+    /// Lamports still held for the trade after the event's transition
+    pub fn locked_lamports(&self) -> u64 {
+        match self {
+            Self::Created(event) => event.locked_lamports,
+            Self::Funded(event) => event.locked_lamports,
+            Self::Completed(event) => event.locked_lamports,
+            Self::Cancelled(event) => event.locked_lamports,
+            Self::Refunded(event) => event.locked_lamports,
+            Self::Amended(event) => event.locked_lamports,
+            Self::OperatorChanged(event) => event.locked_lamports,
+            Self::MakerTransferred(event) => event.locked_lamports,
+            Self::TakerTransferred(event) => event.locked_lamports,
+            Self::ReceiptMinted(event) => event.locked_lamports,
+            Self::ReceiptRedeemed(event) => event.locked_lamports,
+            Self::KeeperCranked(event) => event.locked_lamports,
+            Self::BondSlashed(event) => event.locked_lamports,
+            Self::BidPlaced(event) => event.locked_lamports,
+            Self::BidRevealed(event) => event.locked_lamports,
+            Self::BidWithdrawn(event) => event.locked_lamports,
+            Self::SubscriptionCreated(event) => event.locked_lamports,
+            Self::PeriodCollected(event) => event.locked_lamports,
+            Self::SubscriptionCancelled(event) => event.locked_lamports,
+            // Program-wide events don't change what any escrow holds
+            Self::Paused(_) | Self::Unpaused(_) => 0,
+        }
+    }
+
+    /// The escrow account the event is about, the subscription account for subscription
+    /// events, or the `Config` account for program-wide events
+    pub fn escrow(&self) -> Pubkey {
+        match self {
+            Self::Created(event) => event.escrow,
+            Self::Funded(event) => event.escrow,
+            Self::Completed(event) => event.escrow,
+            Self::Cancelled(event) => event.escrow,
+            Self::Refunded(event) => event.escrow,
+            Self::Amended(event) => event.escrow,
+            Self::OperatorChanged(event) => event.escrow,
+            Self::MakerTransferred(event) => event.escrow,
+            Self::TakerTransferred(event) => event.escrow,
+            Self::ReceiptMinted(event) => event.escrow,
+            Self::ReceiptRedeemed(event) => event.escrow,
+            Self::KeeperCranked(event) => event.escrow,
+            Self::BondSlashed(event) => event.escrow,
+            Self::BidPlaced(event) => event.escrow,
+            Self::BidRevealed(event) => event.escrow,
+            Self::BidWithdrawn(event) => event.escrow,
+            Self::SubscriptionCreated(event) => event.subscription,
+            Self::PeriodCollected(event) => event.subscription,
+            Self::SubscriptionCancelled(event) => event.subscription,
+            Self::Paused(event) => event.config,
+            Self::Unpaused(event) => event.config,
+        }
+    }
+
+    /// Short name stored in the `escrow_events` table
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Created(_) => "created",
+            Self::Funded(_) => "funded",
+            Self::Completed(_) => "completed",
+            Self::Cancelled(_) => "cancelled",
+            Self::Refunded(_) => "refunded",
+            Self::Amended(_) => "amended",
+            Self::OperatorChanged(_) => "operator",
+            Self::MakerTransferred(_) => "maker_transferred",
+            Self::TakerTransferred(_) => "taker_transferred",
+            Self::ReceiptMinted(_) => "receipt_minted",
+            Self::ReceiptRedeemed(_) => "receipt_redeemed",
+            Self::KeeperCranked(_) => "cranked",
+            Self::BondSlashed(_) => "bond_slashed",
+            Self::BidPlaced(_) => "bid_placed",
+            Self::BidRevealed(_) => "bid_revealed",
+            Self::BidWithdrawn(_) => "bid_withdrawn",
+            Self::SubscriptionCreated(_) => "subscription_created",
+            Self::PeriodCollected(_) => "period_collected",
+            Self::SubscriptionCancelled(_) => "subscription_cancelled",
+            Self::Paused(_) => "paused",
+            Self::Unpaused(_) => "unpaused",
+        }
+    }
+
+    /// On-chain timestamp the event was emitted at
+    pub fn ts(&self) -> i64 {
+        match self {
+            Self::Created(event) => event.ts,
+            Self::Funded(event) => event.ts,
+            Self::Completed(event) => event.ts,
+            Self::Cancelled(event) => event.ts,
+            Self::Refunded(event) => event.ts,
+            Self::Amended(event) => event.ts,
+            Self::OperatorChanged(event) => event.ts,
+            Self::MakerTransferred(event) => event.ts,
+            Self::TakerTransferred(event) => event.ts,
+            Self::ReceiptMinted(event) => event.ts,
+            Self::ReceiptRedeemed(event) => event.ts,
+            Self::KeeperCranked(event) => event.ts,
+            Self::BondSlashed(event) => event.ts,
+            Self::BidPlaced(event) => event.ts,
+            Self::BidRevealed(event) => event.ts,
+            Self::BidWithdrawn(event) => event.ts,
+            Self::SubscriptionCreated(event) => event.ts,
+            Self::PeriodCollected(event) => event.ts,
+            Self::SubscriptionCancelled(event) => event.ts,
+            Self::Paused(event) => event.ts,
+            Self::Unpaused(event) => event.ts,
+        }
+    }
+}
+
+/// Deserializes `data` as `T` if it starts with `T`'s discriminator
+fn decode_as<T: Discriminator + AnchorDeserialize>(
+    data: &[u8],
+    kind: &'static str,
+) -> Result<Option<T>> {
+    let Some(mut body) = data.strip_prefix(T::DISCRIMINATOR) else {
+        return Ok(None);
+    };
+    T::deserialize(&mut body)
+        .map(Some)
+        .map_err(|source| IndexerError::MalformedEvent { kind, source })
+}
@@ -0,0 +1,77 @@
+//! # Escrow Event Indexer
+//!
+//! Turns the events the escrow program emits into a queryable **SQLite history**,
+//! so frontends and analytics don't need `getProgramAccounts` scans.
+//!
+//! ## How It Works
+//!
+//! 1. **Read transactions**: Parse `getTransaction` results (JSON encoding)
+//! 2. **Decode events**: Pick out the escrow program's `emit_cpi!` self-invocations
+//! 3. **Materialize**: Apply each event to the `escrows` table and record it in `escrow_events`
+//! 4. **Query**: Answer questions like "open escrows for taker X" or "volume by maker"
+//!
+//! ## Feeding the Indexer
+//!
+//! - **From a file**: `getTransaction` results, one JSON object per line (the test
+//!   fixture is generated by `examples/generate_fixture.rs`)
+//! - **From a local validator**: Poll it over JSON-RPC with [`rpc::RpcClient`]
+//!
+//! Events are de-duplicated by transaction signature and position, so re-ingesting a
+//! transaction is a no-op. Every event also carries a sequence number counted per escrow
+//! (or subscription), which orders an escrow's history and makes gaps detectable.
+//! Out-of-order sources are handled by [`Store`]: late events never roll a row back, and
+//! events for escrows not yet created are staged until they are.
+
+pub mod events;
+pub mod rpc;
+pub mod store;
+pub mod transactions;
+
+pub use events::EscrowEvent;
+pub use store::{EscrowRow, EscrowStatus, MakerVolume, SequenceGap, Store};
+pub use transactions::{TransactionReader, TransactionRecord};
+
+/// # Errors the Indexer Can Return
+#[derive(Debug, thiserror::Error)]
+pub enum IndexerError {
+    /// Reading the transaction source failed
+    #[error("failed to read transactions: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// A transaction line was not valid JSON
+    #[error("invalid transaction JSON: {0}")]
+    Json(#[from] serde_json::Error),
+
+    /// A transaction was JSON but not shaped like a `getTransaction` result
+    #[error("malformed transaction: {reason}")]
+    MalformedTransaction { reason: &'static str },
+
+    /// An event had a known discriminator but a body that didn't deserialize
+    #[error("malformed {kind} event: {source}")]
+    MalformedEvent {
+        kind: &'static str,
+        source: std::io::Error,
+    },
+
+    /// The RPC node couldn't be reached
+    #[error("RPC request failed: {0}")]
+    Rpc(#[from] ureq::Error),
+
+    /// The RPC node answered with a JSON-RPC error
+    #[error("RPC error response: {0}")]
+    RpcResponse(String),
+
+    /// A lamport amount didn't fit in an SQLite integer
+    #[error("amount {0} does not fit in an SQLite integer")]
+    AmountOverflow(u64),
+
+    /// The database was built before events were numbered per escrow and can't be migrated
+    #[error("database predates per-escrow event numbering; delete it and re-ingest")]
+    OutdatedDatabase,
+
+    /// The database rejected a statement
+    #[error("database error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+}
+
+pub type Result<T> = std::result::Result<T, IndexerError>;
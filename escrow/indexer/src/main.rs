@@ -0,0 +1,113 @@
+//! # escrow-indexer CLI
+//!
+//! ```text
+//! escrow-indexer <db> ingest [<transactions.jsonl>|-]
+//! escrow-indexer <db> sync <rpc-url>
+//! escrow-indexer <db> open-for-taker <taker-pubkey>
+//! escrow-indexer <db> volume-by-maker
+//! escrow-indexer <db> gaps
+//! ```
+//!
+//! Catch up with a local validator with:
+//!
+//! ```text
+//! escrow-indexer escrow.db sync http://127.0.0.1:8899
+//! ```
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use anchor_lang::prelude::Pubkey;
+use escrow_indexer::rpc::RpcClient;
+use escrow_indexer::{Store, TransactionReader};
+
+const USAGE: &str = "usage:
+  escrow-indexer <db> ingest [<transactions.jsonl>|-]
+  escrow-indexer <db> sync <rpc-url>
+  escrow-indexer <db> open-for-taker <taker-pubkey>
+  escrow-indexer <db> volume-by-maker
+  escrow-indexer <db> gaps";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let [db, command, rest @ ..] = args else {
+        return Err(USAGE.into());
+    };
+    let mut store = Store::open(db)?;
+
+    match (command.as_str(), rest) {
+        ("ingest", []) => ingest(&mut store, BufReader::new(io::stdin())),
+        ("ingest", [path]) if path == "-" => ingest(&mut store, BufReader::new(io::stdin())),
+        ("ingest", [path]) => ingest(&mut store, BufReader::new(File::open(path)?)),
+        ("sync", [url]) => sync(&mut store, &RpcClient::new(url.as_str())),
+        ("open-for-taker", [taker]) => {
+            let taker = Pubkey::from_str(taker)?;
+            for row in store.open_escrows_for_taker(&taker)? {
+                println!(
+                    "{}\t{}\t{}\t{}\t{}\t{}",
+                    row.escrow,
+                    row.maker,
+                    row.status.as_str(),
+                    row.amount_a,
+                    row.amount_b_expected,
+                    row.expiry_ts
+                );
+            }
+            Ok(())
+        }
+        ("volume-by-maker", []) => {
+            for volume in store.volume_by_maker()? {
+                println!(
+                    "{}\t{}\t{}\t{}",
+                    volume.maker, volume.completed, volume.amount_a, volume.amount_b
+                );
+            }
+            Ok(())
+        }
+        ("gaps", []) => {
+            for gap in store.sequence_gaps()? {
+                println!("{}\t{}\t{}", gap.account, gap.first, gap.last);
+            }
+            Ok(())
+        }
+        _ => Err(USAGE.into()),
+    }
+}
+
+fn ingest(store: &mut Store, reader: impl BufRead) -> Result<(), Box<dyn std::error::Error>> {
+    let mut transactions = 0;
+    let mut events = 0;
+    for tx in TransactionReader::new(reader) {
+        events += store.ingest(&tx?)?;
+        transactions += 1;
+    }
+    eprintln!("ingested {events} new events from {transactions} transactions");
+    Ok(())
+}
+
+fn sync(store: &mut Store, rpc: &RpcClient) -> Result<(), Box<dyn std::error::Error>> {
+    let until = store.last_synced_signature()?;
+    let signatures = rpc.signatures_since(&escrow::ID, until.as_deref())?;
+    let mut events = 0;
+    for signature in &signatures {
+        events += store.ingest(&rpc.transaction(signature)?)?;
+        store.set_last_synced_signature(signature)?;
+    }
+    eprintln!(
+        "ingested {events} new events from {} transactions",
+        signatures.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,89 @@
+//! # RPC Source
+//!
+//! Pulls the program's transactions from a JSON-RPC node (typically a local
+//! `solana-test-validator`) so they can be fed to the store. Plain HTTP only;
+//! point it at `http://127.0.0.1:8899`.
+
+use anchor_lang::prelude::Pubkey;
+use serde_json::{json, Value};
+
+use crate::{IndexerError, Result, TransactionRecord};
+
+/// `getSignaturesForAddress` returns at most this many signatures per call
+const SIGNATURE_PAGE_LIMIT: usize = 1000;
+
+pub struct RpcClient {
+    url: String,
+}
+
+impl RpcClient {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self { url: url.into() }
+    }
+
+    /// Signatures touching `address` newer than `until`, oldest first
+    pub fn signatures_since(&self, address: &Pubkey, until: Option<&str>) -> Result<Vec<String>> {
+        let mut signatures = Vec::new();
+        let mut before: Option<String> = None;
+
+        loop {
+            let mut config = json!({ "limit": SIGNATURE_PAGE_LIMIT, "commitment": "confirmed" });
+            if let Some(until) = until {
+                config["until"] = json!(until);
+            }
+            if let Some(before) = &before {
+                config["before"] = json!(before);
+            }
+
+            let page = self.call(
+                "getSignaturesForAddress",
+                json!([address.to_string(), config]),
+            )?;
+            let page = page.as_array().cloned().unwrap_or_default();
+            let page_len = page.len();
+            for entry in page {
+                if let Some(signature) = entry["signature"].as_str() {
+                    signatures.push(signature.to_string());
+                }
+            }
+
+            if page_len < SIGNATURE_PAGE_LIMIT {
+                break;
+            }
+            before = signatures.last().cloned();
+        }
+
+        // The node returns newest first; events must be applied oldest first
+        signatures.reverse();
+        Ok(signatures)
+    }
+
+    /// Fetches one transaction by signature
+    pub fn transaction(&self, signature: &str) -> Result<TransactionRecord> {
+        let result = self.call(
+            "getTransaction",
+            json!([
+                signature,
+                {
+                    "encoding": "json",
+                    "commitment": "confirmed",
+                    "maxSupportedTransactionVersion": 0
+                }
+            ]),
+        )?;
+        TransactionRecord::from_json(&result)
+    }
+
+    fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let mut response: Value = ureq::post(&self.url)
+            .send_json(&request)?
+            .body_mut()
+            .read_json()?;
+
+        if let Some(error) = response.get("error") {
+            return Err(IndexerError::RpcResponse(error.to_string()));
+        }
+        Ok(response["result"].take())
+    }
+}
@@ -0,0 +1,581 @@
+//! # SQLite Store
+//!
+//! Two tables hold the indexed history:
+//!
+//! - **`escrows`**: One row per escrow address with its terms and current status
+//! - **`escrow_events`**: Every applied event, keyed by its transaction signature and
+//!   position within that transaction
+//!
+//! The event table doubles as the de-duplication log: an event is only applied to
+//! `escrows` the first time its key is seen. A small `sync_state` table remembers how
+//! far an RPC sync got.
+//!
+//! ## Sequence Numbers
+//!
+//! The program numbers events per account rather than globally, so unrelated escrows
+//! never contend for a shared counter. Each escrow counts up from the start of a block
+//! of `EVENT_SEQ_BLOCK` numbers its maker reserved for it, so an escrow re-created at a
+//! closed one's address always numbers above the old one. Subscriptions and the config
+//! count from 0.
+//!
+//! ## Out-of-Order Events
+//!
+//! Sources can deliver events out of order, e.g. a backfill after a live sync. Every
+//! row remembers the escrow's `last_seq` applied to it, and an event only changes a row it is
+//! newer than, so a late event never rolls an escrow back; it's kept in the history
+//! only. An event whose escrow hasn't been created yet is **staged** in
+//! `escrow_events` and applied, in sequence order, once its `EscrowCreated` arrives.
+
+use std::path::Path;
+
+use anchor_lang::prelude::Pubkey;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+use crate::{EscrowEvent, IndexerError, Result, TransactionRecord};
+
+use escrow::EVENT_SEQ_BLOCK;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS escrows (
+    escrow            TEXT PRIMARY KEY,
+    maker             TEXT NOT NULL,
+    taker             TEXT NOT NULL,
+    escrow_id         INTEGER NOT NULL,
+    amount_a          INTEGER NOT NULL,
+    amount_b_expected INTEGER NOT NULL,
+    referral_fee      INTEGER NOT NULL DEFAULT 0,
+    expiry_ts         INTEGER NOT NULL,
+    nft_mint          TEXT,
+    status            TEXT NOT NULL,
+    created_ts        INTEGER NOT NULL,
+    funded_ts         INTEGER,
+    closed_ts         INTEGER,
+    locked_lamports   INTEGER NOT NULL,
+    last_seq          INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS escrows_by_taker ON escrows (taker, status);
+CREATE INDEX IF NOT EXISTS escrows_by_maker ON escrows (maker, status);
+
+CREATE TABLE IF NOT EXISTS escrow_events (
+    signature TEXT NOT NULL,
+    position  INTEGER NOT NULL,
+    seq       INTEGER NOT NULL,
+    slot      INTEGER NOT NULL,
+    escrow    TEXT NOT NULL,
+    kind      TEXT NOT NULL,
+    ts        INTEGER NOT NULL,
+    staged    INTEGER NOT NULL DEFAULT 0,
+    data      BLOB,
+    PRIMARY KEY (signature, position)
+);
+CREATE INDEX IF NOT EXISTS escrow_events_by_escrow ON escrow_events (escrow, seq);
+
+CREATE TABLE IF NOT EXISTS sync_state (
+    key   TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+";
+
+const LAST_SIGNATURE_KEY: &str = "last_signature";
+
+/// Where an escrow is in its lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EscrowStatus {
+    /// Created, waiting for the taker to fund
+    Open,
+    /// Taker has deposited, waiting for completion
+    Funded,
+    Completed,
+    Cancelled,
+    Refunded,
+    /// Refunded by a keeper via `crank_expired`
+    Cranked,
+    /// Unwound via `slash_taker_bond` after the taker missed the settlement deadline
+    Slashed,
+}
+
+impl EscrowStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::Funded => "funded",
+            Self::Completed => "completed",
+            Self::Cancelled => "cancelled",
+            Self::Refunded => "refunded",
+            Self::Cranked => "cranked",
+            Self::Slashed => "slashed",
+        }
+    }
+
+    fn parse(status: &str) -> Option<Self> {
+        Some(match status {
+            "open" => Self::Open,
+            "funded" => Self::Funded,
+            "completed" => Self::Completed,
+            "cancelled" => Self::Cancelled,
+            "refunded" => Self::Refunded,
+            "cranked" => Self::Cranked,
+            "slashed" => Self::Slashed,
+            _ => return None,
+        })
+    }
+}
+
+/// An escrow as currently materialized
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EscrowRow {
+    pub escrow: String,
+    pub maker: String,
+    pub taker: String,
+    pub escrow_id: u64,
+    pub amount_a: u64,
+    pub amount_b_expected: u64,
+    pub expiry_ts: i64,
+    /// Set when the maker offered an NFT; `amount_a` is then 1 token, not lamports
+    pub nft_mint: Option<String>,
+    pub status: EscrowStatus,
+    pub created_ts: i64,
+    pub funded_ts: Option<i64>,
+    pub closed_ts: Option<i64>,
+    /// Lamports held for the trade as of the latest event
+    pub locked_lamports: u64,
+}
+
+/// Sequence numbers (inclusive) an account emitted that were never ingested
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SequenceGap {
+    /// The escrow, subscription or config that numbered the missing events
+    pub account: String,
+    pub first: u64,
+    pub last: u64,
+}
+
+/// Completed trade totals for one maker
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MakerVolume {
+    pub maker: String,
+    pub completed: u64,
+    /// Lamports the maker gave up across completed SOL escrows (NFT escrows count 0)
+    pub amount_a: u64,
+    /// Lamports the maker received across completed escrows, after referral fees
+    pub amount_b: u64,
+}
+
+pub struct Store {
+    conn: Connection,
+}
+
+impl Store {
+    /// Opens (or creates) the database at `path`
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    /// Opens a throwaway database, handy for tests
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        migrate(&conn)?;
+        Ok(Self { conn })
+    }
+
+    /// Applies every escrow event in `tx`, returning how many were new
+    ///
+    /// The whole transaction is applied atomically. New events for escrows that
+    /// haven't been created yet count too, though they're only staged.
+    pub fn ingest(&mut self, tx: &TransactionRecord) -> Result<usize> {
+        let events = tx.events(&escrow::ID)?;
+        let db = self.conn.transaction()?;
+        let mut applied = 0;
+
+        for (position, event) in events.iter().enumerate() {
+            let inserted = db.execute(
+                "INSERT OR IGNORE INTO escrow_events (signature, position, seq, slot, escrow, kind, ts)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    tx.signature,
+                    position,
+                    sql_int(event.seq())?,
+                    sql_int(tx.slot)?,
+                    event.escrow().to_string(),
+                    event.kind(),
+                    event.ts(),
+                ],
+            )?;
+            if inserted == 0 {
+                continue;
+            }
+            apply_or_stage(&db, (&tx.signature, position), event)?;
+            applied += 1;
+        }
+
+        db.commit()?;
+        Ok(applied)
+    }
+
+    /// The newest signature a sync has fully ingested
+    pub fn last_synced_signature(&self) -> Result<Option<String>> {
+        self.conn
+            .query_row(
+                "SELECT value FROM sync_state WHERE key = ?1",
+                [LAST_SIGNATURE_KEY],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(Into::into)
+    }
+
+    /// Records how far a sync got, so the next one can resume from there
+    pub fn set_last_synced_signature(&self, signature: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO sync_state (key, value) VALUES (?1, ?2)",
+            [LAST_SIGNATURE_KEY, signature],
+        )?;
+        Ok(())
+    }
+
+    /// Per account, sequence numbers missing between the first and last seen
+    ///
+    /// Anything listed here was emitted on-chain but never ingested. Only gaps within one
+    /// escrow's block are reported; a jump to a new block is a re-created escrow.
+    pub fn sequence_gaps(&self) -> Result<Vec<SequenceGap>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT escrow, seq + 1, next_seq - 1 FROM (
+                 SELECT escrow, seq, LEAD(seq) OVER (PARTITION BY escrow ORDER BY seq) AS next_seq
+                 FROM escrow_events
+             )
+             WHERE next_seq > seq + 1 AND next_seq / ?1 = seq / ?1
+             ORDER BY escrow, seq",
+        )?;
+        let rows = stmt.query_map([sql_int(EVENT_SEQ_BLOCK)?], |row| {
+            Ok(SequenceGap {
+                account: row.get(0)?,
+                first: row.get::<_, i64>(1)? as u64,
+                last: row.get::<_, i64>(2)? as u64,
+            })
+        })?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Into::into)
+    }
+
+    /// Looks up a single escrow by address
+    pub fn escrow(&self, escrow: &Pubkey) -> Result<Option<EscrowRow>> {
+        self.conn
+            .query_row(
+                "SELECT * FROM escrows WHERE escrow = ?1",
+                [escrow.to_string()],
+                read_escrow,
+            )
+            .optional()
+            .map_err(Into::into)
+    }
+
+    /// Escrows addressed to `taker` that haven't reached a terminal state
+    ///
+    /// Includes funded escrows, since the taker still has to complete them.
+    pub fn open_escrows_for_taker(&self, taker: &Pubkey) -> Result<Vec<EscrowRow>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT * FROM escrows
+             WHERE taker = ?1 AND status IN ('open', 'funded')
+             ORDER BY created_ts, escrow",
+        )?;
+        let rows = stmt.query_map([taker.to_string()], read_escrow)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Into::into)
+    }
+
+    /// Completed trade totals per maker, largest `amount_a` volume first
+    pub fn volume_by_maker(&self) -> Result<Vec<MakerVolume>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT maker, COUNT(*), SUM(lamports_a) AS volume_a, SUM(amount_b_expected - referral_fee)
+             FROM (
+                 SELECT maker, amount_b_expected, referral_fee,
+                        CASE WHEN nft_mint IS NULL THEN amount_a ELSE 0 END AS lamports_a
+                 FROM escrows
+                 WHERE status = 'completed'
+             )
+             GROUP BY maker
+             ORDER BY volume_a DESC, maker",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(MakerVolume {
+                maker: row.get(0)?,
+                completed: row.get::<_, i64>(1)? as u64,
+                amount_a: row.get::<_, i64>(2)? as u64,
+                amount_b: row.get::<_, i64>(3)? as u64,
+            })
+        })?;
+        rows.collect::<rusqlite::Result<_>>().map_err(Into::into)
+    }
+}
+
+/// Applies `event`, or stages it if it changes an escrow row that doesn't exist yet
+///
+/// `key` is the event's (signature, position) in `escrow_events`.
+fn apply_or_stage(db: &Connection, key: (&str, usize), event: &EscrowEvent) -> Result<()> {
+    let escrow = event.escrow().to_string();
+    if let EscrowEvent::Created(created) = event {
+        apply(db, event)?;
+        return apply_staged(db, &escrow, sql_int(created.seq)?);
+    }
+
+    let changes_row = !matches!(
+        event,
+        EscrowEvent::BidPlaced(_)
+            | EscrowEvent::BidRevealed(_)
+            | EscrowEvent::BidWithdrawn(_)
+            | EscrowEvent::SubscriptionCreated(_)
+            | EscrowEvent::PeriodCollected(_)
+            | EscrowEvent::SubscriptionCancelled(_)
+            | EscrowEvent::Paused(_)
+            | EscrowEvent::Unpaused(_)
+    );
+    let exists: bool = db.query_row(
+        "SELECT COUNT(*) > 0 FROM escrows WHERE escrow = ?1",
+        [&escrow],
+        |row| row.get(0),
+    )?;
+    if changes_row && !exists {
+        db.execute(
+            "UPDATE escrow_events SET staged = 1, data = ?3 WHERE signature = ?1 AND position = ?2",
+            params![key.0, key.1, event.encode()],
+        )?;
+        return Ok(());
+    }
+    apply(db, event)
+}
+
+/// Applies the events staged for `escrow` after its creation at `created_seq`, oldest first
+fn apply_staged(db: &Connection, escrow: &str, created_seq: i64) -> Result<()> {
+    let staged: Vec<(String, i64, Vec<u8>)> = db
+        .prepare(
+            "SELECT signature, position, data FROM escrow_events
+             WHERE escrow = ?1 AND staged = 1 AND seq > ?2
+             ORDER BY seq",
+        )?
+        .query_map(params![escrow, created_seq], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+
+    for (signature, position, data) in staged {
+        if let Some(event) = EscrowEvent::decode(&data)? {
+            apply(db, &event)?;
+        }
+        db.execute(
+            "UPDATE escrow_events SET staged = 0, data = NULL WHERE signature = ?1 AND position = ?2",
+            params![signature, position],
+        )?;
+    }
+    Ok(())
+}
+
+/// Moves an escrow's row forward according to `event`, unless the row has already
+/// seen a newer one
+fn apply(db: &Connection, event: &EscrowEvent) -> Result<()> {
+    let seq = sql_int(event.seq())?;
+    let locked = sql_int(event.locked_lamports())?;
+    let escrow = event.escrow().to_string();
+
+    match event {
+        // A closed escrow's address can be reused, so creation replaces any older row
+        EscrowEvent::Created(created) => {
+            db.execute(
+                "INSERT INTO escrows
+                 (escrow, maker, taker, escrow_id, amount_a, amount_b_expected, expiry_ts,
+                  nft_mint, status, created_ts, funded_ts, closed_ts, locked_lamports, last_seq)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, NULL, NULL, ?11, ?12)
+                 ON CONFLICT (escrow) DO UPDATE SET
+                     maker = excluded.maker, taker = excluded.taker,
+                     escrow_id = excluded.escrow_id, amount_a = excluded.amount_a,
+                     amount_b_expected = excluded.amount_b_expected, referral_fee = 0,
+                     expiry_ts = excluded.expiry_ts, nft_mint = excluded.nft_mint,
+                     status = excluded.status, created_ts = excluded.created_ts,
+                     funded_ts = NULL, closed_ts = NULL,
+                     locked_lamports = excluded.locked_lamports, last_seq = excluded.last_seq
+                 WHERE last_seq < excluded.last_seq",
+                params![
+                    escrow,
+                    created.maker.to_string(),
+                    created.taker.to_string(),
+                    // IDs are opaque, so store the bits rather than reject large ones
+                    created.escrow_id as i64,
+                    sql_int(created.amount_a)?,
+                    sql_int(created.amount_b_expected)?,
+                    created.expiry_ts,
+                    created.nft_mint.map(|mint| mint.to_string()),
+                    EscrowStatus::Open.as_str(),
+                    created.ts,
+                    locked,
+                    seq,
+                ],
+            )?;
+        }
+        EscrowEvent::Funded(funded) => {
+            db.execute(
+                // Oracle-priced escrows only learn their payment when funded
+                "UPDATE escrows
+                 SET status = ?2, taker = ?3, funded_ts = ?4, amount_b_expected = ?5,
+                     locked_lamports = ?6, last_seq = ?7
+                 WHERE escrow = ?1 AND last_seq < ?7",
+                params![
+                    escrow,
+                    EscrowStatus::Funded.as_str(),
+                    funded.taker.to_string(),
+                    funded.ts,
+                    sql_int(funded.amount_b)?,
+                    locked,
+                    seq,
+                ],
+            )?;
+        }
+        EscrowEvent::Amended(amended) => {
+            db.execute(
+                "UPDATE escrows
+                 SET amount_b_expected = ?2, expiry_ts = ?3, locked_lamports = ?4, last_seq = ?5
+                 WHERE escrow = ?1 AND last_seq < ?5",
+                params![
+                    escrow,
+                    sql_int(amended.amount_b_expected)?,
+                    amended.expiry_ts,
+                    locked,
+                    seq,
+                ],
+            )?;
+        }
+        // Delegation doesn't change the trade; the event is kept in the history only
+        EscrowEvent::OperatorChanged(_) => {
+            db.execute(
+                "UPDATE escrows SET last_seq = ?2 WHERE escrow = ?1 AND last_seq < ?2",
+                params![escrow, seq],
+            )?;
+        }
+        EscrowEvent::MakerTransferred(transferred) => {
+            db.execute(
+                "UPDATE escrows SET maker = ?2, last_seq = ?3 WHERE escrow = ?1 AND last_seq < ?3",
+                params![escrow, transferred.new_maker.to_string(), seq],
+            )?;
+        }
+        EscrowEvent::TakerTransferred(transferred) => {
+            db.execute(
+                "UPDATE escrows SET taker = ?2, last_seq = ?3 WHERE escrow = ?1 AND last_seq < ?3",
+                params![escrow, transferred.new_taker.to_string(), seq],
+            )?;
+        }
+        // Receipts move who is paid, not what the trade is; redeeming releases a held claim
+        EscrowEvent::ReceiptMinted(_) | EscrowEvent::ReceiptRedeemed(_) => {
+            db.execute(
+                "UPDATE escrows SET locked_lamports = ?2, last_seq = ?3
+                 WHERE escrow = ?1 AND last_seq < ?3",
+                params![escrow, locked, seq],
+            )?;
+        }
+        EscrowEvent::Completed(completed) => {
+            // Auctions only learn their winner and price when settled
+            db.execute(
+                "UPDATE escrows SET taker = ?2, amount_b_expected = ?3, referral_fee = ?4
+                 WHERE escrow = ?1 AND last_seq < ?5",
+                params![
+                    escrow,
+                    completed.taker.to_string(),
+                    sql_int(completed.amount_b_expected)?,
+                    sql_int(completed.referral_fee)?,
+                    seq,
+                ],
+            )?;
+            close(db, &escrow, EscrowStatus::Completed, event)?
+        }
+        EscrowEvent::Cancelled(_) => close(db, &escrow, EscrowStatus::Cancelled, event)?,
+        EscrowEvent::Refunded(_) => close(db, &escrow, EscrowStatus::Refunded, event)?,
+        EscrowEvent::KeeperCranked(_) => close(db, &escrow, EscrowStatus::Cranked, event)?,
+        EscrowEvent::BondSlashed(_) => close(db, &escrow, EscrowStatus::Slashed, event)?,
+        // Only recorded in `escrow_events`; no escrow row changes
+        EscrowEvent::BidPlaced(_)
+        | EscrowEvent::BidRevealed(_)
+        | EscrowEvent::BidWithdrawn(_)
+        | EscrowEvent::SubscriptionCreated(_)
+        | EscrowEvent::PeriodCollected(_)
+        | EscrowEvent::SubscriptionCancelled(_)
+        | EscrowEvent::Paused(_)
+        | EscrowEvent::Unpaused(_) => {}
+    }
+
+    Ok(())
+}
+
+/// Adds columns introduced after a database was first created
+///
+/// Databases keyed by the old global sequence number can't be converted, since the
+/// position of each event within its transaction was never stored.
+fn migrate(conn: &Connection) -> Result<()> {
+    let keyed_by_position: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('escrow_events') WHERE name = 'position'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !keyed_by_position {
+        return Err(IndexerError::OutdatedDatabase);
+    }
+
+    const ADDED_COLUMNS: [(&str, &str, &str); 3] = [
+        ("escrows", "referral_fee", "INTEGER NOT NULL DEFAULT 0"),
+        ("escrow_events", "staged", "INTEGER NOT NULL DEFAULT 0"),
+        ("escrow_events", "data", "BLOB"),
+    ];
+    for (table, column, definition) in ADDED_COLUMNS {
+        let exists: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = ?2",
+            [table, column],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            conn.execute_batch(&format!(
+                "ALTER TABLE {table} ADD COLUMN {column} {definition}"
+            ))?;
+        }
+    }
+    Ok(())
+}
+
+/// Moves an escrow into a terminal status
+fn close(db: &Connection, escrow: &str, status: EscrowStatus, event: &EscrowEvent) -> Result<()> {
+    db.execute(
+        "UPDATE escrows SET status = ?2, closed_ts = ?3, locked_lamports = ?4, last_seq = ?5
+         WHERE escrow = ?1 AND last_seq < ?5",
+        params![
+            escrow,
+            status.as_str(),
+            event.ts(),
+            sql_int(event.locked_lamports())?,
+            sql_int(event.seq())?,
+        ],
+    )?;
+    Ok(())
+}
+
+fn read_escrow(row: &Row<'_>) -> rusqlite::Result<EscrowRow> {
+    let status: String = row.get("status")?;
+    Ok(EscrowRow {
+        escrow: row.get("escrow")?,
+        maker: row.get("maker")?,
+        taker: row.get("taker")?,
+        escrow_id: row.get::<_, i64>("escrow_id")? as u64,
+        amount_a: row.get::<_, i64>("amount_a")? as u64,
+        amount_b_expected: row.get::<_, i64>("amount_b_expected")? as u64,
+        expiry_ts: row.get("expiry_ts")?,
+        nft_mint: row.get("nft_mint")?,
+        status: EscrowStatus::parse(&status).ok_or_else(|| {
+            rusqlite::Error::InvalidColumnType(0, status.clone(), rusqlite::types::Type::Text)
+        })?,
+        created_ts: row.get("created_ts")?,
+        funded_ts: row.get("funded_ts")?,
+        closed_ts: row.get("closed_ts")?,
+        locked_lamports: row.get::<_, i64>("locked_lamports")? as u64,
+    })
+}
+
+/// SQLite integers are signed 64-bit; lamport amounts never get near the limit
+fn sql_int(value: u64) -> Result<i64> {
+    i64::try_from(value).map_err(|_| IndexerError::AmountOverflow(value))
+}
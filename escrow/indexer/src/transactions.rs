@@ -0,0 +1,142 @@
+//! # Transaction Parsing
+//!
+//! Reads transactions in the shape returned by the `getTransaction` RPC method with
+//! `"encoding": "json"`. Events emitted with `emit_cpi!` live in the transaction's
+//! inner instructions, not its logs, so logs are never consulted.
+//!
+//! Transaction files hold one such object per line, either bare or wrapped in the
+//! JSON-RPC response envelope (`{"jsonrpc": "2.0", "result": {...}}`).
+
+use std::io::BufRead;
+use std::str::FromStr;
+
+use anchor_lang::prelude::Pubkey;
+use serde_json::Value;
+
+use crate::{EscrowEvent, IndexerError, Result};
+
+/// The parts of a transaction the indexer needs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionRecord {
+    pub slot: u64,
+    pub signature: String,
+    /// False when the transaction failed; its events never took effect
+    pub succeeded: bool,
+    /// Every inner instruction, in execution order, as (program id, data)
+    pub inner_instructions: Vec<(Pubkey, Vec<u8>)>,
+}
+
+impl TransactionRecord {
+    /// Extracts a record from a `getTransaction` result
+    pub fn from_json(value: &Value) -> Result<Self> {
+        let value = value.get("result").unwrap_or(value);
+        let malformed = |reason| IndexerError::MalformedTransaction { reason };
+
+        let slot = value["slot"].as_u64().ok_or(malformed("missing slot"))?;
+        let signature = value["transaction"]["signatures"][0]
+            .as_str()
+            .ok_or(malformed("missing signature"))?
+            .to_string();
+        let meta = &value["meta"];
+        let succeeded = meta.get("err").is_some_and(Value::is_null);
+
+        // Versioned transactions append lookup-table addresses after the static keys
+        let mut account_keys = Vec::new();
+        let loaded = &meta["loadedAddresses"];
+        for key in [
+            &value["transaction"]["message"]["accountKeys"],
+            &loaded["writable"],
+            &loaded["readonly"],
+        ]
+        .into_iter()
+        .filter_map(Value::as_array)
+        .flatten()
+        {
+            let key = key
+                .as_str()
+                .ok_or(malformed("account key is not a string"))?;
+            account_keys.push(Pubkey::from_str(key).map_err(|_| malformed("invalid account key"))?);
+        }
+
+        let mut inner_instructions = Vec::new();
+        let groups = meta["innerInstructions"].as_array().map(Vec::as_slice);
+        for ix in groups
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|group| group["instructions"].as_array())
+            .flatten()
+        {
+            let program_index = ix["programIdIndex"]
+                .as_u64()
+                .ok_or(malformed("missing programIdIndex"))?;
+            let program_id = *account_keys
+                .get(program_index as usize)
+                .ok_or(malformed("programIdIndex out of range"))?;
+            let data = bs58::decode(ix["data"].as_str().ok_or(malformed("missing data"))?)
+                .into_vec()
+                .map_err(|_| malformed("instruction data is not base58"))?;
+            inner_instructions.push((program_id, data));
+        }
+
+        Ok(Self {
+            slot,
+            signature,
+            succeeded,
+            inner_instructions,
+        })
+    }
+
+    /// Decodes the events `program_id` emitted, in execution order
+    ///
+    /// Only the program itself can sign as its event authority, so any successful
+    /// self-CPI carrying the event tag is genuine. Failed transactions yield no events.
+    pub fn events(&self, program_id: &Pubkey) -> Result<Vec<EscrowEvent>> {
+        if !self.succeeded {
+            return Ok(Vec::new());
+        }
+
+        let mut events = Vec::new();
+        for (invoked, data) in &self.inner_instructions {
+            if invoked == program_id {
+                events.extend(EscrowEvent::from_cpi_data(data)?);
+            }
+        }
+        Ok(events)
+    }
+}
+
+/// Streams [`TransactionRecord`]s out of a reader with one JSON transaction per line
+///
+/// Blank lines are skipped.
+pub struct TransactionReader<R> {
+    lines: std::io::Lines<R>,
+}
+
+impl<R: BufRead> TransactionReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            lines: reader.lines(),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for TransactionReader<R> {
+    type Item = Result<TransactionRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err.into())),
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            return Some(
+                serde_json::from_str(&line)
+                    .map_err(IndexerError::from)
+                    .and_then(|value| TransactionRecord::from_json(&value)),
+            );
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use std::io::BufReader;
+
+use anchor_lang::prelude::Pubkey;
+use escrow_indexer::{
+    EscrowStatus, IndexerError, SequenceGap, Store, TransactionReader, TransactionRecord,
+};
+
+const LIFECYCLE: &str = include_str!("fixtures/transactions.jsonl");
+
+fn key(n: u8) -> Pubkey {
+    Pubkey::new_from_array([n; 32])
+}
+
+fn transactions(fixture: &str) -> Vec<TransactionRecord> {
+    TransactionReader::new(BufReader::new(fixture.as_bytes()))
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+fn ingest_all(store: &mut Store, fixture: &str) -> usize {
+    transactions(fixture)
+        .iter()
+        .map(|tx| store.ingest(tx).unwrap())
+        .sum()
+}
+
+#[test]
+fn parses_every_transaction_in_the_fixture() {
+    let txs = transactions(LIFECYCLE);
+    assert_eq!(txs.len(), 30);
+    assert_eq!(txs[0].slot, 10);
+    assert_eq!(txs[0].signature, bs58::encode([1u8; 64]).into_string());
+    assert!(txs[0].succeeded);
+    assert!(!txs[6].succeeded);
+
+    // System transfer plus the escrow program's event self-CPI
+    assert_eq!(txs[0].inner_instructions.len(), 2);
+    assert_eq!(txs[0].inner_instructions[1].0, escrow::ID);
+}
+
+#[test]
+fn materializes_escrow_lifecycles() {
+    let mut store = Store::open_in_memory().unwrap();
+    // 7 creates, 4 funds, 3 completes, 1 cancel, 1 amendment, 1 operator change,
+    // 1 pause, 1 unpause, 2 position transfers, 1 receipt, 3 subscription events,
+    // 2 bids and 1 bond slash; the failed and spoofed events are skipped
+    assert_eq!(ingest_all(&mut store, LIFECYCLE), 28);
+
+    let completed = store.escrow(&key(11)).unwrap().unwrap();
+    assert_eq!(completed.status, EscrowStatus::Completed);
+    assert_eq!(completed.funded_ts, Some(1_700_000_100));
+    assert_eq!(completed.closed_ts, Some(1_700_000_200));
+    assert_eq!(completed.locked_lamports, 0);
+
+    let cancelled = store.escrow(&key(13)).unwrap().unwrap();
+    assert_eq!(cancelled.status, EscrowStatus::Cancelled);
+
+    // The failed funding and the other program's CPI left escrow 12 open
+    let open = store.escrow(&key(12)).unwrap().unwrap();
+    assert_eq!(open.status, EscrowStatus::Open);
+    assert_eq!(open.funded_ts, None);
+    assert_eq!(open.locked_lamports, 2000);
+    assert_eq!(open.nft_mint, None);
+
+    // The amendment repriced it and pushed the expiry out
+    assert_eq!(open.amount_b_expected, 900);
+    assert_eq!(open.expiry_ts, 1_700_007_210);
+
+    let nft = store.escrow(&key(14)).unwrap().unwrap();
+    assert_eq!(nft.status, EscrowStatus::Completed);
+    assert_eq!(nft.nft_mint, Some(key(20).to_string()));
+
+    // The oracle-priced escrow's payment is filled in when it's funded
+    let oracle = store.escrow(&key(15)).unwrap().unwrap();
+    assert_eq!(oracle.status, EscrowStatus::Funded);
+    assert_eq!(oracle.amount_b_expected, 750);
+    assert_eq!(oracle.locked_lamports, 2250);
+
+    // Both sides of it were then transferred
+    assert_eq!(oracle.maker, key(2).to_string());
+    assert_eq!(oracle.taker, key(4).to_string());
+
+    // Pausing is logged against the config account without creating an escrow row
+    assert_eq!(store.escrow(&key(30)).unwrap(), None);
+
+    // So is the subscription, whose events are numbered on their own
+    assert_eq!(store.escrow(&key(23)).unwrap(), None);
+    assert!(store.sequence_gaps().unwrap().is_empty());
+
+    // The auction's winner and winning bid replace the listing key and reserve price
+    let auction = store.escrow(&key(24)).unwrap().unwrap();
+    assert_eq!(auction.status, EscrowStatus::Completed);
+    assert_eq!(auction.taker, key(27).to_string());
+    assert_eq!(auction.amount_b_expected, 600);
+
+    // The bonded escrow's taker missed the deadline and the maker unwound it
+    let slashed = store.escrow(&key(28)).unwrap().unwrap();
+    assert_eq!(slashed.status, EscrowStatus::Slashed);
+    assert_eq!(slashed.funded_ts, Some(1_700_092_300));
+    assert_eq!(slashed.locked_lamports, 0);
+}
+
+#[test]
+fn lists_open_escrows_for_a_taker() {
+    let mut store = Store::open_in_memory().unwrap();
+    ingest_all(&mut store, LIFECYCLE);
+
+    let open = store.open_escrows_for_taker(&key(3)).unwrap();
+    assert_eq!(open.len(), 1);
+    assert_eq!(open[0].escrow, key(12).to_string());
+    assert_eq!(open[0].amount_a, 2000);
+    assert_eq!(open[0].amount_b_expected, 900);
+
+    // The new taker of the transferred oracle escrow sees it; the old one doesn't
+    let transferred = store.open_escrows_for_taker(&key(4)).unwrap();
+    assert_eq!(transferred.len(), 1);
+    assert_eq!(transferred[0].escrow, key(15).to_string());
+    assert!(store.open_escrows_for_taker(&key(6)).unwrap().is_empty());
+}
+
+#[test]
+fn sums_completed_volume_by_maker() {
+    let mut store = Store::open_in_memory().unwrap();
+    ingest_all(&mut store, LIFECYCLE);
+
+    let volume = store.volume_by_maker().unwrap();
+    assert_eq!(volume.len(), 2);
+    assert_eq!(volume[0].maker, key(1).to_string());
+    assert_eq!(volume[0].completed, 1);
+    assert_eq!(volume[0].amount_a, 1000);
+    // 10 of the 500 lamport payment went to the referrer
+    assert_eq!(volume[0].amount_b, 490);
+
+    // The NFT sale brought in SOL but gave up no lamports; the auction did both
+    assert_eq!(volume[1].maker, key(2).to_string());
+    assert_eq!(volume[1].completed, 2);
+    assert_eq!(volume[1].amount_a, 400);
+    assert_eq!(volume[1].amount_b, 5600);
+}
+
+#[test]
+fn replaying_logs_is_a_no_op() {
+    let mut store = Store::open_in_memory().unwrap();
+    ingest_all(&mut store, LIFECYCLE);
+
+    assert_eq!(ingest_all(&mut store, LIFECYCLE), 0);
+    let completed = store.escrow(&key(11)).unwrap().unwrap();
+    assert_eq!(completed.status, EscrowStatus::Completed);
+}
+
+#[test]
+fn reports_sequence_gaps_from_missed_transactions() {
+    let mut store = Store::open_in_memory().unwrap();
+    // Skip the funding transaction, escrow 11's second event
+    for (i, tx) in transactions(LIFECYCLE).iter().enumerate() {
+        if i != 3 {
+            store.ingest(tx).unwrap();
+        }
+    }
+    let gap = SequenceGap {
+        account: key(11).to_string(),
+        first: 1,
+        last: 1,
+    };
+    assert_eq!(store.sequence_gaps().unwrap(), vec![gap]);
+
+    // Backfilling closes the gap
+    store.ingest(&transactions(LIFECYCLE)[3]).unwrap();
+    assert!(store.sequence_gaps().unwrap().is_empty());
+}
+
+#[test]
+fn stages_events_that_arrive_before_their_escrow() {
+    let mut in_order = Store::open_in_memory().unwrap();
+    ingest_all(&mut in_order, LIFECYCLE);
+
+    // Newest first: every event but the creations arrives before its escrow exists
+    let mut reversed = Store::open_in_memory().unwrap();
+    let mut txs = transactions(LIFECYCLE);
+    txs.reverse();
+    let applied: usize = txs.iter().map(|tx| reversed.ingest(tx).unwrap()).sum();
+    assert_eq!(applied, 28);
+
+    for escrow in [11, 12, 13, 14, 15, 24, 28] {
+        let escrow = key(escrow);
+        assert_eq!(
+            reversed.escrow(&escrow).unwrap(),
+            in_order.escrow(&escrow).unwrap()
+        );
+    }
+}
+
+#[test]
+fn late_events_never_roll_an_escrow_back() {
+    let mut store = Store::open_in_memory().unwrap();
+    // Skip the funding of escrow 11, then backfill it after it completed
+    let txs = transactions(LIFECYCLE);
+    for (i, tx) in txs.iter().enumerate() {
+        if i != 3 {
+            store.ingest(tx).unwrap();
+        }
+    }
+    assert_eq!(store.ingest(&txs[3]).unwrap(), 1);
+
+    let completed = store.escrow(&key(11)).unwrap().unwrap();
+    assert_eq!(completed.status, EscrowStatus::Completed);
+    assert_eq!(completed.locked_lamports, 0);
+}
+
+#[test]
+fn refuses_databases_keyed_by_the_old_global_sequence() {
+    let path = std::env::temp_dir().join(format!("escrow-indexer-{}.db", std::process::id()));
+    let conn = rusqlite::Connection::open(&path).unwrap();
+    conn.execute_batch(
+        "CREATE TABLE escrow_events (seq INTEGER PRIMARY KEY, signature TEXT NOT NULL,
+             slot INTEGER NOT NULL, escrow TEXT NOT NULL, kind TEXT NOT NULL, ts INTEGER NOT NULL)",
+    )
+    .unwrap();
+    drop(conn);
+
+    let result = Store::open(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(result, Err(IndexerError::OutdatedDatabase)));
+}
+
+#[test]
+fn rejects_lines_that_are_not_transactions() {
+    let input = "{\"slot\": 1}\n";
+    let result: Result<Vec<_>, _> =
+        TransactionReader::new(BufReader::new(input.as_bytes())).collect();
+    assert!(result.is_err());
+}
@@ -36,22 +36,160 @@
 //! ## Development Best Practices
 //!
 //! - **Comprehensive validation**: All inputs are validated before processing
-//! - **Event emission**: All state changes are logged for transparency
+//! - **Event emission**: All state changes are emitted as self-CPI events, numbered per escrow
 //! - **Error handling**: Clear error messages for debugging and user feedback
 //! - **Gas optimization**: Efficient operations to minimize transaction costs
 
+// `#[program]` expands to IDL account handlers that call the deprecated
+// `AccountInfo::realloc`; silence it until Anchor switches to `resize`.
+#![allow(deprecated)]
+
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+use anchor_lang::solana_program::program_option::COption;
 use anchor_lang::system_program;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::memo::{self, BuildMemo, Memo};
+use anchor_spl::metadata::{Metadata, MetadataAccount};
+use anchor_spl::token::{self, Burn, CloseAccount, Mint, MintTo, Token, TokenAccount};
 
 // Program ID - This unique address identifies our smart contract on Solana
 // Think of it like a street address for our program
 declare_id!("4BnPg8BniGiwC9Pop7b45gDqTV2vGERgUTBSHEDCrkR7");
 
+/// How many escrows a single `EscrowIndex` page can list
+#[constant]
+pub const MAX_ESCROWS_PER_INDEX_PAGE: u32 = 32;
+
+/// How many owners a single `Multisig` can have
+#[constant]
+pub const MAX_MULTISIG_OWNERS: u8 = 10;
+
+/// Longest Merkle proof `fund_escrow` accepts for an allowlisted escrow
+/// Enough for allowlists of up to 2^32 wallets
+#[constant]
+pub const MAX_ALLOWLIST_PROOF_LEN: u32 = 32;
+
+/// Operator permission: may call `cancel_escrow`
+#[constant]
+pub const OPERATOR_CAN_CANCEL: u8 = 1 << 0;
+
+/// Operator permission: may call `amend_escrow`
+#[constant]
+pub const OPERATOR_CAN_AMEND: u8 = 1 << 1;
+
+/// Operator permission: may call `refund_after_expiry`
+#[constant]
+pub const OPERATOR_CAN_REFUND: u8 = 1 << 2;
+
+/// Most escrows `batch_create_escrows` or `batch_cancel_escrows` handle in one call
+#[constant]
+pub const MAX_BATCH_SIZE: u32 = 16;
+
+/// Largest referral fee a maker may declare, in basis points of the taker's payment
+#[constant]
+pub const MAX_REFERRAL_FEE_BPS: u16 = 500;
+
+/// Longest `EscrowMetadata::uri`, in bytes
+#[constant]
+pub const MAX_METADATA_URI_LEN: u32 = 96;
+
+/// Event sequence numbers reserved for each escrow a maker creates
+/// Escrow `n` of a maker numbers its events from `n * EVENT_SEQ_BLOCK`
+#[constant]
+pub const EVENT_SEQ_BLOCK: u64 = 1u64 << 32;
+
+/// Programs that own Pyth price accounts (mainnet, then devnet)
+/// Oracle-priced escrows only read prices from accounts these programs own
+pub const PYTH_PROGRAM_IDS: [Pubkey; 2] = [
+    pubkey!("FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH"),
+    pubkey!("gSbePebfvPy7tRqimPoVecS2UsBvYv46ynrzWocc92s"),
+];
+
 
 #[program]
 pub mod escrow {
     use super::*;
 
+    /// # Initialize Config Instruction
+    ///
+    /// **What it does**: Creates the program-wide `Config` PDA holding the emergency pause flag.
+    ///
+    /// ## Who Can Call It
+    ///
+    /// Only the program's upgrade authority, which becomes the config's `admin`. Checking
+    /// the upgrade authority stops anyone from front-running the deployment and seizing
+    /// the pause switch.
+    pub fn initialize_config(ctx: Context<InitializeConfig>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.admin = ctx.accounts.admin.key();
+        config.paused = false;
+        config.bump = ctx.bumps.config;
+        Ok(())
+    }
+
+    /// # Pause Program Instruction
+    ///
+    /// **What it does**: The admin stops new deposits program-wide, e.g. while a bug is investigated.
+    ///
+    /// ## What Stops and What Doesn't
+    ///
+    /// - **Blocked**: `create_escrow`, `create_nft_escrow`, `create_multisig_escrow` and `fund_escrow`
+    /// - **Still allowed**: Every cancel, refund, crank and settlement, so users can always exit
+    pub fn pause_program(ctx: Context<SetPaused>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        require!(!config.paused, EscrowError::ProgramPaused);
+        config.paused = true;
+
+        let event = Paused {
+            seq: config.next_event_seq()?,
+            config: config.key(),
+            admin: config.admin,
+            ts: Clock::get()?.unix_timestamp,
+        };
+        emit_cpi!(event);
+
+        Ok(())
+    }
+
+    /// # Unpause Program Instruction
+    ///
+    /// **What it does**: The admin lifts the pause; deposits are accepted again.
+    pub fn unpause_program(ctx: Context<SetPaused>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        require!(config.paused, EscrowError::NotPaused);
+        config.paused = false;
+
+        let event = Unpaused {
+            seq: config.next_event_seq()?,
+            config: config.key(),
+            admin: config.admin,
+            ts: Clock::get()?.unix_timestamp,
+        };
+        emit_cpi!(event);
+
+        Ok(())
+    }
+
+    /// # Open User Stats Instruction
+    ///
+    /// **What it does**: Creates `wallet`'s `UserStats` record ahead of its first settled,
+    /// cancelled or expired escrow.
+    ///
+    /// ## Who Pays
+    ///
+    /// `payer`, normally the wallet itself. Opening is optional: every instruction that
+    /// updates a record creates it on first use, paid by that instruction's signer, so a
+    /// wallet that opens its own spares its counterparties and keepers the rent. Anyone may
+    /// pay for any wallet, which is how a multisig's record gets opened: its treasury PDA
+    /// can't sign.
+    pub fn open_user_stats(ctx: Context<OpenUserStats>) -> Result<()> {
+        let user_stats = &mut ctx.accounts.user_stats;
+        user_stats.wallet = ctx.accounts.wallet.key();
+        user_stats.bump = ctx.bumps.user_stats;
+        Ok(())
+    }
+
     /// # Create Escrow Instruction
     ///
     /// **What it does**: Party A (the maker) creates a new escrow offer and deposits their tokens.
@@ -59,60 +197,190 @@ pub mod escrow {
     /// ## Step-by-Step Process
     ///
     /// 1. **Validate inputs**: Ensure amounts > 0 and expiry is in future
-    /// 2. **Create escrow account**: Store all trade details on blockchain
-    /// 3. **Lock maker's tokens**: Transfer Token A to secure vault controlled by program
-    /// 4. **Emit event**: Log the creation for transparency and tracking
+    /// 2. **Pick escrow ID**: Use `escrow_id` if given, otherwise the next ID from the maker's profile
+    /// 3. **Create escrow account**: Store all trade details on blockchain
+    /// 4. **Lock maker's tokens**: Transfer Token A to secure vault controlled by program
+    /// 5. **Emit event**: Emit the creation via self-CPI, numbered as the escrow's first event
+    ///
+    /// ## Choosing an Escrow ID
+    ///
+    /// Pass `None` to let the program assign `maker_profile.next_escrow_id`. Clients can
+    /// read that counter to derive the escrow PDA before sending the transaction.
+    /// Explicit IDs still work; the counter skips past them so automatic IDs never collide.
+    ///
+    /// ## Choosing an Index Page
+    ///
+    /// `index_page` picks which of the taker's `EscrowIndex` pages lists the new escrow.
+    /// Clients use the first page that is missing or has room; a full page is rejected.
+    ///
+    /// ## Options
+    ///
+    /// Every term below is a field of `options`; `EscrowOptions::default()` (all unset)
+    /// creates a plain fixed-price swap with `taker_pubkey`. See `EscrowOptions`.
+    ///
+    /// ## Oracle Pricing
+    ///
+    /// Pass `oracle` to let a Pyth price feed set the taker's payment when they fund,
+    /// instead of fixing it now. `amount_b_expected` is ignored and filled in at funding.
+    /// See `OracleTerms` for how the payment is computed and bounded.
+    ///
+    /// ## Dutch Auctions
+    ///
+    /// Pass `dutch_auction` to have the price fall from a start price to a floor by
+    /// `expiry_ts`, either smoothly or in steps; the taker pays the price at the moment
+    /// they fund. `amount_b_expected` is ignored and holds the start price until funded.
+    /// Can't be combined with `oracle`. See `DutchAuctionTerms`.
+    ///
+    /// ## Auctions
+    ///
+    /// Pass `auction` to sell `amount_a` to the highest bidder instead of a named taker.
+    /// Bidders lock their bids in their own `Bid` PDAs with `place_bid` (English) or
+    /// `commit_bid` / `reveal_bid` (sealed-bid), and after bidding closes anyone can
+    /// `settle_auction`. As with allowlists, `taker_pubkey` only picks the listing index.
+    /// `amount_b_expected` is ignored and holds the reserve price until settled.
+    /// Auctions take no other pricing, attester or allowlist terms. See `AuctionTerms`.
+    ///
+    /// ## Attestation-Gated Release
+    ///
+    /// Pass `attester` to make a third party (e.g. a shipping verifier) decide how a funded
+    /// escrow ends: only `attest_release` or `attest_refund`, signed by that key, can settle it.
+    ///
+    /// ## Taker Allowlists
+    ///
+    /// Pass `taker_allowlist`, the Merkle root of a list of wallets, to let any of them take
+    /// the escrow; `fund_escrow` checks the funder's proof against it. The list itself never
+    /// goes on-chain. `taker_pubkey` then only picks whose `EscrowIndex` lists the offer
+    /// (e.g. a key the maker publishes for the sale), and `escrow.taker` stays unset until funded.
+    ///
+    /// ## Referral Fees
+    ///
+    /// Pass a non-zero `referral_fee_bps` (at most `MAX_REFERRAL_FEE_BPS`) to pay whichever
+    /// frontend routes the taker a share of the payment. It comes out of what the maker
+    /// receives at settlement, so the taker's price is unchanged. See `fund_escrow`.
+    ///
+    /// ## Receipts
+    ///
+    /// Add `mint_receipt` for the maker side to the same transaction to get a token that
+    /// carries the right to the maker's payout; see `mint_receipt`.
+    ///
+    /// ## Transferable Positions
+    ///
+    /// Either side can hand its position over with `transfer_maker_rights` or
+    /// `transfer_taker_rights`. Set `require_transfer_consent` to make every such
+    /// transfer need the other side's signature too.
+    ///
+    /// ## Reference Metadata
+    ///
+    /// Pass `metadata` to tie the escrow to an off-chain record such as an invoice or
+    /// order: a 32-byte content hash and an optional short URI, stored on the escrow and
+    /// reported in `EscrowCreated`. Settlement instructions echo it through the SPL Memo
+    /// program when it's passed to them. See `EscrowMetadata`.
+    ///
+    /// ## Sponsored Rent
+    ///
+    /// `rent_payer` pays the rent for the escrow PDA (and for the maker profile and index
+    /// page if they're new), so a relayer can onboard makers who only hold the SOL they
+    /// offer. Makers paying their own way pass themselves. It's recorded on the escrow and
+    /// gets the escrow's rent back when the escrow closes, on whichever instruction finishes
+    /// it; the shared profile and index accounts are never closed. The maker still signs and
+    /// deposits `amount_a`.
+    ///
+    /// ## Taker Bonds
+    ///
+    /// Pass `taker_bond` to make whoever funds also deposit a bond and complete the swap
+    /// within `settle_secs` of funding. It's returned when they complete; if they don't in
+    /// time, `slash_taker_bond` unwinds the trade and pays the bond to the maker. Not for
+    /// escrows with an `attester` or an `auction`, which the taker doesn't settle. See `TakerBond`.
+    ///
+    /// ## Cancellation Penalties
+    ///
+    /// Pass a non-zero `cancel_penalty_bps` (at most 10,000) to let the maker cancel even
+    /// after a taker funds, compensating them with that share of `amount_a`; see
+    /// `cancel_escrow`. With 0, as before, funded escrows can only be settled. Not for
+    /// escrows with an `attester` or an `auction`.
+    ///
+    /// ## Taker Reputation
+    ///
+    /// Pass a non-zero `min_taker_completed` to only accept takers whose `UserStats` show at
+    /// least that many settled swaps; `fund_escrow` and `transfer_taker_rights` then need the
+    /// (new) taker's stats account. Not for `auction` escrows, which bidders don't fund
+    /// through `fund_escrow`. The count can be inflated with tiny swaps; see `UserStats`.
     ///
     /// ## Security Checks
     ///
     /// - Only positive amounts allowed (prevents zero-value escrows)
     /// - Expiry must be future-dated (prevents instant expiration)
+    /// - The program must not be paused
     /// - Maker must have sufficient tokens (enforced by token program)
     /// - All accounts properly validated (enforced by Anchor)
     ///
     /// ## What Happens Next
     ///
-    /// - Escrow is now visible to potential takers
+    /// - Escrow is now visible to potential takers, and listed on the taker's `EscrowIndex` page
     /// - Maker's SOL is safely locked in escrow PDA
     /// - Anyone can call `fund_escrow` to complete the trade
     /// - If no one takes it before expiry, maker can refund
+    /// - If the maker never refunds, any keeper can call `crank_expired`
+    ///   and collect the `keeper_tip` deposited here alongside `amount_a`
     ///
     /// The escrow PDA holds the SOL securely.
     #[allow(clippy::too_many_arguments)]
     pub fn create_escrow(
         ctx: Context<CreateEscrow>,
-        escrow_id: u64,
+        escrow_id: Option<u64>,
         amount_a: u64,
         amount_b_expected: u64,
         expiry_ts: i64,
         taker_pubkey: Pubkey,
+        keeper_tip: u64,
+        index_page: u32,
+        options: EscrowOptions,
     ) -> Result<()> {
-        // Basic validations
-        require!(amount_a > 0, EscrowError::InvalidAmount);
-        require!(amount_b_expected > 0, EscrowError::InvalidAmount);
-        require!(
-            expiry_ts > Clock::get()?.unix_timestamp,
-            EscrowError::InvalidExpiry
-        );
+        let now = Clock::get()?.unix_timestamp;
+
+        // Consume the ID and advance the maker's counter past it
+        let maker_profile = &mut ctx.accounts.maker_profile;
+        let escrow_id = maker_profile.assign_escrow_id(escrow_id)?;
+        let event_seq = maker_profile.reserve_event_seqs()?;
+        maker_profile.maker = ctx.accounts.maker.key();
+        maker_profile.bump = ctx.bumps.maker_profile;
 
         // Get escrow key and account info before mutable borrow
         let escrow_key = ctx.accounts.escrow.key();
         let escrow_account_info = ctx.accounts.escrow.to_account_info();
 
-        // Initialize escrow account state
+        // Check the terms and initialize escrow account state
         let escrow = &mut ctx.accounts.escrow;
-        escrow.maker = ctx.accounts.maker.key();
-        escrow.taker = Some(taker_pubkey);
-        escrow.escrow_id = escrow_id;
-        escrow.amount_a = amount_a;
-        escrow.amount_b_expected = amount_b_expected;
-        escrow.is_funded = false;
-        escrow.is_active = true;
-        escrow.is_completed = false;
-        escrow.expiry_ts = expiry_ts;
-        escrow.bump = ctx.bumps.escrow;
+        escrow.set_inner(EscrowAccount::init(
+            NewEscrow {
+                maker: ctx.accounts.maker.key(),
+                escrow_id,
+                bump: ctx.bumps.escrow,
+                rent_payer: ctx.accounts.rent_payer.key(),
+                amount_a,
+                amount_b_expected,
+                expiry_ts,
+                taker_pubkey,
+                keeper_tip,
+                index_page,
+                nft_mint: None,
+                options,
+                event_seq,
+            },
+            now,
+        )?);
+
+        // List the escrow on the taker's discovery index
+        let escrow_index = &mut ctx.accounts.escrow_index;
+        escrow_index.taker = taker_pubkey;
+        escrow_index.page = index_page;
+        escrow_index.bump = ctx.bumps.escrow_index;
+        escrow_index.push(escrow_key)?;
 
-        // Transfer SOL from maker to escrow PDA
+        // Transfer SOL (offer plus keeper tip) from maker to escrow PDA
+        let deposit = amount_a
+            .checked_add(keeper_tip)
+            .ok_or(EscrowError::InvalidAmount)?;
         let transfer_ix = system_program::Transfer {
             from: ctx.accounts.maker.to_account_info(),
             to: escrow_account_info,
@@ -122,18 +390,11 @@ pub mod escrow {
                 ctx.accounts.system_program.to_account_info(),
                 transfer_ix,
             ),
-            amount_a,
+            deposit,
         )?;
 
-        emit!(EscrowCreated {
-            escrow: escrow_key,
-            maker: escrow.maker,
-            escrow_id,
-            amount_a,
-            amount_b_expected,
-            expiry_ts,
-            ts: Clock::get()?.unix_timestamp,
-        });
+        let event = escrow.created_event(escrow_key, now)?;
+        emit_cpi!(event);
 
         Ok(())
     }
@@ -153,6 +414,7 @@ pub mod escrow {
     ///
     /// - Escrow must be active (not completed/cancelled/expired)
     /// - Escrow must not be already funded (prevents double-funding)
+    /// - The program must not be paused
     /// - Taker must have sufficient SOL (enforced by system program)
     ///
     /// ## What Happens Next
@@ -166,7 +428,56 @@ pub mod escrow {
     ///
     /// This is the critical "acceptance" step where the taker commits to the trade.
     /// Once funded, the escrow becomes a binding agreement between both parties.
-    pub fn fund_escrow(ctx: Context<FundEscrow>) -> Result<()> {
+    ///
+    /// ## Oracle-Priced Escrows
+    ///
+    /// Pass the escrow's `price_feed`. The payment is computed from the feed's current
+    /// price and stored as `amount_b_expected`, so settlement works as for fixed prices.
+    /// The price used is recorded in `EscrowFunded`.
+    ///
+    /// ## Dutch Auctions
+    ///
+    /// The payment is the auction's price at the current `Clock` time, stored as
+    /// `amount_b_expected`. Since it moves between signing and landing, takers should pass
+    /// `max_amount_b`.
+    ///
+    /// ## Price Protection
+    ///
+    /// Pass `max_amount_b` to fail instead of paying more than that; `None` accepts
+    /// whatever the escrow charges. Useful for every pricing mode that isn't fixed.
+    ///
+    /// ## Allowlisted Escrows
+    ///
+    /// Pass the Merkle proof that the funder is on the escrow's `taker_allowlist`; it
+    /// replaces the single-taker check, and the funder becomes `escrow.taker`.
+    /// Other escrows take an empty proof.
+    ///
+    /// ## Receipts
+    ///
+    /// Add `mint_receipt` for the taker side to the same transaction to tokenize the
+    /// taker's payout, as for the maker in `create_escrow`.
+    ///
+    /// ## Referrers
+    ///
+    /// Frontends pass their own wallet as `referrer` to be recorded on the escrow; it is
+    /// paid the escrow's `referral_fee_bps` share when the swap settles. Escrows without a
+    /// referral fee ignore it. The taker, the attester and the escrow itself can't be the
+    /// referrer, and none can be added later: escrows funded without one pay the maker in full.
+    ///
+    /// ## Taker Reputation
+    ///
+    /// Escrows with a `min_taker_completed` need the taker's `taker_stats` showing at least
+    /// that many settled swaps. A wallet without a record has none, so it can't fund them.
+    ///
+    /// ## Sponsored Takers
+    ///
+    /// Funding creates no accounts, so there is no rent to sponsor; a relayer only needs
+    /// to be the transaction's fee payer. The taker still signs for `amount_b_expected`.
+    pub fn fund_escrow(
+        ctx: Context<FundEscrow>,
+        allowlist_proof: Vec<[u8; 32]>,
+        max_amount_b: Option<u64>,
+    ) -> Result<()> {
         // Get escrow key and account info before mutable borrow
         let escrow_key = ctx.accounts.escrow.key();
         let escrow_account_info = ctx.accounts.escrow.to_account_info();
@@ -174,7 +485,23 @@ pub mod escrow {
 
         require!(escrow.is_active, EscrowError::NotActive);
         require!(!escrow.is_funded, EscrowError::AlreadyFunded);
-        require!(escrow.taker == Some(ctx.accounts.taker.key()), EscrowError::Unauthorized);
+        require!(escrow.auction.is_none(), EscrowError::AuctionEscrow);
+        match &escrow.taker_allowlist {
+            Some(root) => {
+                require!(
+                    allowlist_proof.len() <= MAX_ALLOWLIST_PROOF_LEN as usize,
+                    EscrowError::NotOnAllowlist
+                );
+                require!(
+                    verify_allowlist_proof(root, &ctx.accounts.taker.key(), &allowlist_proof),
+                    EscrowError::NotOnAllowlist
+                );
+            }
+            None => {
+                require!(escrow.taker == Some(ctx.accounts.taker.key()), EscrowError::Unauthorized);
+            }
+        }
+        check_taker_reputation(escrow, &ctx.accounts.taker_stats)?;
 
         // Check if escrow has expired
         let clock = Clock::get()?;
@@ -183,8 +510,33 @@ pub mod escrow {
             EscrowError::EscrowExpired
         );
 
-        // Transfer SOL from taker to escrow PDA
+        // Price oracle-priced escrows now; fixed-price ones already know their payment
+        let oracle_price = match &escrow.oracle {
+            Some(terms) => {
+                let feed = ctx
+                    .accounts
+                    .price_feed
+                    .as_ref()
+                    .filter(|feed| feed.key() == terms.price_feed)
+                    .ok_or(EscrowError::OracleMismatch)?;
+                let price = OraclePrice::from_pyth_account(feed)?;
+                escrow.amount_b_expected =
+                    terms.payment_for(escrow.amount_a, &price, clock.unix_timestamp)?;
+                Some(price)
+            }
+            None => None,
+        };
+        if let Some(terms) = &escrow.dutch_auction {
+            escrow.amount_b_expected = terms.price_at(clock.unix_timestamp, escrow.expiry_ts);
+        }
+
+        // Transfer SOL (payment plus any bond) from taker to escrow PDA
         let amount_b = escrow.amount_b_expected;
+        if let Some(max_amount_b) = max_amount_b {
+            require!(amount_b <= max_amount_b, EscrowError::PriceAboveMax);
+        }
+        let bond = escrow.taker_bond.map_or(0, |bond| bond.amount);
+        let deposit = amount_b.checked_add(bond).ok_or(EscrowError::InvalidAmount)?;
         let transfer_ix = system_program::Transfer {
             from: ctx.accounts.taker.to_account_info(),
             to: escrow_account_info,
@@ -194,19 +546,41 @@ pub mod escrow {
                 ctx.accounts.system_program.to_account_info(),
                 transfer_ix,
             ),
-            amount_b,
+            deposit,
         )?;
 
-        // Mark funded and record taker
+        // Mark funded and record taker (and who routed them, if paying for it)
         escrow.is_funded = true;
         escrow.taker = Some(ctx.accounts.taker.key());
+        if let Some(bond) = &escrow.taker_bond {
+            escrow.settle_by_ts = clock.unix_timestamp + i64::from(bond.settle_secs);
+        }
+        if escrow.referral_fee_bps > 0 {
+            if let Some(referrer) = &ctx.accounts.referrer {
+                let referrer = referrer.key();
+                require!(
+                    referrer != ctx.accounts.taker.key()
+                        && referrer != escrow_key
+                        && Some(referrer) != escrow.attester,
+                    EscrowError::SelfReferral
+                );
+                escrow.referrer = Some(referrer);
+            }
+        }
 
-        emit!(EscrowFunded {
+        let event = EscrowFunded {
+            seq: escrow.next_event_seq()?,
             escrow: escrow_key,
             taker: ctx.accounts.taker.key(),
+            amount_a: escrow.amount_a,
             amount_b,
+            oracle_price,
+            taker_bond: bond,
+            settle_by_ts: escrow.taker_bond.map(|_| escrow.settle_by_ts),
+            locked_lamports: escrow.locked_lamports(),
             ts: Clock::get()?.unix_timestamp,
-        });
+        };
+        emit_cpi!(event);
 
         Ok(())
     }
@@ -238,8 +612,28 @@ pub mod escrow {
     ///
     /// This function represents successful completion of the escrow agreement.
     /// Both parties walk away satisfied with their SOL exchanged.
+    ///
+    /// Escrows with an `attester` can't be completed here; see `attest_release`.
+    ///
+    /// ## Referral Fee
+    ///
+    /// If the escrow declares a referral fee, its share of the payment goes to the
+    /// `referrer` instead of the maker. The referrer recorded at funding must be passed;
+    /// if none was, the maker keeps the fee and no referrer may be passed.
+    ///
+    /// ## Memo
+    ///
+    /// Pass `memo_program` to write the escrow's metadata as an SPL memo alongside the
+    /// transfers, for reconciliation. The same goes for every settlement instruction.
+    ///
+    /// ## Taker Bond
+    ///
+    /// A bond posted at funding goes back to the taker with `amount_a`. Completing after
+    /// `settle_by_ts` still works as long as the maker hasn't called `slash_taker_bond`.
     pub fn complete_swap(ctx: Context<CompleteSwap>) -> Result<()> {
         let escrow = &ctx.accounts.escrow;
+        require!(escrow.nft_mint.is_none(), EscrowError::NftEscrow);
+        require!(escrow.attester.is_none(), EscrowError::AttestationRequired);
         require!(escrow.is_active, EscrowError::NotActive);
         require!(escrow.is_funded, EscrowError::NotFunded);
 
@@ -247,13 +641,42 @@ pub mod escrow {
         let taker_key = escrow.taker.ok_or(EscrowError::TakerNotSet)?;
         require_keys_eq!(taker_key, ctx.accounts.taker.key(), EscrowError::Unauthorized);
 
-        // Transfer SOL from escrow PDA to taker (maker's SOL)
-        **ctx.accounts.escrow.to_account_info().try_borrow_mut_lamports()? -= escrow.amount_a;
-        **ctx.accounts.taker.to_account_info().try_borrow_mut_lamports()? += escrow.amount_a;
+        // Pay the referrer their share of the taker's SOL
+        let (referrer, referral_fee) = pay_referrer(&ctx.accounts.escrow, &ctx.accounts.referrer)?;
+        let taker_amount = escrow
+            .amount_a
+            .checked_add(escrow.taker_bond.map_or(0, |bond| bond.amount))
+            .ok_or(EscrowError::ArithmeticOverflow)?;
+        let maker_amount = escrow
+            .amount_b_expected
+            .checked_sub(referral_fee)
+            .and_then(|amount| amount.checked_add(escrow.keeper_tip))
+            .ok_or(EscrowError::ArithmeticOverflow)?;
+
+        // Transfer SOL from escrow PDA to taker (maker's SOL, plus their bond back)
+        let taker = ctx.accounts.taker.to_account_info();
+        pay_side(&mut ctx.accounts.escrow, ReceiptSide::Taker, &taker, taker_amount)?;
 
-        // Transfer SOL from escrow PDA to maker (taker's SOL)
-        **ctx.accounts.escrow.to_account_info().try_borrow_mut_lamports()? -= escrow.amount_b_expected;
-        **ctx.accounts.maker.to_account_info().try_borrow_mut_lamports()? += escrow.amount_b_expected;
+        // Transfer SOL from escrow PDA to maker (the rest of the taker's SOL plus the unused keeper tip)
+        let maker = ctx.accounts.maker.to_account_info();
+        pay_side(&mut ctx.accounts.escrow, ReceiptSide::Maker, &maker, maker_amount)?;
+
+        echo_memo(&ctx.accounts.memo_program, &ctx.accounts.escrow)?;
+
+        // Drop the escrow from the taker's discovery index
+        ctx.accounts.escrow_index.remove(&ctx.accounts.escrow.key())?;
+
+        // Credit both sides' track records
+        let amount_a = ctx.accounts.escrow.amount_a;
+        ctx.accounts
+            .maker_stats
+            .for_wallet(ctx.accounts.maker.key(), ctx.bumps.maker_stats)
+            .record_completed(amount_a);
+        let amount_b = ctx.accounts.escrow.amount_b_expected;
+        ctx.accounts
+            .taker_stats
+            .for_wallet(ctx.accounts.taker.key(), ctx.bumps.taker_stats)
+            .record_completed(amount_b);
 
         // Mark inactive and clear taker
         let escrow = &mut ctx.accounts.escrow;
@@ -262,12 +685,23 @@ pub mod escrow {
         escrow.is_completed = true;
         escrow.taker = None;
 
-        emit!(EscrowCompleted {
+        let event = EscrowCompleted {
+            seq: escrow.next_event_seq()?,
             escrow: escrow.key(),
             maker: escrow.maker,
             taker: ctx.accounts.taker.key(),
+            amount_a: escrow.amount_a,
+            amount_b_expected: escrow.amount_b_expected,
+            keeper_tip: escrow.keeper_tip,
+            attester: None,
+            referrer,
+            referral_fee,
+            locked_lamports: escrow.locked_lamports(),
             ts: Clock::get()?.unix_timestamp,
-        });
+        };
+        emit_cpi!(event);
+
+        close_if_settled(&mut ctx.accounts.escrow, &ctx.accounts.rent_payer)?;
 
         Ok(())
     }
@@ -292,8 +726,11 @@ pub mod escrow {
     ///
     /// ## Security Features
     ///
-    /// - **Maker only**: Only the original maker can cancel
-    /// - **Unfunded only**: Cannot cancel after taker has deposited
+    /// - **Maker only**: Only the original maker can cancel, or their operator with `OPERATOR_CAN_CANCEL`
+    ///   while the escrow is unfunded
+    /// - **Maker paid**: The SOL always goes back to the maker, whoever signs
+    /// - **Unfunded only**: Cannot cancel after taker has deposited (unless the escrow has a
+    ///   cancellation penalty), or once an auction has bids
     /// - **Active only**: Cannot cancel already completed escrows
     ///
     /// ## Why This Function Exists
@@ -301,25 +738,87 @@ pub mod escrow {
     /// Gives makers control over their offers. If no suitable taker appears,
     /// the maker can withdraw their tokens instead of waiting forever.
     /// This is different from `refund_after_expiry` which is for expired escrows.
+    ///
+    /// ## Cancelling After Funding
+    ///
+    /// Escrows created with a `cancel_penalty_bps` can also be cancelled once funded, at a
+    /// price: the taker (passed as `taker`) gets back their payment and any bond, plus
+    /// `cancel_penalty_bps` of `amount_a` as compensation; the maker gets the rest of
+    /// `amount_a` and the tip. The amount paid is recorded in `EscrowCancelled.penalty`.
+    /// Only the maker can cancel a funded escrow; operators can't spend the maker's penalty.
     pub fn cancel_escrow(ctx: Context<CancelEscrow>) -> Result<()> {
         let escrow = &ctx.accounts.escrow;
-        require_keys_eq!(escrow.maker, ctx.accounts.maker.key(), EscrowError::Unauthorized);
+        escrow.check_authority(&ctx.accounts.authority.key(), OPERATOR_CAN_CANCEL)?;
+        require!(escrow.nft_mint.is_none(), EscrowError::NftEscrow);
         require!(escrow.is_active, EscrowError::NotActive);
-        require!(!escrow.is_funded, EscrowError::AlreadyFunded);
+        require!(
+            !escrow.is_funded || escrow.cancel_penalty_bps > 0,
+            EscrowError::AlreadyFunded
+        );
+        require!(escrow.bid_count == 0, EscrowError::AuctionHasBids);
 
-        // Transfer SOL from escrow PDA to maker
-        **ctx.accounts.escrow.to_account_info().try_borrow_mut_lamports()? -= escrow.amount_a;
-        **ctx.accounts.maker.to_account_info().try_borrow_mut_lamports()? += escrow.amount_a;
+        // Once funded, the taker gets their deposit back plus the penalty out of `amount_a`
+        let penalty = if escrow.is_funded {
+            // The penalty is the maker's money, so only the maker can choose to pay it
+            require_keys_eq!(ctx.accounts.authority.key(), escrow.maker, EscrowError::Unauthorized);
+            let taker = ctx
+                .accounts
+                .taker
+                .as_ref()
+                .filter(|taker| escrow.taker == Some(taker.key()))
+                .ok_or(EscrowError::Unauthorized)?
+                .to_account_info();
+            let penalty = escrow.cancel_penalty();
+            let bond = escrow.taker_bond.map_or(0, |bond| bond.amount);
+            let taker_amount = escrow
+                .amount_b_expected
+                .checked_add(bond)
+                .and_then(|amount| amount.checked_add(penalty))
+                .ok_or(EscrowError::ArithmeticOverflow)?;
+            pay_side(&mut ctx.accounts.escrow, ReceiptSide::Taker, &taker, taker_amount)?;
+            penalty
+        } else {
+            0
+        };
+
+        // Transfer SOL (offer less any penalty, plus keeper tip) from escrow PDA to maker
+        let escrow = &ctx.accounts.escrow;
+        let refund_amount = escrow
+            .amount_a
+            .checked_sub(penalty)
+            .and_then(|amount| amount.checked_add(escrow.keeper_tip))
+            .ok_or(EscrowError::ArithmeticOverflow)?;
+        let maker = ctx.accounts.maker.to_account_info();
+        pay_side(&mut ctx.accounts.escrow, ReceiptSide::Maker, &maker, refund_amount)?;
+
+        // Drop the escrow from the taker's discovery index
+        ctx.accounts.escrow_index.remove(&ctx.accounts.escrow.key())?;
+
+        // Count the cancel against the maker
+        ctx.accounts
+            .maker_stats
+            .for_wallet(ctx.accounts.maker.key(), ctx.bumps.maker_stats)
+            .record_cancelled();
 
         // Mark inactive
         let escrow = &mut ctx.accounts.escrow;
         escrow.is_active = false;
+        escrow.is_funded = false;
 
-        emit!(EscrowCancelled {
+        let event = EscrowCancelled {
+            seq: escrow.next_event_seq()?,
             escrow: escrow.key(),
             maker: escrow.maker,
+            amount_a: escrow.amount_a,
+            amount_b_expected: escrow.amount_b_expected,
+            keeper_tip: escrow.keeper_tip,
+            penalty,
+            locked_lamports: escrow.locked_lamports(),
             ts: Clock::get()?.unix_timestamp,
-        });
+        };
+        emit_cpi!(event);
+
+        close_if_settled(&mut ctx.accounts.escrow, &ctx.accounts.rent_payer)?;
 
         Ok(())
     }
@@ -347,7 +846,8 @@ pub mod escrow {
     /// ## Security Features
     ///
     /// - **Time-locked**: Cannot refund until expiry time has passed
-    /// - **Maker only**: Only the original maker can claim the refund
+    /// - **Maker only**: Only the original maker, or their operator with `OPERATOR_CAN_REFUND`,
+    ///   can trigger the refund, and the SOL always goes to the maker
     /// - **Unfunded only**: Cannot refund if taker has deposited tokens
     ///
     /// ## Why This Protection Exists
@@ -360,237 +860,5421 @@ pub mod escrow {
     ///
     /// Unlike `cancel_escrow`, this can only be called after expiry.
     /// It provides automatic protection against stuck funds.
+    ///
+    /// ## Auctions
+    ///
+    /// Only once bidding (and any reveal phase) is over with no winning bid; otherwise
+    /// the auction must be settled. Losing bidders withdraw their own bids.
     pub fn refund_after_expiry(ctx: Context<RefundAfterExpiry>) -> Result<()> {
         let escrow = &ctx.accounts.escrow;
-        require_keys_eq!(escrow.maker, ctx.accounts.maker.key(), EscrowError::Unauthorized);
+        escrow.check_authority(&ctx.accounts.authority.key(), OPERATOR_CAN_REFUND)?;
+        require!(escrow.nft_mint.is_none(), EscrowError::NftEscrow);
         require!(escrow.is_active, EscrowError::NotActive);
         require!(!escrow.is_funded, EscrowError::AlreadyFunded);
+        require!(escrow.highest_bidder.is_none(), EscrowError::AuctionHasBids);
 
         let now = Clock::get()?.unix_timestamp;
-        require!(now > escrow.expiry_ts, EscrowError::NotExpired);
+        require!(now > escrow.bidding_end_ts(), EscrowError::NotExpired);
 
-        // Transfer SOL from escrow PDA to maker
-        **ctx.accounts.escrow.to_account_info().try_borrow_mut_lamports()? -= escrow.amount_a;
-        **ctx.accounts.maker.to_account_info().try_borrow_mut_lamports()? += escrow.amount_a;
+        // Transfer SOL (offer plus keeper tip) from escrow PDA to maker
+        let refund_amount = escrow
+            .amount_a
+            .checked_add(escrow.keeper_tip)
+            .ok_or(EscrowError::ArithmeticOverflow)?;
+        let maker = ctx.accounts.maker.to_account_info();
+        pay_side(&mut ctx.accounts.escrow, ReceiptSide::Maker, &maker, refund_amount)?;
+
+        // Drop the escrow from the taker's discovery index
+        ctx.accounts.escrow_index.remove(&ctx.accounts.escrow.key())?;
+
+        // Count the lapse against the maker
+        ctx.accounts
+            .maker_stats
+            .for_wallet(ctx.accounts.maker.key(), ctx.bumps.maker_stats)
+            .record_expired();
 
         let escrow = &mut ctx.accounts.escrow;
         escrow.is_active = false;
 
-        emit!(EscrowRefunded {
+        let event = EscrowRefunded {
+            seq: escrow.next_event_seq()?,
             escrow: escrow.key(),
             maker: escrow.maker,
+            amount_a: escrow.amount_a,
+            amount_b_expected: escrow.amount_b_expected,
+            keeper_tip: escrow.keeper_tip,
+            attester: None,
+            locked_lamports: escrow.locked_lamports(),
             ts: Clock::get()?.unix_timestamp,
-        });
+        };
+        emit_cpi!(event);
+
+        close_if_settled(&mut ctx.accounts.escrow, &ctx.accounts.rent_payer)?;
 
         Ok(())
     }
-}
 
+    /// # Crank Expired Instruction
+    ///
+    /// **What it does**: Lets anyone (a "keeper") clean up an expired, unfunded escrow
+    /// on behalf of a maker who never came back to call `refund_after_expiry`.
+    ///
+    /// ## Step-by-Step Process
+    ///
+    /// 1. **Check expiry**: Verify current time is past expiry timestamp
+    /// 2. **Validate conditions**: Ensure escrow is still active and unfunded
+    /// 3. **Return SOL**: Transfer `amount_a` back to the maker
+    /// 4. **Pay bounty**: Transfer the maker's `keeper_tip` to the keeper
+    /// 5. **Unlist**: Remove the escrow from the taker's `EscrowIndex` page
+    /// 6. **Emit event**: Log the crank for transparency
+    /// 7. **Close account**: Return the escrow PDA's rent to its `rent_payer`
+    ///
+    /// ## Who Gets the Rent
+    ///
+    /// The escrow's `rent_payer` (the maker, unless a sponsor paid it), as on every other
+    /// terminal path. The keeper's bounty is the tip alone, so cranking an escrow without
+    /// one earns nothing. An escrow still holding a receipt claim or unwithdrawn bids stays
+    /// open until the last is redeemed or withdrawn.
+    ///
+    /// ## Security Features
+    ///
+    /// - **Permissionless**: No maker signature needed, but funds can only go to the stored maker
+    /// - **Time-locked**: Cannot crank until expiry time has passed
+    /// - **Unfunded only**: Funded escrows still belong to the taker to complete
+    /// - **SOL only**: NFT escrows carry no tip and are returned with `cancel_nft_escrow`
+    pub fn crank_expired(ctx: Context<CrankExpired>) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+        require!(escrow.nft_mint.is_none(), EscrowError::NftEscrow);
+        require!(escrow.is_active, EscrowError::NotActive);
+        require!(!escrow.is_funded, EscrowError::AlreadyFunded);
+        require!(escrow.highest_bidder.is_none(), EscrowError::AuctionHasBids);
 
-#[derive(Accounts)]
-#[instruction(escrow_id: u64)]
-pub struct CreateEscrow<'info> {
-    /// Escrow PDA: seeds = ["escrow", maker, escrow_id]
-    #[account(
-        init,
-        payer = maker,
-        space = EscrowAccount::calculate_max_space(),
-        seeds = [b"escrow", maker.key().as_ref(), &escrow_id.to_le_bytes()],
-        bump
-    )]
-    pub escrow: Account<'info, EscrowAccount>,
+        let now = Clock::get()?.unix_timestamp;
+        require!(now > escrow.bidding_end_ts(), EscrowError::NotExpired);
 
-    /// Maker creating the escrow
-    #[account(mut)]
-    pub maker: Signer<'info>,
+        let amount_a = escrow.amount_a;
+        let amount_b_expected = escrow.amount_b_expected;
+        let keeper_tip = escrow.keeper_tip;
 
-    pub system_program: Program<'info, System>,
-}
+        // Transfer SOL from escrow PDA to maker
+        let maker = ctx.accounts.maker.to_account_info();
+        pay_side(&mut ctx.accounts.escrow, ReceiptSide::Maker, &maker, amount_a)?;
 
-#[derive(Accounts)]
-pub struct FundEscrow<'info> {
-    /// Escrow must exist (PDA)
-    #[account(mut, has_one = maker)]
-    pub escrow: Account<'info, EscrowAccount>,
+        // Transfer the tip from escrow PDA to keeper
+        **ctx.accounts.escrow.to_account_info().try_borrow_mut_lamports()? -= keeper_tip;
+        **ctx.accounts.keeper.to_account_info().try_borrow_mut_lamports()? += keeper_tip;
 
-    /// Taker funds the escrow
-    #[account(mut)]
-    pub taker: Signer<'info>,
+        // Drop the escrow from the taker's discovery index
+        ctx.accounts.escrow_index.remove(&ctx.accounts.escrow.key())?;
 
-    /// Maker (for has_one check)
-    /// CHECK: This account is used for has_one constraint validation on the escrow account
-    pub maker: UncheckedAccount<'info>,
+        // Count the lapse against the maker
+        ctx.accounts
+            .maker_stats
+            .for_wallet(ctx.accounts.maker.key(), ctx.bumps.maker_stats)
+            .record_expired();
 
-    pub system_program: Program<'info, System>,
-}
+        ctx.accounts.escrow.is_active = false;
 
-#[derive(Accounts)]
-pub struct CompleteSwap<'info> {
-    #[account(mut, has_one = maker)]
-    pub escrow: Account<'info, EscrowAccount>,
+        let event = KeeperCranked {
+            seq: ctx.accounts.escrow.next_event_seq()?,
+            escrow: ctx.accounts.escrow.key(),
+            maker: ctx.accounts.maker.key(),
+            keeper: ctx.accounts.keeper.key(),
+            amount_a,
+            amount_b_expected,
+            bounty: keeper_tip,
+            locked_lamports: ctx.accounts.escrow.locked_lamports(),
+            ts: now,
+        };
+        emit_cpi!(event);
 
-    /// Taker finalizes the swap (must equal escrow.taker)
-    #[account(mut)]
-    pub taker: Signer<'info>,
+        close_if_settled(&mut ctx.accounts.escrow, &ctx.accounts.rent_payer)?;
 
-    /// Maker (not signer here)
-    /// CHECK: This account is validated through the escrow's maker field constraint
-    #[account(mut)]
-    pub maker: UncheckedAccount<'info>,
+        Ok(())
+    }
 
-    pub system_program: Program<'info, System>,
-}
+    /// # Create NFT Escrow Instruction
+    ///
+    /// **What it does**: The maker offers a single NFT instead of SOL and names a SOL price.
+    ///
+    /// ## Step-by-Step Process
+    ///
+    /// 1. **Validate inputs**: Ensure the price is > 0 and expiry is in future
+    /// 2. **Check the NFT**: The mint must have a supply of 1, 0 decimals, and no mint authority
+    ///    other than its Metaplex master edition, so no second token can ever be minted
+    /// 3. **Check the collection**: If `collection` is set, the Metaplex metadata must list it as verified
+    /// 4. **Create escrow account**: Same PDA, ID and index rules as `create_escrow`
+    /// 5. **Lock the NFT**: Move it into a vault (the escrow PDA's associated token account)
+    /// 6. **Emit event**: `EscrowCreated` with `nft_mint` set and `amount_a` = 1
+    ///
+    /// ## How the Trade Settles
+    ///
+    /// - The taker funds with `fund_escrow` exactly as for SOL escrows
+    /// - `complete_nft_swap` hands the NFT to the taker and the SOL to the maker
+    /// - `cancel_nft_escrow` returns an unfunded NFT to the maker, before or after expiry
+    ///
+    /// ## Dutch Auctions
+    ///
+    /// Pass `dutch_auction` to sell the NFT at a falling price, as in `create_escrow`.
+    ///
+    /// ## Sponsored Rent
+    ///
+    /// As in `create_escrow`, `rent_payer` pays for the new accounts, the vault included,
+    /// and gets the vault's and the escrow's rent back when the NFT leaves the vault.
+    ///
+    /// ## Security Checks
+    ///
+    /// - Only classic SPL Token NFTs; programmable NFTs are frozen and cannot be deposited
+    /// - Collection membership must be **verified** by the collection authority, not just claimed
+    /// - The metadata account is checked to be the Metaplex PDA for this mint
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_nft_escrow(
+        ctx: Context<CreateNftEscrow>,
+        escrow_id: Option<u64>,
+        amount_b_expected: u64,
+        expiry_ts: i64,
+        taker_pubkey: Pubkey,
+        collection: Option<Pubkey>,
+        index_page: u32,
+        dutch_auction: Option<DutchAuctionTerms>,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
 
-#[derive(Accounts)]
-pub struct CancelEscrow<'info> {
-    #[account(mut, has_one = maker)]
-    pub escrow: Account<'info, EscrowAccount>,
+        // Only accept NFTs from the requested collection
+        if let Some(collection) = collection {
+            let verified = ctx
+                .accounts
+                .nft_metadata
+                .as_ref()
+                .and_then(|metadata| metadata.collection.as_ref())
+                .is_some_and(|member| member.verified && member.key == collection);
+            require!(verified, EscrowError::NftCollectionMismatch);
+        }
 
-    #[account(mut)]
-    pub maker: Signer<'info>,
+        // Consume the ID and advance the maker's counter past it
+        let maker_profile = &mut ctx.accounts.maker_profile;
+        let escrow_id = maker_profile.assign_escrow_id(escrow_id)?;
+        let event_seq = maker_profile.reserve_event_seqs()?;
+        maker_profile.maker = ctx.accounts.maker.key();
+        maker_profile.bump = ctx.bumps.maker_profile;
 
-    pub system_program: Program<'info, System>,
-}
+        let escrow_key = ctx.accounts.escrow.key();
 
-#[derive(Accounts)]
-pub struct RefundAfterExpiry<'info> {
-    #[account(mut, has_one = maker)]
-    pub escrow: Account<'info, EscrowAccount>,
+        // Check the terms and initialize escrow account state; the NFT counts as `amount_a` = 1
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.set_inner(EscrowAccount::init(
+            NewEscrow {
+                maker: ctx.accounts.maker.key(),
+                escrow_id,
+                bump: ctx.bumps.escrow,
+                rent_payer: ctx.accounts.rent_payer.key(),
+                amount_a: 1,
+                amount_b_expected,
+                expiry_ts,
+                taker_pubkey,
+                keeper_tip: 0,
+                index_page,
+                nft_mint: Some(ctx.accounts.nft_mint.key()),
+                options: EscrowOptions {
+                    dutch_auction,
+                    ..EscrowOptions::default()
+                },
+                event_seq,
+            },
+            now,
+        )?);
 
-    #[account(mut)]
-    pub maker: Signer<'info>,
+        // List the escrow on the taker's discovery index
+        let escrow_index = &mut ctx.accounts.escrow_index;
+        escrow_index.taker = taker_pubkey;
+        escrow_index.page = index_page;
+        escrow_index.bump = ctx.bumps.escrow_index;
+        escrow_index.push(escrow_key)?;
 
-    pub system_program: Program<'info, System>,
-}
+        // Move the NFT from the maker into the vault
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.maker_nft_account.to_account_info(),
+                    to: ctx.accounts.vault.to_account_info(),
+                    authority: ctx.accounts.maker.to_account_info(),
+                },
+            ),
+            1,
+        )?;
 
+        let event = escrow.created_event(escrow_key, now)?;
+        emit_cpi!(event);
 
-/// # Escrow Account Structure
-///
-/// This is the **main data structure** that stores all information about an escrow transaction.
-/// Think of it as a digital "contract" that both parties agree to follow.
-///
-/// ## What It Stores
-///
-/// - **Unique ID**: Each escrow has a unique identifier (like a transaction number)
-/// - **SOL Details**: How much SOL is being exchanged
-/// - **Party Information**: Who created the escrow and who can take it
-/// - **Time Controls**: When the escrow expires and when it was created
-/// - **Current State**: Whether it's waiting for funding, ready to complete, or expired
+        Ok(())
+    }
+
+    /// # Complete NFT Swap Instruction
+    ///
+    /// **What it does**: Settles a funded NFT escrow: the NFT goes to the taker, the SOL to the maker.
+    ///
+    /// ## Step-by-Step Process
+    ///
+    /// 1. **Validate conditions**: Escrow is an active, funded NFT escrow and caller is the taker
+    /// 2. **Deliver the NFT**: Vault → taker's associated token account (created if missing)
+    /// 3. **Close the vault**: Its rent goes back to the `rent_payer` who paid it
+    /// 4. **Pay the maker**: Transfer `amount_b_expected` from the escrow PDA
+    /// 5. **Update state**: Mark completed, unlist, emit `EscrowCompleted`
+    pub fn complete_nft_swap(ctx: Context<CompleteNftSwap>) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+        require!(escrow.is_active, EscrowError::NotActive);
+        require!(escrow.is_funded, EscrowError::NotFunded);
+
+        let taker_key = escrow.taker.ok_or(EscrowError::TakerNotSet)?;
+        require_keys_eq!(taker_key, ctx.accounts.taker.key(), EscrowError::Unauthorized);
+
+        // Release the NFT to the taker and close the vault back to whoever paid its rent
+        let escrow_id = escrow.escrow_id.to_le_bytes();
+        let seeds: &[&[u8]] = &[b"escrow", escrow.creator.as_ref(), &escrow_id, &[escrow.bump]];
+        release_nft(
+            &ctx.accounts.token_program,
+            &ctx.accounts.vault,
+            ctx.accounts.taker_nft_account.to_account_info(),
+            ctx.accounts.escrow.to_account_info(),
+            ctx.accounts.rent_payer.to_account_info(),
+            seeds,
+        )?;
+
+        // Transfer SOL from escrow PDA to maker (the taker's payment)
+        let maker_amount = escrow.amount_b_expected;
+        **ctx.accounts.escrow.to_account_info().try_borrow_mut_lamports()? -= maker_amount;
+        **ctx.accounts.maker.to_account_info().try_borrow_mut_lamports()? += maker_amount;
+        echo_memo(&ctx.accounts.memo_program, &ctx.accounts.escrow)?;
+
+        // Drop the escrow from the taker's discovery index
+        ctx.accounts.escrow_index.remove(&ctx.accounts.escrow.key())?;
+
+        // Credit both sides' track records; the maker's volume is the price their NFT fetched
+        ctx.accounts
+            .maker_stats
+            .for_wallet(ctx.accounts.maker.key(), ctx.bumps.maker_stats)
+            .record_completed(maker_amount);
+        ctx.accounts
+            .taker_stats
+            .for_wallet(ctx.accounts.taker.key(), ctx.bumps.taker_stats)
+            .record_completed(maker_amount);
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.is_active = false;
+        escrow.is_funded = false;
+        escrow.is_completed = true;
+        escrow.taker = None;
+
+        let event = EscrowCompleted {
+            seq: escrow.next_event_seq()?,
+            escrow: escrow.key(),
+            maker: escrow.maker,
+            taker: ctx.accounts.taker.key(),
+            amount_a: escrow.amount_a,
+            amount_b_expected: escrow.amount_b_expected,
+            keeper_tip: 0,
+            attester: None,
+            referrer: None,
+            referral_fee: 0,
+            locked_lamports: escrow.locked_lamports(),
+            ts: Clock::get()?.unix_timestamp,
+        };
+        emit_cpi!(event);
+
+        close_if_settled(&mut ctx.accounts.escrow, &ctx.accounts.rent_payer)?;
+
+        Ok(())
+    }
+
+    /// # Cancel NFT Escrow Instruction
+    ///
+    /// **What it does**: Maker takes back the NFT from an unfunded NFT escrow.
+    ///
+    /// ## When This Can Be Used
+    ///
+    /// Any time before the taker funds, including after expiry, so it covers both
+    /// `cancel_escrow` and `refund_after_expiry` for NFT escrows.
+    ///
+    /// ## Step-by-Step Process
+    ///
+    /// 1. **Validate conditions**: Caller is maker, escrow is an active, unfunded NFT escrow
+    /// 2. **Return the NFT**: Vault → maker's associated token account (created if missing)
+    /// 3. **Close the vault**: Rent goes back to the escrow's `rent_payer`
+    /// 4. **Update state**: Mark inactive, unlist, count a cancel (or, once expired, a lapse)
+    ///    against the maker, emit `EscrowCancelled`
+    pub fn cancel_nft_escrow(ctx: Context<CancelNftEscrow>) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+        require!(escrow.is_active, EscrowError::NotActive);
+        require!(!escrow.is_funded, EscrowError::AlreadyFunded);
+
+        // Return the NFT to the maker and close the vault
+        let escrow_id = escrow.escrow_id.to_le_bytes();
+        let seeds: &[&[u8]] = &[b"escrow", escrow.creator.as_ref(), &escrow_id, &[escrow.bump]];
+        release_nft(
+            &ctx.accounts.token_program,
+            &ctx.accounts.vault,
+            ctx.accounts.maker_nft_account.to_account_info(),
+            ctx.accounts.escrow.to_account_info(),
+            ctx.accounts.rent_payer.to_account_info(),
+            seeds,
+        )?;
+
+        // Drop the escrow from the taker's discovery index
+        ctx.accounts.escrow_index.remove(&ctx.accounts.escrow.key())?;
+
+        // Count it against the maker as `cancel_escrow` or `refund_after_expiry` would
+        let now = Clock::get()?.unix_timestamp;
+        if now > ctx.accounts.escrow.expiry_ts {
+            ctx.accounts
+                .maker_stats
+                .for_wallet(ctx.accounts.maker.key(), ctx.bumps.maker_stats)
+                .record_expired();
+        } else {
+            ctx.accounts
+                .maker_stats
+                .for_wallet(ctx.accounts.maker.key(), ctx.bumps.maker_stats)
+                .record_cancelled();
+        }
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.is_active = false;
+
+        let event = EscrowCancelled {
+            seq: escrow.next_event_seq()?,
+            escrow: escrow.key(),
+            maker: escrow.maker,
+            amount_a: escrow.amount_a,
+            amount_b_expected: escrow.amount_b_expected,
+            keeper_tip: 0,
+            penalty: 0,
+            locked_lamports: escrow.locked_lamports(),
+            ts: now,
+        };
+        emit_cpi!(event);
+
+        close_if_settled(&mut ctx.accounts.escrow, &ctx.accounts.rent_payer)?;
+
+        Ok(())
+    }
+
+    /// # Attest Release Instruction
+    ///
+    /// **What it does**: The escrow's attester confirms the real-world condition was met
+    /// (e.g. goods delivered) and the swap settles exactly like `complete_swap`.
+    ///
+    /// ## Step-by-Step Process
+    ///
+    /// 1. **Validate conditions**: Escrow is active and funded, signer is the escrow's attester
+    /// 2. **Atomic exchange**: `amount_a` to the taker, `amount_b_expected` plus the tip to the maker,
+    ///    less any referral fee (see `complete_swap`)
+    /// 3. **Update state**: Mark completed, unlist, emit `EscrowCompleted` naming the attester
+    ///
+    /// ## Trust Model
+    ///
+    /// The attester can only choose between the two outcomes the parties agreed to;
+    /// it can never receive funds. It is trusted to answer, though: a funded gated
+    /// escrow stays locked until it signs one way or the other.
+    pub fn attest_release(ctx: Context<Attest>) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+        require!(escrow.nft_mint.is_none(), EscrowError::NftEscrow);
+        require!(
+            escrow.attester == Some(ctx.accounts.attester.key()),
+            EscrowError::InvalidAttester
+        );
+        require!(escrow.is_active, EscrowError::NotActive);
+        require!(escrow.is_funded, EscrowError::NotFunded);
+
+        // Pay the referrer their share of the taker's SOL
+        let (referrer, referral_fee) = pay_referrer(&ctx.accounts.escrow, &ctx.accounts.referrer)?;
+        let taker_amount = escrow.amount_a;
+        let maker_amount = escrow
+            .amount_b_expected
+            .checked_sub(referral_fee)
+            .and_then(|amount| amount.checked_add(escrow.keeper_tip))
+            .ok_or(EscrowError::ArithmeticOverflow)?;
+
+        // Transfer SOL from escrow PDA to taker (maker's SOL)
+        let taker = ctx.accounts.taker.to_account_info();
+        pay_side(&mut ctx.accounts.escrow, ReceiptSide::Taker, &taker, taker_amount)?;
+
+        // Transfer SOL from escrow PDA to maker (the rest of the taker's SOL plus the unused keeper tip)
+        let maker = ctx.accounts.maker.to_account_info();
+        pay_side(&mut ctx.accounts.escrow, ReceiptSide::Maker, &maker, maker_amount)?;
+        echo_memo(&ctx.accounts.memo_program, &ctx.accounts.escrow)?;
+
+        // Drop the escrow from the taker's discovery index
+        ctx.accounts.escrow_index.remove(&ctx.accounts.escrow.key())?;
+
+        // Credit both sides' track records
+        let amount_a = ctx.accounts.escrow.amount_a;
+        ctx.accounts
+            .maker_stats
+            .for_wallet(ctx.accounts.maker.key(), ctx.bumps.maker_stats)
+            .record_completed(amount_a);
+        let amount_b = ctx.accounts.escrow.amount_b_expected;
+        ctx.accounts
+            .taker_stats
+            .for_wallet(ctx.accounts.taker.key(), ctx.bumps.taker_stats)
+            .record_completed(amount_b);
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.is_active = false;
+        escrow.is_funded = false;
+        escrow.is_completed = true;
+        escrow.taker = None;
+
+        let event = EscrowCompleted {
+            seq: escrow.next_event_seq()?,
+            escrow: escrow.key(),
+            maker: escrow.maker,
+            taker: ctx.accounts.taker.key(),
+            amount_a: escrow.amount_a,
+            amount_b_expected: escrow.amount_b_expected,
+            keeper_tip: escrow.keeper_tip,
+            attester: Some(ctx.accounts.attester.key()),
+            referrer,
+            referral_fee,
+            locked_lamports: escrow.locked_lamports(),
+            ts: Clock::get()?.unix_timestamp,
+        };
+        emit_cpi!(event);
+
+        close_if_settled(&mut ctx.accounts.escrow, &ctx.accounts.rent_payer)?;
+
+        Ok(())
+    }
+
+    /// # Attest Refund Instruction
+    ///
+    /// **What it does**: The escrow's attester reports the condition failed and both
+    /// deposits go back: `amount_a` plus the tip to the maker, `amount_b_expected` to the taker.
+    ///
+    /// ## Step-by-Step Process
+    ///
+    /// 1. **Validate conditions**: Escrow is active and funded, signer is the escrow's attester
+    /// 2. **Unwind**: Return each party's deposit
+    /// 3. **Update state**: Mark inactive, unlist, emit `EscrowRefunded` naming the attester
+    pub fn attest_refund(ctx: Context<Attest>) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+        require!(escrow.nft_mint.is_none(), EscrowError::NftEscrow);
+        require!(
+            escrow.attester == Some(ctx.accounts.attester.key()),
+            EscrowError::InvalidAttester
+        );
+        require!(escrow.is_active, EscrowError::NotActive);
+        require!(escrow.is_funded, EscrowError::NotFunded);
+
+        let maker_amount = escrow
+            .amount_a
+            .checked_add(escrow.keeper_tip)
+            .ok_or(EscrowError::ArithmeticOverflow)?;
+        let taker_amount = escrow.amount_b_expected;
+
+        // Return the maker's offer plus keeper tip
+        let maker = ctx.accounts.maker.to_account_info();
+        pay_side(&mut ctx.accounts.escrow, ReceiptSide::Maker, &maker, maker_amount)?;
+
+        // Return the taker's payment
+        let taker = ctx.accounts.taker.to_account_info();
+        pay_side(&mut ctx.accounts.escrow, ReceiptSide::Taker, &taker, taker_amount)?;
+        echo_memo(&ctx.accounts.memo_program, &ctx.accounts.escrow)?;
+
+        // Drop the escrow from the taker's discovery index
+        ctx.accounts.escrow_index.remove(&ctx.accounts.escrow.key())?;
+
+        // The attester ruled against the maker
+        ctx.accounts
+            .maker_stats
+            .for_wallet(ctx.accounts.maker.key(), ctx.bumps.maker_stats)
+            .record_dispute_lost();
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.is_active = false;
+        escrow.is_funded = false;
+
+        let event = EscrowRefunded {
+            seq: escrow.next_event_seq()?,
+            escrow: escrow.key(),
+            maker: escrow.maker,
+            amount_a: escrow.amount_a,
+            amount_b_expected: escrow.amount_b_expected,
+            keeper_tip: escrow.keeper_tip,
+            attester: Some(ctx.accounts.attester.key()),
+            locked_lamports: escrow.locked_lamports(),
+            ts: Clock::get()?.unix_timestamp,
+        };
+        emit_cpi!(event);
+
+        close_if_settled(&mut ctx.accounts.escrow, &ctx.accounts.rent_payer)?;
+
+        Ok(())
+    }
+
+    /// # Slash Taker Bond Instruction
+    ///
+    /// **What it does**: Unwinds a funded escrow whose taker missed the settlement deadline,
+    /// paying their bond to the maker as compensation.
+    ///
+    /// ## Step-by-Step Process
+    ///
+    /// 1. **Validate conditions**: Signer is the maker (or their operator with `OPERATOR_CAN_REFUND`),
+    ///    escrow is active, funded and bonded, and `settle_by_ts` has passed
+    /// 2. **Unwind**: `amount_a`, the keeper tip and the taker's bond to the maker;
+    ///    `amount_b_expected` back to the taker
+    /// 3. **Update state**: Mark inactive, unlist, emit `TakerBondSlashed`
+    ///
+    /// ## Why the Maker Decides
+    ///
+    /// The taker may still complete late; slashing is the maker's choice to stop waiting,
+    /// so nobody else can force it. Funds only go to the stored maker and taker.
+    pub fn slash_taker_bond(ctx: Context<SlashTakerBond>) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+        escrow.check_authority(&ctx.accounts.authority.key(), OPERATOR_CAN_REFUND)?;
+        require!(escrow.is_active, EscrowError::NotActive);
+        require!(escrow.is_funded, EscrowError::NotFunded);
+        let bond = escrow.taker_bond.ok_or(EscrowError::NoTakerBond)?.amount;
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(now > escrow.settle_by_ts, EscrowError::SettlementWindowOpen);
+
+        let maker_amount = escrow
+            .amount_a
+            .checked_add(escrow.keeper_tip)
+            .and_then(|amount| amount.checked_add(bond))
+            .ok_or(EscrowError::ArithmeticOverflow)?;
+        let taker_amount = escrow.amount_b_expected;
+
+        // Return the maker's offer and tip, plus the forfeited bond
+        let maker = ctx.accounts.maker.to_account_info();
+        pay_side(&mut ctx.accounts.escrow, ReceiptSide::Maker, &maker, maker_amount)?;
+
+        // Return the taker's payment
+        let taker = ctx.accounts.taker.to_account_info();
+        pay_side(&mut ctx.accounts.escrow, ReceiptSide::Taker, &taker, taker_amount)?;
+        echo_memo(&ctx.accounts.memo_program, &ctx.accounts.escrow)?;
+
+        // Drop the escrow from the taker's discovery index
+        ctx.accounts.escrow_index.remove(&ctx.accounts.escrow.key())?;
+
+        // The taker missed their settlement deadline
+        ctx.accounts
+            .taker_stats
+            .for_wallet(ctx.accounts.taker.key(), ctx.bumps.taker_stats)
+            .record_dispute_lost();
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.is_active = false;
+        escrow.is_funded = false;
+
+        let event = TakerBondSlashed {
+            seq: escrow.next_event_seq()?,
+            escrow: escrow.key(),
+            maker: escrow.maker,
+            taker: ctx.accounts.taker.key(),
+            amount_a: escrow.amount_a,
+            amount_b_expected: escrow.amount_b_expected,
+            bond,
+            locked_lamports: escrow.locked_lamports(),
+            ts: now,
+        };
+        emit_cpi!(event);
+
+        close_if_settled(&mut ctx.accounts.escrow, &ctx.accounts.rent_payer)?;
+
+        Ok(())
+    }
+
+    /// # Amend Escrow Instruction
+    ///
+    /// **What it does**: The maker changes the price and expiry of an escrow no taker has funded yet.
+    ///
+    /// ## Step-by-Step Process
+    ///
+    /// 1. **Validate conditions**: Escrow is active and unfunded, new expiry is in the future
+    /// 2. **Update terms**: Store the new `amount_b_expected` (ignored for oracle-priced and Dutch auction escrows) and expiry
+    /// 3. **Bump `terms_version`**: Any multisig proposal gathered for the old terms goes stale
+    /// 4. **Emit event**: `EscrowAmended` with the new terms
+    ///
+    /// The maker's operator may also amend if it holds `OPERATOR_CAN_AMEND`.
+    /// Escrows made by a `Multisig` are amended through a proposal instead.
+    pub fn amend_escrow(
+        ctx: Context<AmendEscrow>,
+        amount_b_expected: u64,
+        expiry_ts: i64,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.check_authority(&ctx.accounts.authority.key(), OPERATOR_CAN_AMEND)?;
+        escrow.amend(amount_b_expected, expiry_ts, now)?;
+
+        let event = EscrowAmended {
+            seq: escrow.next_event_seq()?,
+            escrow: escrow.key(),
+            maker: escrow.maker,
+            amount_b_expected: escrow.amount_b_expected,
+            expiry_ts: escrow.expiry_ts,
+            terms_version: escrow.terms_version,
+            locked_lamports: escrow.locked_lamports(),
+            ts: now,
+        };
+        emit_cpi!(event);
+
+        Ok(())
+    }
+
+    /// # Set Operator Instruction
+    ///
+    /// **What it does**: The maker delegates management of one escrow to an `operator`
+    /// key (e.g. a market-making bot) so the bot never holds the maker's main key.
+    ///
+    /// ## Permissions
+    ///
+    /// `permissions` is any non-empty combination of `OPERATOR_CAN_CANCEL`,
+    /// `OPERATOR_CAN_AMEND` and `OPERATOR_CAN_REFUND`. Calling again replaces the
+    /// operator and its permissions.
+    ///
+    /// ## Security Features
+    ///
+    /// - **Maker only**: Operators can't appoint other operators
+    /// - **Funds stay with the maker**: Every path an operator can trigger pays the stored maker
+    pub fn set_operator(ctx: Context<SetOperator>, operator: Pubkey, permissions: u8) -> Result<()> {
+        let all = OPERATOR_CAN_CANCEL | OPERATOR_CAN_AMEND | OPERATOR_CAN_REFUND;
+        require!(
+            permissions != 0 && permissions & !all == 0,
+            EscrowError::InvalidOperatorPermissions
+        );
+
+        let escrow = &mut ctx.accounts.escrow;
+        require!(escrow.is_active, EscrowError::NotActive);
+        escrow.operator = Some(operator);
+        escrow.operator_permissions = permissions;
+
+        let event = OperatorChanged {
+            seq: escrow.next_event_seq()?,
+            escrow: escrow.key(),
+            maker: escrow.maker,
+            operator: Some(operator),
+            permissions,
+            locked_lamports: escrow.locked_lamports(),
+            ts: Clock::get()?.unix_timestamp,
+        };
+        emit_cpi!(event);
+
+        Ok(())
+    }
+
+    /// # Revoke Operator Instruction
+    ///
+    /// **What it does**: The maker removes the escrow's operator; it loses every permission at once.
+    pub fn revoke_operator(ctx: Context<SetOperator>) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        require!(escrow.operator.is_some(), EscrowError::NoOperator);
+        escrow.operator = None;
+        escrow.operator_permissions = 0;
+
+        let event = OperatorChanged {
+            seq: escrow.next_event_seq()?,
+            escrow: escrow.key(),
+            maker: escrow.maker,
+            operator: None,
+            permissions: 0,
+            locked_lamports: escrow.locked_lamports(),
+            ts: Clock::get()?.unix_timestamp,
+        };
+        emit_cpi!(event);
+
+        Ok(())
+    }
+
+    /// # Transfer Maker Rights Instruction
+    ///
+    /// **What it does**: The maker novates their side of an open escrow to `new_maker`,
+    /// who from then on gets the payment, refunds and every maker-only instruction.
+    ///
+    /// ## Step-by-Step Process
+    ///
+    /// 1. **Validate conditions**: Escrow is active, signer is the maker, `new_maker` differs
+    /// 2. **Check consent**: If the escrow requires it, the taker must co-sign as `counterparty`
+    /// 3. **Hand over**: Set `maker`, drop the old maker's operator, emit `MakerRightsTransferred`
+    ///
+    /// ## Notes
+    ///
+    /// - The escrow keeps its address; `creator` remembers the maker its PDA was derived from
+    /// - Consent is only possible once there is a taker to give it, so allowlisted escrows
+    ///   transfer freely until funded
+    pub fn transfer_maker_rights(ctx: Context<TransferMakerRights>, new_maker: Pubkey) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        require!(escrow.is_active, EscrowError::NotActive);
+        require_keys_neq!(new_maker, escrow.maker, EscrowError::InvalidTransfer);
+        let counterparty_consent =
+            check_transfer_consent(escrow, escrow.taker, &ctx.accounts.counterparty)?;
+
+        let old_maker = escrow.maker;
+        escrow.maker = new_maker;
+        escrow.operator = None;
+        escrow.operator_permissions = 0;
+
+        let event = MakerRightsTransferred {
+            seq: escrow.next_event_seq()?,
+            escrow: escrow.key(),
+            old_maker,
+            new_maker,
+            counterparty_consent,
+            locked_lamports: escrow.locked_lamports(),
+            ts: Clock::get()?.unix_timestamp,
+        };
+        emit_cpi!(event);
+
+        Ok(())
+    }
+
+    /// # Transfer Taker Rights Instruction
+    ///
+    /// **What it does**: The taker novates their side to `new_taker`: the right to fund,
+    /// or once funded, their deposit together with the right to complete and be refunded.
+    ///
+    /// ## Step-by-Step Process
+    ///
+    /// 1. **Validate conditions**: Escrow is active, signer is the taker, `new_taker` differs
+    /// 2. **Check consent**: If the escrow requires it, the maker must co-sign as `counterparty`
+    /// 3. **Check reputation**: `new_taker_stats` must meet the escrow's `min_taker_completed`,
+    ///    as at funding, so a gated escrow can't be handed to a wallet that couldn't fund it
+    /// 4. **Relist**: Move the escrow from its index page to `new_taker`'s page `index_page`
+    ///    (created if needed, paid by the old taker)
+    /// 5. **Hand over**: Set `taker`, emit `TakerRightsTransferred`
+    ///
+    /// Allowlisted escrows have no taker until funded, so there is nothing to transfer before then.
+    pub fn transfer_taker_rights(
+        ctx: Context<TransferTakerRights>,
+        new_taker: Pubkey,
+        index_page: u32,
+    ) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+        require!(escrow.is_active, EscrowError::NotActive);
+        let old_taker = escrow.taker.ok_or(EscrowError::TakerNotSet)?;
+        require_keys_eq!(old_taker, ctx.accounts.taker.key(), EscrowError::Unauthorized);
+        require_keys_neq!(new_taker, old_taker, EscrowError::InvalidTransfer);
+        let counterparty_consent =
+            check_transfer_consent(escrow, Some(escrow.maker), &ctx.accounts.counterparty)?;
+        check_taker_reputation(escrow, &ctx.accounts.new_taker_stats)?;
+
+        // Move the listing to the new taker's discovery index
+        let escrow_key = escrow.key();
+        ctx.accounts.escrow_index.remove(&escrow_key)?;
+        let new_escrow_index = &mut ctx.accounts.new_escrow_index;
+        new_escrow_index.taker = new_taker;
+        new_escrow_index.page = index_page;
+        new_escrow_index.bump = ctx.bumps.new_escrow_index;
+        new_escrow_index.push(escrow_key)?;
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.taker = Some(new_taker);
+        escrow.index_taker = new_taker;
+        escrow.index_page = index_page;
+
+        let event = TakerRightsTransferred {
+            seq: escrow.next_event_seq()?,
+            escrow: escrow_key,
+            old_taker,
+            new_taker,
+            counterparty_consent,
+            locked_lamports: escrow.locked_lamports(),
+            ts: Clock::get()?.unix_timestamp,
+        };
+        emit_cpi!(event);
+
+        Ok(())
+    }
+
+    /// # Mint Receipt Instruction
+    ///
+    /// **What it does**: Tokenizes one side's payout. The depositor gets a single receipt
+    /// token, and from then on that side's SOL is paid to whoever redeems it instead of
+    /// to the stored `maker`/`taker`.
+    ///
+    /// ## Step-by-Step Process
+    ///
+    /// 1. **Validate conditions**: Active SOL escrow, no receipt yet for `side`; the signer is
+    ///    the maker, or for the taker side the taker of a funded escrow
+    /// 2. **Mint**: Create the receipt mint `["receipt", escrow, side]` (0 decimals, the escrow
+    ///    as mint authority) and mint one token to the depositor's associated token account
+    /// 3. **Emit event**: `ReceiptMinted`
+    ///
+    /// ## How Payouts Change
+    ///
+    /// Settlement, cancel and refund instructions no longer send that side's lamports out;
+    /// they add them to `maker_claim` or `taker_claim` and leave them in the escrow until
+    /// `redeem_receipt`. Control is unchanged: the maker and taker keys still sign as before.
+    ///
+    /// Clients add this to the `create_escrow` or `fund_escrow` transaction to deposit and
+    /// tokenize atomically.
+    pub fn mint_receipt(ctx: Context<MintReceipt>, side: ReceiptSide) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+        require!(escrow.nft_mint.is_none(), EscrowError::NftEscrow);
+        require!(escrow.is_active, EscrowError::NotActive);
+        require!(escrow.receipt(side).is_none(), EscrowError::ReceiptAlreadyMinted);
+        let depositor = ctx.accounts.depositor.key();
+        match side {
+            ReceiptSide::Maker => require_keys_eq!(depositor, escrow.maker, EscrowError::Unauthorized),
+            ReceiptSide::Taker => {
+                require!(escrow.is_funded, EscrowError::NotFunded);
+                require!(escrow.taker == Some(depositor), EscrowError::Unauthorized);
+            }
+        }
+
+        let escrow_id = escrow.escrow_id.to_le_bytes();
+        let seeds: &[&[u8]] = &[b"escrow", escrow.creator.as_ref(), &escrow_id, &[escrow.bump]];
+        token::mint_to(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                MintTo {
+                    mint: ctx.accounts.receipt_mint.to_account_info(),
+                    to: ctx.accounts.depositor_receipt_account.to_account_info(),
+                    authority: ctx.accounts.escrow.to_account_info(),
+                },
+                &[seeds],
+            ),
+            1,
+        )?;
+
+        let mint = ctx.accounts.receipt_mint.key();
+        let escrow = &mut ctx.accounts.escrow;
+        match side {
+            ReceiptSide::Maker => escrow.maker_receipt = Some(mint),
+            ReceiptSide::Taker => escrow.taker_receipt = Some(mint),
+        }
+
+        let event = ReceiptMinted {
+            seq: escrow.next_event_seq()?,
+            escrow: escrow.key(),
+            side,
+            mint,
+            owner: depositor,
+            locked_lamports: escrow.locked_lamports(),
+            ts: Clock::get()?.unix_timestamp,
+        };
+        emit_cpi!(event);
+
+        Ok(())
+    }
+
+    /// # Redeem Receipt Instruction
+    ///
+    /// **What it does**: Whoever holds a receipt burns it and collects the lamports held
+    /// for that side.
+    ///
+    /// ## Step-by-Step Process
+    ///
+    /// 1. **Validate conditions**: The mint is the escrow's receipt for `side` and the side
+    ///    has a claim, i.e. the escrow has settled, been cancelled or been refunded
+    /// 2. **Burn**: One token from the holder's account
+    /// 3. **Pay**: The whole claim to the holder, then emit `ReceiptRedeemed`
+    /// 4. **Close**: Once no claim or bid is left, the escrow's rent goes back to its `rent_payer`
+    pub fn redeem_receipt(ctx: Context<RedeemReceipt>, side: ReceiptSide) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+        let amount = match side {
+            ReceiptSide::Maker => escrow.maker_claim,
+            ReceiptSide::Taker => escrow.taker_claim,
+        };
+        require!(amount > 0, EscrowError::NothingToRedeem);
+
+        token::burn(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Burn {
+                    mint: ctx.accounts.receipt_mint.to_account_info(),
+                    from: ctx.accounts.holder_receipt_account.to_account_info(),
+                    authority: ctx.accounts.holder.to_account_info(),
+                },
+            ),
+            1,
+        )?;
+
+        **ctx.accounts.escrow.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.holder.to_account_info().try_borrow_mut_lamports()? += amount;
+
+        let escrow = &mut ctx.accounts.escrow;
+        match side {
+            ReceiptSide::Maker => escrow.maker_claim = 0,
+            ReceiptSide::Taker => escrow.taker_claim = 0,
+        }
+
+        let event = ReceiptRedeemed {
+            seq: escrow.next_event_seq()?,
+            escrow: escrow.key(),
+            side,
+            holder: ctx.accounts.holder.key(),
+            amount,
+            locked_lamports: escrow.locked_lamports(),
+            ts: Clock::get()?.unix_timestamp,
+        };
+        emit_cpi!(event);
+
+        close_if_settled(&mut ctx.accounts.escrow, &ctx.accounts.rent_payer)?;
+
+        Ok(())
+    }
+
+    /// # Create Multisig Instruction
+    ///
+    /// **What it does**: Sets up an M-of-N `Multisig` that can act as an escrow maker,
+    /// for treasuries that can't hand a single key the maker's authority.
+    ///
+    /// ## Parameters
+    ///
+    /// - `owners`: Up to `MAX_MULTISIG_OWNERS` distinct keys
+    /// - `threshold`: How many owners must approve a proposal, between 1 and `owners.len()`
+    ///
+    /// The PDA is derived from `create_key`, a throwaway signer, so one payer can create
+    /// as many multisigs as it likes. The account doubles as the treasury: every payout
+    /// from its escrows lands in it, and leaves only through an approved `Withdraw`.
+    pub fn create_multisig(
+        ctx: Context<CreateMultisig>,
+        owners: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        let mut unique = owners.clone();
+        unique.sort();
+        unique.dedup();
+        require!(
+            unique.len() == owners.len()
+                && owners.len() <= MAX_MULTISIG_OWNERS as usize
+                && threshold > 0
+                && threshold as usize <= owners.len(),
+            EscrowError::InvalidMultisig
+        );
+
+        let multisig = &mut ctx.accounts.multisig;
+        multisig.create_key = ctx.accounts.create_key.key();
+        multisig.owners = owners;
+        multisig.threshold = threshold;
+        multisig.proposal_count = 0;
+        multisig.bump = ctx.bumps.multisig;
+        Ok(())
+    }
+
+    /// # Create Multisig Escrow Instruction
+    ///
+    /// **What it does**: Any owner opens a SOL escrow whose maker is the `Multisig`.
+    ///
+    /// ## How It Differs from `create_escrow`
+    ///
+    /// - **Maker**: `escrow.maker` is the multisig PDA, so escrow and profile PDAs are derived from it
+    /// - **Deposit**: The proposing owner pays `amount_a`, the keeper tip and rent from their own wallet,
+    ///   and is recorded as the `rent_payer`
+    /// - **Payouts**: Everything a maker would receive goes to the multisig's treasury balance
+    /// - **Authority**: Cancelling, refunding and amending need `threshold` approvals on a `Proposal`
+    ///
+    /// Oracle pricing and attesters aren't offered for multisig escrows.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_multisig_escrow(
+        ctx: Context<CreateMultisigEscrow>,
+        escrow_id: Option<u64>,
+        amount_a: u64,
+        amount_b_expected: u64,
+        expiry_ts: i64,
+        taker_pubkey: Pubkey,
+        keeper_tip: u64,
+        index_page: u32,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+
+        let multisig_key = ctx.accounts.multisig.key();
+
+        // Consume the ID and advance the multisig's counter past it
+        let maker_profile = &mut ctx.accounts.maker_profile;
+        let escrow_id = maker_profile.assign_escrow_id(escrow_id)?;
+        let event_seq = maker_profile.reserve_event_seqs()?;
+        maker_profile.maker = multisig_key;
+        maker_profile.bump = ctx.bumps.maker_profile;
+
+        let escrow_key = ctx.accounts.escrow.key();
+        let escrow_account_info = ctx.accounts.escrow.to_account_info();
+
+        // Check the terms and initialize escrow account state
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.set_inner(EscrowAccount::init(
+            NewEscrow {
+                maker: multisig_key,
+                escrow_id,
+                bump: ctx.bumps.escrow,
+                rent_payer: ctx.accounts.owner.key(),
+                amount_a,
+                amount_b_expected,
+                expiry_ts,
+                taker_pubkey,
+                keeper_tip,
+                index_page,
+                nft_mint: None,
+                options: EscrowOptions::default(),
+                event_seq,
+            },
+            now,
+        )?);
+
+        // List the escrow on the taker's discovery index
+        let escrow_index = &mut ctx.accounts.escrow_index;
+        escrow_index.taker = taker_pubkey;
+        escrow_index.page = index_page;
+        escrow_index.bump = ctx.bumps.escrow_index;
+        escrow_index.push(escrow_key)?;
+
+        // Transfer SOL (offer plus keeper tip) from the proposing owner to escrow PDA
+        let deposit = amount_a
+            .checked_add(keeper_tip)
+            .ok_or(EscrowError::InvalidAmount)?;
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.owner.to_account_info(),
+                    to: escrow_account_info,
+                },
+            ),
+            deposit,
+        )?;
+
+        let event = escrow.created_event(escrow_key, now)?;
+        emit_cpi!(event);
+
+        Ok(())
+    }
+
+    /// # Propose Instruction
+    ///
+    /// **What it does**: An owner opens a `Proposal` for the multisig to act, counting as its first approval.
+    ///
+    /// ## Actions
+    ///
+    /// - `Cancel`, `Refund`, `Amend`: Pass the target `escrow`; its `terms_version` is recorded
+    /// - `Withdraw`: Moves treasury lamports to `destination`; no escrow involved
+    ///
+    /// Proposals are numbered by `multisig.proposal_count` and live at
+    /// `["proposal", multisig, index]`. Conditions like expiry are checked at execution.
+    pub fn propose(ctx: Context<Propose>, action: ProposalAction) -> Result<()> {
+        let multisig_key = ctx.accounts.multisig.key();
+        let (escrow, terms_version) = match action {
+            ProposalAction::Withdraw { lamports, .. } => {
+                require!(lamports > 0, EscrowError::InvalidAmount);
+                (Pubkey::default(), 0)
+            }
+            _ => {
+                let escrow = ctx
+                    .accounts
+                    .escrow
+                    .as_ref()
+                    .ok_or(EscrowError::ProposalMismatch)?;
+                require_keys_eq!(escrow.maker, multisig_key, EscrowError::ProposalMismatch);
+                (escrow.key(), escrow.terms_version)
+            }
+        };
+
+        let multisig = &mut ctx.accounts.multisig;
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.multisig = multisig_key;
+        proposal.proposer = ctx.accounts.proposer.key();
+        proposal.index = multisig.proposal_count;
+        proposal.escrow = escrow;
+        proposal.action = action;
+        proposal.terms_version = terms_version;
+        proposal.approvals = vec![ctx.accounts.proposer.key()];
+        proposal.bump = ctx.bumps.proposal;
+
+        multisig.proposal_count += 1;
+        Ok(())
+    }
+
+    /// # Approve Proposal Instruction
+    ///
+    /// **What it does**: Another owner adds their approval to a `Proposal`.
+    ///
+    /// Approvals can be gathered across as many transactions as it takes. For escrow
+    /// actions the target escrow must be passed: if its terms were amended since the
+    /// proposal was opened, every approval is void and this fails with `StaleProposal`.
+    pub fn approve_proposal(ctx: Context<ApproveProposal>) -> Result<()> {
+        let owner = ctx.accounts.owner.key();
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.check_terms(ctx.accounts.escrow.as_ref())?;
+        require!(
+            !proposal.approvals.contains(&owner),
+            EscrowError::AlreadyApproved
+        );
+        proposal.approvals.push(owner);
+        Ok(())
+    }
+
+    /// # Execute Proposal Instruction
+    ///
+    /// **What it does**: Any owner carries out a proposal once it has `threshold` approvals.
+    ///
+    /// ## Step-by-Step Process
+    ///
+    /// 1. **Check approvals**: At least `threshold` owners approved
+    /// 2. **Check terms**: The escrow's `terms_version` still matches the proposal's
+    /// 3. **Act**: Same rules as `cancel_escrow`, `refund_after_expiry` and `amend_escrow`,
+    ///    with the multisig's treasury standing in for the maker, or pay out a `Withdraw`
+    /// 4. **Close the proposal**: Rent goes back to the proposer
+    ///
+    /// ## Accounts per Action
+    ///
+    /// - `Cancel`/`Refund`: `escrow`, the `escrow_index` page listing it, its `rent_payer`,
+    ///   which gets the escrow's rent back, and the multisig's `maker_stats`, which records the
+    ///   cancel or lapse as the single-maker instructions do
+    /// - `Amend`: `escrow`
+    /// - `Withdraw`: `destination`; the treasury never drops below rent exemption
+    pub fn execute_proposal(ctx: Context<ExecuteProposal>) -> Result<()> {
+        let proposal = &ctx.accounts.proposal;
+        require!(
+            proposal.approvals.len() >= ctx.accounts.multisig.threshold as usize,
+            EscrowError::ThresholdNotMet
+        );
+        proposal.check_terms(ctx.accounts.escrow.as_ref())?;
+        let action = proposal.action;
+
+        let now = Clock::get()?.unix_timestamp;
+        let treasury = ctx.accounts.multisig.to_account_info();
+
+        if let ProposalAction::Withdraw { destination, lamports } = action {
+            let destination_account = ctx
+                .accounts
+                .destination
+                .as_ref()
+                .ok_or(EscrowError::ProposalMismatch)?;
+            require_keys_eq!(
+                destination_account.key(),
+                destination,
+                EscrowError::ProposalMismatch
+            );
+
+            let rent = Rent::get()?.minimum_balance(treasury.data_len());
+            require!(
+                treasury.lamports().saturating_sub(rent) >= lamports,
+                EscrowError::InsufficientTreasury
+            );
+            **treasury.try_borrow_mut_lamports()? -= lamports;
+            **destination_account.to_account_info().try_borrow_mut_lamports()? += lamports;
+            return Ok(());
+        }
+
+        let escrow = ctx
+            .accounts
+            .escrow
+            .as_mut()
+            .ok_or(EscrowError::ProposalMismatch)?;
+
+        if let ProposalAction::Amend { amount_b_expected, expiry_ts } = action {
+            escrow.amend(amount_b_expected, expiry_ts, now)?;
+
+            let event = EscrowAmended {
+                seq: escrow.next_event_seq()?,
+                escrow: escrow.key(),
+                maker: escrow.maker,
+                amount_b_expected: escrow.amount_b_expected,
+                expiry_ts: escrow.expiry_ts,
+                terms_version: escrow.terms_version,
+                locked_lamports: escrow.locked_lamports(),
+                ts: now,
+            };
+            emit_cpi!(event);
+            return Ok(());
+        }
+
+        // Cancel or refund: the same checks the single-maker instructions make
+        require!(escrow.is_active, EscrowError::NotActive);
+        require!(!escrow.is_funded, EscrowError::AlreadyFunded);
+        if action == ProposalAction::Refund {
+            require!(now > escrow.expiry_ts, EscrowError::NotExpired);
+        }
+
+        // Transfer SOL (offer plus keeper tip) from escrow PDA to the treasury
+        let refund_amount = escrow
+            .amount_a
+            .checked_add(escrow.keeper_tip)
+            .ok_or(EscrowError::ArithmeticOverflow)?;
+        pay_side(escrow, ReceiptSide::Maker, &treasury, refund_amount)?;
+
+        // Drop the escrow from the taker's discovery index
+        let escrow_index = ctx
+            .accounts
+            .escrow_index
+            .as_mut()
+            .ok_or(EscrowError::NotInIndex)?;
+        check_escrow_index(escrow_index, escrow)?;
+        escrow_index.remove(&escrow.key())?;
+        let rent_payer = ctx
+            .accounts
+            .rent_payer
+            .as_ref()
+            .filter(|rent_payer| rent_payer.key() == escrow.rent_payer)
+            .ok_or(ErrorCode::ConstraintHasOne)?;
+        let maker_stats = ctx
+            .accounts
+            .maker_stats
+            .as_mut()
+            .ok_or(ErrorCode::AccountNotEnoughKeys)?
+            .for_wallet(ctx.accounts.multisig.key(), ctx.bumps.maker_stats.unwrap_or_default());
+        if action == ProposalAction::Cancel {
+            maker_stats.record_cancelled();
+        } else {
+            maker_stats.record_expired();
+        }
+
+        escrow.is_active = false;
+
+        let seq = escrow.next_event_seq()?;
+        if action == ProposalAction::Cancel {
+            let event = EscrowCancelled {
+                seq,
+                escrow: escrow.key(),
+                maker: escrow.maker,
+                amount_a: escrow.amount_a,
+                amount_b_expected: escrow.amount_b_expected,
+                keeper_tip: escrow.keeper_tip,
+                penalty: 0,
+                locked_lamports: escrow.locked_lamports(),
+                ts: now,
+            };
+            emit_cpi!(event);
+        } else {
+            let event = EscrowRefunded {
+                seq,
+                escrow: escrow.key(),
+                maker: escrow.maker,
+                amount_a: escrow.amount_a,
+                amount_b_expected: escrow.amount_b_expected,
+                keeper_tip: escrow.keeper_tip,
+                attester: None,
+                locked_lamports: escrow.locked_lamports(),
+                ts: now,
+            };
+            emit_cpi!(event);
+        }
+
+        close_if_settled(escrow, rent_payer)?;
+
+        Ok(())
+    }
+
+    /// # Batch Create Escrows Instruction
+    ///
+    /// **What it does**: Creates up to `MAX_BATCH_SIZE` plain escrows in one transaction,
+    /// as if `create_escrow` were called once per entry of `terms`.
+    ///
+    /// ## Accounts
+    ///
+    /// `remaining_accounts` holds two writable accounts per entry, in order:
+    /// the escrow PDA `["escrow", maker, escrow_id]` and the index page
+    /// `["escrow_index", taker, index_page]`. Entries sharing a page pass it again.
+    ///
+    /// ## Step-by-Step Process
+    ///
+    /// 1. **Validate the batch**: Size within bounds, two accounts per entry
+    /// 2. **For each entry**: Validate terms and seeds, create the escrow (and page if missing),
+    ///    list it, lock the maker's SOL, emit `EscrowCreated`
+    ///
+    /// ## Failure
+    ///
+    /// Any bad entry fails the whole batch, so no escrow is created. The error names
+    /// the entry as `batch[i]` (the AnchorError's account), counting from 0.
+    ///
+    /// ## Supported Terms
+    ///
+    /// Batched escrows are fixed-price and addressed to one taker. Of the `EscrowOptions`,
+    /// entries only take `referral_fee_bps`; escrows with any other option (oracle or
+    /// auction pricing, an attester, an allowlist, transfer consent, metadata, a taker bond,
+    /// a cancel penalty or a reputation gate) need `create_escrow`. As there, `rent_payer`
+    /// pays for the new accounts and is recorded to get each escrow's rent back.
+    pub fn batch_create_escrows<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BatchCreateEscrows<'info>>,
+        terms: Vec<BatchEscrowTerms>,
+    ) -> Result<()> {
+        require!(
+            !terms.is_empty() && terms.len() <= MAX_BATCH_SIZE as usize,
+            EscrowError::InvalidBatchSize
+        );
+        require!(
+            ctx.remaining_accounts.len() == terms.len() * 2,
+            EscrowError::InvalidBatchSize
+        );
+
+        let maker_key = ctx.accounts.maker.key();
+        let maker_profile = &mut ctx.accounts.maker_profile;
+        maker_profile.maker = maker_key;
+        maker_profile.bump = ctx.bumps.maker_profile;
+        let now = Clock::get()?.unix_timestamp;
+
+        let remaining_accounts = ctx.remaining_accounts;
+        for (i, (item, accounts)) in terms.iter().zip(remaining_accounts.chunks(2)).enumerate() {
+            let (escrow_info, index_info) = (&accounts[0], &accounts[1]);
+            let mut create = || -> Result<()> {
+                // Create the escrow PDA for the next ID, as `init` would
+                let escrow_id = ctx.accounts.maker_profile.assign_escrow_id(item.escrow_id)?;
+                let event_seq = ctx.accounts.maker_profile.reserve_event_seqs()?;
+                let id_bytes = escrow_id.to_le_bytes();
+                let (escrow_key, escrow_bump) =
+                    Pubkey::find_program_address(&[b"escrow", maker_key.as_ref(), &id_bytes], &crate::ID);
+                require_keys_eq!(escrow_info.key(), escrow_key, ErrorCode::ConstraintSeeds);
+                // Check the terms and build the escrow state
+                let mut escrow = EscrowAccount::init(
+                    NewEscrow {
+                        maker: maker_key,
+                        escrow_id,
+                        bump: escrow_bump,
+                        rent_payer: ctx.accounts.rent_payer.key(),
+                        amount_a: item.amount_a,
+                        amount_b_expected: item.amount_b_expected,
+                        expiry_ts: item.expiry_ts,
+                        taker_pubkey: item.taker_pubkey,
+                        keeper_tip: item.keeper_tip,
+                        index_page: item.index_page,
+                        nft_mint: None,
+                        options: EscrowOptions {
+                            referral_fee_bps: item.referral_fee_bps,
+                            ..EscrowOptions::default()
+                        },
+                        event_seq,
+                    },
+                    now,
+                )?;
+                let event = escrow.created_event(escrow_key, now)?;
+                create_pda_account(
+                    &ctx.accounts.rent_payer,
+                    escrow_info,
+                    EscrowAccount::calculate_max_space(),
+                    &[b"escrow", maker_key.as_ref(), &id_bytes, &[escrow_bump]],
+                    &ctx.accounts.system_program,
+                )?;
+                escrow.try_serialize(&mut &mut escrow_info.try_borrow_mut_data()?[..])?;
+
+                // List it on the taker's page, creating the page as `init_if_needed` would
+                let page_bytes = item.index_page.to_le_bytes();
+                let (index_key, index_bump) = Pubkey::find_program_address(
+                    &[b"escrow_index", item.taker_pubkey.as_ref(), &page_bytes],
+                    &crate::ID,
+                );
+                require_keys_eq!(index_info.key(), index_key, ErrorCode::ConstraintSeeds);
+                if index_info.owner == &system_program::ID {
+                    create_pda_account(
+                        &ctx.accounts.rent_payer,
+                        index_info,
+                        EscrowIndex::calculate_max_space(),
+                        &[b"escrow_index", item.taker_pubkey.as_ref(), &page_bytes, &[index_bump]],
+                        &ctx.accounts.system_program,
+                    )?;
+                    let page = EscrowIndex {
+                        taker: item.taker_pubkey,
+                        page: item.index_page,
+                        escrows: Vec::new(),
+                        bump: index_bump,
+                    };
+                    page.try_serialize(&mut &mut index_info.try_borrow_mut_data()?[..])?;
+                }
+                let mut escrow_index = Account::<EscrowIndex>::try_from(index_info)?;
+                escrow_index.push(escrow_key)?;
+                escrow_index.exit(&crate::ID)?;
+
+                // Transfer SOL (offer plus keeper tip) from maker to escrow PDA
+                let deposit = item
+                    .amount_a
+                    .checked_add(item.keeper_tip)
+                    .ok_or(EscrowError::InvalidAmount)?;
+                system_program::transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        system_program::Transfer {
+                            from: ctx.accounts.maker.to_account_info(),
+                            to: escrow_info.clone(),
+                        },
+                    ),
+                    deposit,
+                )?;
+
+                emit_cpi!(event);
+                Ok(())
+            };
+            create().map_err(|error| error.with_account_name(format!("batch[{}]", i)))?;
+        }
+
+        Ok(())
+    }
+
+    /// # Batch Cancel Escrows Instruction
+    ///
+    /// **What it does**: Cancels up to `MAX_BATCH_SIZE` unfunded escrows of one maker in one
+    /// transaction, as if `cancel_escrow` were called once per escrow.
+    ///
+    /// ## Accounts
+    ///
+    /// `remaining_accounts` holds three writable accounts per escrow, in order: the escrow,
+    /// the index page listing it, and the escrow's `rent_payer`, which gets its rent back.
+    /// The escrow and page are checked against their PDA seeds.
+    ///
+    /// ## Failure
+    ///
+    /// As with `batch_create_escrows`, any bad entry (wrong maker, already funded, cancelled
+    /// twice in the batch, ...) fails the whole batch with the error naming `batch[i]`.
+    pub fn batch_cancel_escrows<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BatchCancelEscrows<'info>>,
+    ) -> Result<()> {
+        let remaining_accounts = ctx.remaining_accounts;
+        let batch_len = remaining_accounts.len() / 3;
+        require!(
+            batch_len > 0
+                && batch_len <= MAX_BATCH_SIZE as usize
+                && remaining_accounts.len() == batch_len * 3,
+            EscrowError::InvalidBatchSize
+        );
+
+        let maker_key = ctx.accounts.maker.key();
+        let now = Clock::get()?.unix_timestamp;
+
+        for (i, accounts) in remaining_accounts.chunks(3).enumerate() {
+            let (escrow_info, index_info, rent_payer_info) = (&accounts[0], &accounts[1], &accounts[2]);
+            let mut cancel = || -> Result<()> {
+                let mut escrow = Account::<EscrowAccount>::try_from(escrow_info)?;
+                require_keys_eq!(escrow.maker, maker_key, ErrorCode::ConstraintHasOne);
+                let escrow_key = Pubkey::create_program_address(
+                    &[b"escrow", escrow.creator.as_ref(), &escrow.escrow_id.to_le_bytes(), &[escrow.bump]],
+                    &crate::ID,
+                )
+                .map_err(|_| error!(ErrorCode::ConstraintSeeds))?;
+                require_keys_eq!(escrow_info.key(), escrow_key, ErrorCode::ConstraintSeeds);
+
+                let mut escrow_index = Account::<EscrowIndex>::try_from(index_info)?;
+                check_escrow_index(&escrow_index, &escrow)?;
+                require_keys_eq!(rent_payer_info.key(), escrow.rent_payer, ErrorCode::ConstraintHasOne);
+
+                escrow.check_authority(&ctx.accounts.authority.key(), OPERATOR_CAN_CANCEL)?;
+                require!(escrow.nft_mint.is_none(), EscrowError::NftEscrow);
+                require!(escrow.is_active, EscrowError::NotActive);
+                require!(!escrow.is_funded, EscrowError::AlreadyFunded);
+                require!(escrow.bid_count == 0, EscrowError::AuctionHasBids);
+
+                // Transfer SOL (offer plus keeper tip) from escrow PDA to maker
+                let refund_amount = escrow
+                    .amount_a
+                    .checked_add(escrow.keeper_tip)
+                    .ok_or(EscrowError::ArithmeticOverflow)?;
+                pay_side(&mut escrow, ReceiptSide::Maker, &ctx.accounts.maker, refund_amount)?;
+
+                // Drop the escrow from the taker's discovery index and mark it inactive
+                escrow_index.remove(&escrow_key)?;
+                escrow_index.exit(&crate::ID)?;
+                escrow.is_active = false;
+                ctx.accounts
+                    .maker_stats
+                    .for_wallet(ctx.accounts.maker.key(), ctx.bumps.maker_stats)
+                    .record_cancelled();
+
+                let event = EscrowCancelled {
+                    seq: escrow.next_event_seq()?,
+                    escrow: escrow_key,
+                    maker: maker_key,
+                    amount_a: escrow.amount_a,
+                    amount_b_expected: escrow.amount_b_expected,
+                    keeper_tip: escrow.keeper_tip,
+                    penalty: 0,
+                    locked_lamports: escrow.locked_lamports(),
+                    ts: now,
+                };
+                emit_cpi!(event);
+
+                close_if_settled(&mut escrow, rent_payer_info)?;
+                escrow.exit(&crate::ID)
+            };
+            cancel().map_err(|error| error.with_account_name(format!("batch[{}]", i)))?;
+        }
+
+        Ok(())
+    }
+
+    /// # Create Subscription Instruction
+    ///
+    /// **What it does**: A payer pre-funds `periods` billing periods of `amount_per_period`
+    /// lamports for a payee, who pulls them one at a time with `collect_period`.
+    ///
+    /// ## Step-by-Step Process
+    ///
+    /// 1. **Validate inputs**: Positive amount, period length and period count
+    /// 2. **Create subscription account**: PDA `["subscription", payer, subscription_id]`
+    /// 3. **Lock the payer's SOL**: `amount_per_period * periods` into the PDA
+    /// 4. **Emit event**: `SubscriptionCreated`
+    ///
+    /// ## Billing Schedule
+    ///
+    /// Period `n` (from 0) becomes collectable at `start_ts + (n + 1) * period_secs`, so
+    /// the payee is paid in arrears for each interval served. `start_ts` is when this
+    /// instruction runs.
+    pub fn create_subscription(
+        ctx: Context<CreateSubscription>,
+        subscription_id: u64,
+        payee: Pubkey,
+        amount_per_period: u64,
+        period_secs: i64,
+        periods: u32,
+    ) -> Result<()> {
+        require!(amount_per_period > 0 && periods > 0, EscrowError::InvalidAmount);
+        require!(period_secs > 0, EscrowError::InvalidPeriod);
+        let deposit = amount_per_period
+            .checked_mul(periods as u64)
+            .ok_or(EscrowError::InvalidAmount)?;
+
+        let subscription = &mut ctx.accounts.subscription;
+        subscription.payer = ctx.accounts.payer.key();
+        subscription.payee = payee;
+        subscription.subscription_id = subscription_id;
+        subscription.amount_per_period = amount_per_period;
+        subscription.period_secs = period_secs;
+        subscription.start_ts = Clock::get()?.unix_timestamp;
+        subscription.periods = periods;
+        subscription.periods_collected = 0;
+        subscription.bump = ctx.bumps.subscription;
+
+        // Transfer every period's SOL from payer to the subscription PDA
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: ctx.accounts.subscription.to_account_info(),
+                },
+            ),
+            deposit,
+        )?;
+
+        let subscription = &mut ctx.accounts.subscription;
+        let event = SubscriptionCreated {
+            seq: subscription.next_event_seq()?,
+            subscription: subscription.key(),
+            payer: subscription.payer,
+            payee,
+            subscription_id,
+            amount_per_period,
+            period_secs,
+            periods,
+            locked_lamports: subscription.locked_lamports(),
+            ts: subscription.start_ts,
+        };
+        emit_cpi!(event);
+
+        Ok(())
+    }
+
+    /// # Collect Period Instruction
+    ///
+    /// **What it does**: The payee pulls exactly one period's amount, once that period has elapsed.
+    ///
+    /// ## Step-by-Step Process
+    ///
+    /// 1. **Check the schedule**: The next uncollected period must have elapsed by `Clock` time
+    /// 2. **Pay**: `amount_per_period` from the PDA to the payee
+    /// 3. **Emit event**: `PeriodCollected`
+    /// 4. **Close when done**: After the last period the account's rent goes back to the payer
+    ///
+    /// A payee who falls behind calls this once per missed period to catch up.
+    pub fn collect_period(ctx: Context<CollectPeriod>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let subscription = &ctx.accounts.subscription;
+        require!(
+            subscription.periods_collected < subscription.periods_elapsed(now),
+            EscrowError::PeriodNotDue
+        );
+
+        let amount = subscription.amount_per_period;
+        **ctx.accounts.subscription.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.payee.to_account_info().try_borrow_mut_lamports()? += amount;
+
+        let subscription = &mut ctx.accounts.subscription;
+        subscription.periods_collected += 1;
+        let period = subscription.periods_collected - 1;
+        let finished = subscription.periods_collected == subscription.periods;
+
+        let event = PeriodCollected {
+            seq: subscription.next_event_seq()?,
+            subscription: subscription.key(),
+            payee: subscription.payee,
+            period,
+            amount,
+            locked_lamports: subscription.locked_lamports(),
+            ts: now,
+        };
+        emit_cpi!(event);
+
+        if finished {
+            ctx.accounts.subscription.close(ctx.accounts.payer.to_account_info())?;
+        }
+
+        Ok(())
+    }
+
+    /// # Cancel Subscription Instruction
+    ///
+    /// **What it does**: The payer ends the subscription at any time and reclaims every
+    /// period that hasn't elapsed yet.
+    ///
+    /// ## Step-by-Step Process
+    ///
+    /// 1. **Settle earned periods**: Periods that elapsed but weren't collected are paid to
+    ///    the payee, so cancelling can't claw back service already delivered
+    /// 2. **Refund**: Close the PDA, returning the remaining periods and rent to the payer
+    /// 3. **Emit event**: `SubscriptionCancelled`
+    pub fn cancel_subscription(ctx: Context<CancelSubscription>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let subscription = &ctx.accounts.subscription;
+        let earned = subscription.periods_elapsed(now) - subscription.periods_collected;
+        let paid_to_payee = subscription.amount_per_period * earned as u64;
+        let refunded = subscription.amount_per_period
+            * (subscription.periods - subscription.periods_collected - earned) as u64;
+
+        **ctx.accounts.subscription.to_account_info().try_borrow_mut_lamports()? -= paid_to_payee;
+        **ctx.accounts.payee.to_account_info().try_borrow_mut_lamports()? += paid_to_payee;
+
+        let subscription = &mut ctx.accounts.subscription;
+        let event = SubscriptionCancelled {
+            seq: subscription.next_event_seq()?,
+            subscription: subscription.key(),
+            payer: subscription.payer,
+            paid_to_payee,
+            refunded,
+            locked_lamports: 0,
+            ts: now,
+        };
+        emit_cpi!(event);
+
+        // The rest, rent included, goes back to the payer
+        ctx.accounts.subscription.close(ctx.accounts.payer.to_account_info())
+    }
+
+    /// # Place Bid Instruction
+    ///
+    /// **What it does**: Bids `amount` lamports on an English auction escrow, locking them in
+    /// the bidder's `Bid` PDA.
+    ///
+    /// ## Step-by-Step Process
+    ///
+    /// 1. **Check the bid**: Bidding is open and `amount` is at least the reserve price, or the
+    ///    leading bid plus `min_increment`
+    /// 2. **Lock the SOL**: Top the bidder's PDA up to `amount`
+    /// 3. **Refund the outbid leader**: Their `Bid` PDA is closed back to them, rent included
+    /// 4. **Take the lead**: Record the bid as `highest_bid` and emit `BidPlaced`
+    ///
+    /// The current leader may raise their own bid; nothing is refunded then. Otherwise the
+    /// leader's `Bid` and wallet must be passed as `previous_bid` and `previous_bidder`.
+    pub fn place_bid(ctx: Context<PlaceBid>, amount: u64) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let escrow = &ctx.accounts.escrow;
+        let terms = escrow.auction.ok_or(EscrowError::WrongAuctionKind)?;
+        require!(terms.kind == AuctionKind::English, EscrowError::WrongAuctionKind);
+        require!(escrow.is_active, EscrowError::NotActive);
+        require!(now < escrow.expiry_ts, EscrowError::EscrowExpired);
+        let minimum = match escrow.highest_bidder {
+            Some(_) => escrow
+                .highest_bid
+                .checked_add(terms.min_increment)
+                .ok_or(EscrowError::InvalidAmount)?,
+            None => terms.reserve_price,
+        };
+        require!(amount >= minimum, EscrowError::BidTooLow);
+
+        // A new bidder opens a bid; the leader raising theirs tops it up
+        let bidder_key = ctx.accounts.bidder.key();
+        let bid = &mut ctx.accounts.bid;
+        if bid.bidder == Pubkey::default() {
+            bid.escrow = escrow.key();
+            bid.bidder = bidder_key;
+            bid.commitment = None;
+            bid.bump = ctx.bumps.bid;
+            ctx.accounts.escrow.bid_count += 1;
+        }
+        let top_up = amount - ctx.accounts.bid.deposit;
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.bidder.to_account_info(),
+                    to: ctx.accounts.bid.to_account_info(),
+                },
+            ),
+            top_up,
+        )?;
+        let bid = &mut ctx.accounts.bid;
+        bid.amount = amount;
+        bid.deposit = amount;
+        bid.revealed = true;
+
+        // Refund whoever was leading, unless it's the same bidder
+        let refunded = match ctx.accounts.escrow.highest_bidder {
+            Some(leader) if leader != bidder_key => {
+                let (previous_bid, previous_bidder) = ctx
+                    .accounts
+                    .previous_bid
+                    .as_ref()
+                    .zip(ctx.accounts.previous_bidder.as_ref())
+                    .ok_or(EscrowError::BidMismatch)?;
+                require_keys_eq!(previous_bid.bidder, leader, EscrowError::BidMismatch);
+                require_keys_eq!(previous_bidder.key(), leader, EscrowError::BidMismatch);
+                require_keys_eq!(previous_bid.escrow, ctx.accounts.escrow.key(), EscrowError::BidMismatch);
+                previous_bid.close(previous_bidder.to_account_info())?;
+                ctx.accounts.escrow.bid_count -= 1;
+                Some(leader)
+            }
+            _ => None,
+        };
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.highest_bid = amount;
+        escrow.highest_bidder = Some(bidder_key);
+
+        let event = BidPlaced {
+            seq: escrow.next_event_seq()?,
+            escrow: escrow.key(),
+            bidder: bidder_key,
+            amount,
+            sealed: false,
+            refunded,
+            locked_lamports: escrow.locked_lamports(),
+            ts: now,
+        };
+        emit_cpi!(event);
+
+        Ok(())
+    }
+
+    /// # Commit Bid Instruction
+    ///
+    /// **What it does**: Places a sealed bid on a sealed-bid auction escrow: only a hash of the
+    /// bid goes on-chain, backed by a `deposit` of at least the bid.
+    ///
+    /// ## The Commitment
+    ///
+    /// `commitment = sha256(amount as u64 little-endian || salt || bidder)`, with a random
+    /// 32-byte `salt` the bidder keeps for `reveal_bid`. The deposit is public, so bidders
+    /// who don't want to give away their bid deposit more than it.
+    ///
+    /// Commitments can be made until `expiry_ts`, once per bidder, and are binding until then.
+    pub fn commit_bid(ctx: Context<CommitBid>, commitment: [u8; 32], deposit: u64) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let escrow = &ctx.accounts.escrow;
+        let terms = escrow.auction.ok_or(EscrowError::WrongAuctionKind)?;
+        require!(terms.kind == AuctionKind::SealedBid, EscrowError::WrongAuctionKind);
+        require!(escrow.is_active, EscrowError::NotActive);
+        require!(now < escrow.expiry_ts, EscrowError::EscrowExpired);
+        require!(deposit >= terms.reserve_price, EscrowError::BidTooLow);
+
+        let bid = &mut ctx.accounts.bid;
+        bid.escrow = escrow.key();
+        bid.bidder = ctx.accounts.bidder.key();
+        bid.amount = 0;
+        bid.deposit = deposit;
+        bid.commitment = Some(commitment);
+        bid.revealed = false;
+        bid.bump = ctx.bumps.bid;
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.bidder.to_account_info(),
+                    to: ctx.accounts.bid.to_account_info(),
+                },
+            ),
+            deposit,
+        )?;
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.bid_count += 1;
+
+        let event = BidPlaced {
+            seq: escrow.next_event_seq()?,
+            escrow: escrow.key(),
+            bidder: ctx.accounts.bidder.key(),
+            amount: deposit,
+            sealed: true,
+            refunded: None,
+            locked_lamports: escrow.locked_lamports(),
+            ts: now,
+        };
+        emit_cpi!(event);
+
+        Ok(())
+    }
+
+    /// # Reveal Bid Instruction
+    ///
+    /// **What it does**: Opens a sealed bid during the reveal phase, between `expiry_ts` and
+    /// `expiry_ts + reveal_secs`.
+    ///
+    /// ## Step-by-Step Process
+    ///
+    /// 1. **Check the reveal**: `amount` and `salt` must hash to the commitment, and the
+    ///    deposit must cover `amount`
+    /// 2. **Rank it**: A bid of at least the reserve that beats the leading bid takes the lead;
+    ///    on a tie the earlier reveal keeps it
+    /// 3. **Emit event**: `BidRevealed`, saying whether the bid now leads
+    ///
+    /// Bids never revealed can't win and are withdrawn after the reveal phase.
+    pub fn reveal_bid(ctx: Context<RevealBid>, amount: u64, salt: [u8; 32]) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let escrow = &ctx.accounts.escrow;
+        require!(escrow.is_active, EscrowError::NotActive);
+        require!(
+            now >= escrow.expiry_ts && now < escrow.bidding_end_ts(),
+            EscrowError::NotInRevealPhase
+        );
+
+        let bidder_key = ctx.accounts.bidder.key();
+        let bid = &mut ctx.accounts.bid;
+        require!(!bid.revealed, EscrowError::InvalidReveal);
+        let expected = hashv(&[&amount.to_le_bytes(), &salt, bidder_key.as_ref()]).to_bytes();
+        require!(bid.commitment == Some(expected), EscrowError::InvalidReveal);
+        require!(amount <= bid.deposit, EscrowError::InvalidReveal);
+        bid.amount = amount;
+        bid.revealed = true;
+
+        let escrow = &mut ctx.accounts.escrow;
+        let reserve_price = escrow.auction.map_or(0, |terms| terms.reserve_price);
+        let leading = amount >= reserve_price
+            && (escrow.highest_bidder.is_none() || amount > escrow.highest_bid);
+        if leading {
+            escrow.highest_bid = amount;
+            escrow.highest_bidder = Some(bidder_key);
+        }
+
+        let event = BidRevealed {
+            seq: escrow.next_event_seq()?,
+            escrow: escrow.key(),
+            bidder: bidder_key,
+            amount,
+            leading,
+            locked_lamports: escrow.locked_lamports(),
+            ts: now,
+        };
+        emit_cpi!(event);
+
+        Ok(())
+    }
+
+    /// # Withdraw Bid Instruction
+    ///
+    /// **What it does**: A bidder who isn't leading takes their bid back, closing their `Bid` PDA.
+    ///
+    /// ## When This Can Be Used
+    ///
+    /// - **English auctions**: Rarely needed, since `place_bid` refunds outbid bids itself
+    /// - **Sealed-bid auctions**: Once bidding has closed, for any bid that isn't leading;
+    ///   unrevealed bids are simply forfeited from the ranking, not from the deposit
+    ///
+    /// The leading bid stays locked until `settle_auction` pays it to the maker. The last bid
+    /// withdrawn from a finished auction closes the escrow back to its `rent_payer`.
+    pub fn withdraw_bid(ctx: Context<WithdrawBid>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let escrow = &ctx.accounts.escrow;
+        let bidder_key = ctx.accounts.bidder.key();
+        require!(
+            !(escrow.is_active && escrow.highest_bidder == Some(bidder_key)),
+            EscrowError::BidStillLeading
+        );
+        if escrow.auction.is_some_and(|terms| terms.kind == AuctionKind::SealedBid) {
+            require!(now >= escrow.expiry_ts, EscrowError::NotExpired);
+        }
+
+        let amount = ctx.accounts.bid.deposit;
+        ctx.accounts.bid.close(ctx.accounts.bidder.to_account_info())?;
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.bid_count -= 1;
+
+        let event = BidWithdrawn {
+            seq: escrow.next_event_seq()?,
+            escrow: escrow.key(),
+            bidder: bidder_key,
+            amount,
+            locked_lamports: escrow.locked_lamports(),
+            ts: now,
+        };
+        emit_cpi!(event);
+
+        close_if_settled(&mut ctx.accounts.escrow, &ctx.accounts.rent_payer)?;
+
+        Ok(())
+    }
+
+    /// # Settle Auction Instruction
+    ///
+    /// **What it does**: After bidding (and any reveal phase) ends, anyone completes the trade
+    /// with the highest bidder.
+    ///
+    /// ## Step-by-Step Process
+    ///
+    /// 1. **Check the auction is over**: `expiry_ts` for English auctions, the end of the
+    ///    reveal phase for sealed-bid ones, with a winning bid
+    /// 2. **Pay the maker**: The winning bid plus the unused keeper tip
+    /// 3. **Pay the winner**: `amount_a`; the rest of their deposit and the `Bid` rent go back too
+    /// 4. **Update state**: The winner becomes the taker, `amount_b_expected` the winning bid;
+    ///    mark completed, unlist, emit `EscrowCompleted`
+    ///
+    /// Auctions that end without a winning bid are refunded like any other expired escrow.
+    pub fn settle_auction(ctx: Context<SettleAuction>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let escrow = &ctx.accounts.escrow;
+        require!(escrow.auction.is_some(), EscrowError::WrongAuctionKind);
+        require!(escrow.is_active, EscrowError::NotActive);
+        require!(now >= escrow.bidding_end_ts(), EscrowError::NotExpired);
+        let winner = escrow.highest_bidder.ok_or(EscrowError::NoWinningBid)?;
+        require_keys_eq!(ctx.accounts.winner.key(), winner, EscrowError::BidMismatch);
+        require_keys_eq!(ctx.accounts.winning_bid.bidder, winner, EscrowError::BidMismatch);
+        require_keys_eq!(ctx.accounts.winning_bid.escrow, escrow.key(), EscrowError::BidMismatch);
+
+        // Move the winning bid into the escrow, then settle as `complete_swap` does
+        let amount_b = escrow.highest_bid;
+        **ctx.accounts.winning_bid.to_account_info().try_borrow_mut_lamports()? -= amount_b;
+        **ctx.accounts.escrow.to_account_info().try_borrow_mut_lamports()? += amount_b;
+        ctx.accounts.winning_bid.close(ctx.accounts.winner.to_account_info())?;
+
+        let taker_amount = escrow.amount_a;
+        let maker_amount = amount_b
+            .checked_add(escrow.keeper_tip)
+            .ok_or(EscrowError::ArithmeticOverflow)?;
+        let winner_info = ctx.accounts.winner.to_account_info();
+        pay_side(&mut ctx.accounts.escrow, ReceiptSide::Taker, &winner_info, taker_amount)?;
+        let maker = ctx.accounts.maker.to_account_info();
+        pay_side(&mut ctx.accounts.escrow, ReceiptSide::Maker, &maker, maker_amount)?;
+        echo_memo(&ctx.accounts.memo_program, &ctx.accounts.escrow)?;
+
+        // Drop the escrow from the listing index
+        ctx.accounts.escrow_index.remove(&ctx.accounts.escrow.key())?;
+
+        // Credit both sides' track records
+        let amount_a = ctx.accounts.escrow.amount_a;
+        ctx.accounts
+            .maker_stats
+            .for_wallet(ctx.accounts.maker.key(), ctx.bumps.maker_stats)
+            .record_completed(amount_a);
+        ctx.accounts
+            .winner_stats
+            .for_wallet(ctx.accounts.winner.key(), ctx.bumps.winner_stats)
+            .record_completed(amount_b);
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.taker = Some(winner);
+        escrow.amount_b_expected = amount_b;
+        escrow.bid_count -= 1;
+        escrow.is_active = false;
+        escrow.is_completed = true;
+
+        let event = EscrowCompleted {
+            seq: escrow.next_event_seq()?,
+            escrow: escrow.key(),
+            maker: escrow.maker,
+            taker: winner,
+            amount_a: escrow.amount_a,
+            amount_b_expected: amount_b,
+            keeper_tip: escrow.keeper_tip,
+            attester: None,
+            referrer: None,
+            referral_fee: 0,
+            locked_lamports: escrow.locked_lamports(),
+            ts: now,
+        };
+        emit_cpi!(event);
+
+        close_if_settled(&mut ctx.accounts.escrow, &ctx.accounts.rent_payer)?;
+
+        Ok(())
+    }
+}
+
+/// Pays one side of an escrow out of its lamports
+///
+/// Sides with a receipt are paid by `redeem_receipt` instead, so `amount` stays in the
+/// escrow and is added to that side's claim.
+fn pay_side<'info>(
+    escrow: &mut Account<'info, EscrowAccount>,
+    side: ReceiptSide,
+    recipient: &AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    match (side, escrow.receipt(side)) {
+        (ReceiptSide::Maker, Some(_)) => escrow.maker_claim += amount,
+        (ReceiptSide::Taker, Some(_)) => escrow.taker_claim += amount,
+        (_, None) => {
+            **escrow.to_account_info().try_borrow_mut_lamports()? -= amount;
+            **recipient.try_borrow_mut_lamports()? += amount;
+        }
+    }
+    Ok(())
+}
+
+/// Checks that `index` is the discovery index page `escrow` is listed on
+///
+/// For index pages Anchor can't check with `seeds`: optional accounts and `remaining_accounts`.
+fn check_escrow_index(index: &Account<EscrowIndex>, escrow: &EscrowAccount) -> Result<()> {
+    let expected = Pubkey::create_program_address(
+        &[
+            b"escrow_index",
+            escrow.index_taker.as_ref(),
+            &escrow.index_page.to_le_bytes(),
+            &[index.bump],
+        ],
+        &crate::ID,
+    )
+    .map_err(|_| error!(ErrorCode::ConstraintSeeds))?;
+    require_keys_eq!(index.key(), expected, ErrorCode::ConstraintSeeds);
+    Ok(())
+}
+
+/// Whether `mint` is a one-of-one token nobody can mint more of
+///
+/// The mint authority must be gone, or be the Metaplex master edition, which takes it over
+/// when a master edition is created and only prints editions from other mints.
+fn is_nft(mint: &Account<Mint>) -> bool {
+    if mint.supply != 1 || mint.decimals != 0 {
+        return false;
+    }
+    match mint.mint_authority {
+        COption::None => true,
+        COption::Some(authority) => {
+            let (edition, _) = Pubkey::find_program_address(
+                &[b"metadata", Metadata::id().as_ref(), mint.key().as_ref(), b"edition"],
+                &Metadata::id(),
+            );
+            authority == edition
+        }
+    }
+}
+
+/// Writes an escrow's metadata as an SPL memo, if the caller passed the Memo program
+///
+/// Escrows without metadata write nothing, so clients can always pass the program.
+fn echo_memo<'info>(
+    memo_program: &Option<Program<'info, Memo>>,
+    escrow: &Account<'info, EscrowAccount>,
+) -> Result<()> {
+    let (Some(memo_program), Some(metadata)) = (memo_program, &escrow.metadata) else {
+        return Ok(());
+    };
+    memo::build_memo(
+        CpiContext::new(memo_program.to_account_info(), BuildMemo {}),
+        metadata.memo(&escrow.key()).as_bytes(),
+    )
+}
+
+/// Checks the counterparty signed a position transfer when the escrow requires it
+///
+/// `required` is the key that must consent, if the escrow has one. Returns whether
+/// consent was given, for the transfer event.
+fn check_transfer_consent(
+    escrow: &EscrowAccount,
+    required: Option<Pubkey>,
+    counterparty: &Option<Signer>,
+) -> Result<bool> {
+    let consented = match (required, counterparty) {
+        (Some(required), Some(counterparty)) => counterparty.key() == required,
+        _ => false,
+    };
+    if escrow.require_transfer_consent && required.is_some() {
+        require!(consented, EscrowError::ConsentRequired);
+    }
+    Ok(consented)
+}
+
+/// Checks a taker's `UserStats` meet the escrow's `min_taker_completed`
+///
+/// Without a stats account the taker counts as having settled nothing.
+fn check_taker_reputation(escrow: &EscrowAccount, stats: &Option<Account<UserStats>>) -> Result<()> {
+    let completed = stats.as_ref().map_or(0, |stats| stats.completed);
+    require!(
+        completed >= escrow.min_taker_completed,
+        EscrowError::TakerReputationTooLow
+    );
+    Ok(())
+}
+
+/// Creates a program-owned PDA the way Anchor's `init` does, even if it was pre-funded
+///
+/// Used by the batch instructions, whose accounts arrive through `remaining_accounts`.
+fn create_pda_account<'info>(
+    payer: &Signer<'info>,
+    target: &AccountInfo<'info>,
+    space: usize,
+    seeds: &[&[u8]],
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    let rent = Rent::get()?.minimum_balance(space);
+    let signer_seeds: &[&[&[u8]]] = &[seeds];
+    let current = target.lamports();
+    if current == 0 {
+        return system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                system_program::CreateAccount {
+                    from: payer.to_account_info(),
+                    to: target.clone(),
+                },
+                signer_seeds,
+            ),
+            rent,
+            space as u64,
+            &crate::ID,
+        );
+    }
+
+    // Someone sent lamports to the address first: top up, then allocate and assign
+    if current < rent {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                system_program::Transfer {
+                    from: payer.to_account_info(),
+                    to: target.clone(),
+                },
+            ),
+            rent - current,
+        )?;
+    }
+    system_program::allocate(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            system_program::Allocate { account_to_allocate: target.clone() },
+            signer_seeds,
+        ),
+        space as u64,
+    )?;
+    system_program::assign(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            system_program::Assign { account_to_assign: target.clone() },
+            signer_seeds,
+        ),
+        &crate::ID,
+    )
+}
+
+/// Pays the escrow's referral fee out of the taker's payment still held by the escrow
+///
+/// The referrer recorded at funding must be the one passed; escrows funded without one
+/// pay no fee and take no referrer, so whoever settles can't route the fee to themselves.
+/// Returns who was paid and how much, for `EscrowCompleted`.
+fn pay_referrer<'info>(
+    escrow: &Account<'info, EscrowAccount>,
+    referrer: &Option<UncheckedAccount<'info>>,
+) -> Result<(Option<Pubkey>, u64)> {
+    if escrow.referral_fee_bps == 0 {
+        return Ok((None, 0));
+    }
+    let referrer = match (escrow.referrer, referrer) {
+        (Some(recorded), Some(referrer)) => {
+            require_keys_eq!(recorded, referrer.key(), EscrowError::ReferrerMismatch);
+            referrer
+        }
+        (Some(_), None) | (None, Some(_)) => return err!(EscrowError::ReferrerMismatch),
+        (None, None) => return Ok((None, 0)),
+    };
+
+    let fee = escrow.referral_fee();
+    **escrow.to_account_info().try_borrow_mut_lamports()? -= fee;
+    **referrer.to_account_info().try_borrow_mut_lamports()? += fee;
+    Ok((Some(referrer.key()), fee))
+}
+
+/// Checks `taker` is on the allowlist whose Merkle root is `root`
+///
+/// Leaves are `sha256(0x00 || taker)` and inner nodes `sha256(0x01 || lo || hi)`, where
+/// `lo`/`hi` are the two children in byte order. Sorting the pair means proofs need no
+/// left/right flags; the prefixes stop an inner node from passing as a leaf.
+fn verify_allowlist_proof(root: &[u8; 32], taker: &Pubkey, proof: &[[u8; 32]]) -> bool {
+    let mut node = hashv(&[&[0], taker.as_ref()]).to_bytes();
+    for sibling in proof {
+        let (lo, hi) = if node <= *sibling {
+            (node, *sibling)
+        } else {
+            (*sibling, node)
+        };
+        node = hashv(&[&[1], &lo, &hi]).to_bytes();
+    }
+    node == *root
+}
+
+/// Moves the NFT out of an escrow's vault to `destination`, then closes the vault
+///
+/// The escrow PDA signs as the vault's authority; the vault's rent goes to `rent_receiver`.
+fn release_nft<'info>(
+    token_program: &Program<'info, Token>,
+    vault: &Account<'info, TokenAccount>,
+    destination: AccountInfo<'info>,
+    escrow: AccountInfo<'info>,
+    rent_receiver: AccountInfo<'info>,
+    escrow_seeds: &[&[u8]],
+) -> Result<()> {
+    let signer_seeds = &[escrow_seeds];
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            token::Transfer {
+                from: vault.to_account_info(),
+                to: destination,
+                authority: escrow.clone(),
+            },
+            signer_seeds,
+        ),
+        1,
+    )?;
+    token::close_account(CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        CloseAccount {
+            account: vault.to_account_info(),
+            destination: rent_receiver,
+            authority: escrow,
+        },
+        signer_seeds,
+    ))
+}
+
+/// Closes an escrow that has finished, returning its rent to the `rent_payer` who paid it
+///
+/// An escrow still holding a receipt claim or unwithdrawn bids stays open, inactive,
+/// since `redeem_receipt` and `withdraw_bid` need it; the last of those closes it instead.
+fn close_if_settled<'info>(
+    escrow: &mut Account<'info, EscrowAccount>,
+    rent_payer: &AccountInfo<'info>,
+) -> Result<()> {
+    if escrow.is_active || escrow.maker_claim > 0 || escrow.taker_claim > 0 || escrow.bid_count > 0 {
+        return Ok(());
+    }
+    escrow.close(rent_payer.clone())
+}
+
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    /// Config PDA: seeds = ["config"]
+    #[account(
+        init,
+        payer = admin,
+        space = Config::calculate_max_space(),
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, Config>,
+
+    /// This program, to find its program data account
+    #[account(constraint = escrow_program.programdata_address()? == Some(program_data.key()))]
+    pub escrow_program: Program<'info, crate::program::Escrow>,
+
+    /// The program's upgrade authority record
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(admin.key()) @ EscrowError::Unauthorized
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    /// The upgrade authority, who becomes the admin
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetPaused<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, Config>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct OpenUserStats<'info> {
+    /// The wallet's stats PDA: seeds = ["user_stats", wallet]
+    #[account(
+        init,
+        payer = payer,
+        space = UserStats::calculate_max_space(),
+        seeds = [b"user_stats", wallet.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+
+    /// Wallet whose track record this is
+    /// CHECK: Only used as a seed; any wallet, a multisig PDA included, can have a record
+    pub wallet: UncheckedAccount<'info>,
+
+    /// Pays the record's rent, normally the wallet itself
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(
+    escrow_id: Option<u64>,
+    amount_a: u64,
+    amount_b_expected: u64,
+    expiry_ts: i64,
+    taker_pubkey: Pubkey,
+    keeper_tip: u64,
+    index_page: u32,
+    options: EscrowOptions,
+)]
+pub struct CreateEscrow<'info> {
+    /// Maker profile PDA holding the automatic escrow ID counter: seeds = ["maker_profile", maker]
+    #[account(
+        init_if_needed,
+        payer = rent_payer,
+        space = MakerProfile::calculate_max_space(),
+        seeds = [b"maker_profile", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_profile: Account<'info, MakerProfile>,
+
+    /// Escrow PDA: seeds = ["escrow", maker, escrow_id]
+    /// When `escrow_id` is None the maker profile's next ID is used
+    #[account(
+        init,
+        payer = rent_payer,
+        space = EscrowAccount::calculate_max_space(),
+        seeds = [
+            b"escrow",
+            maker.key().as_ref(),
+            &escrow_id.unwrap_or(maker_profile.next_escrow_id).to_le_bytes()
+        ],
+        bump
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Taker's discovery index page: seeds = ["escrow_index", taker, index_page]
+    #[account(
+        init_if_needed,
+        payer = rent_payer,
+        space = EscrowIndex::calculate_max_space(),
+        seeds = [b"escrow_index", taker_pubkey.as_ref(), &index_page.to_le_bytes()],
+        bump
+    )]
+    pub escrow_index: Account<'info, EscrowIndex>,
+
+    /// Maker creating the escrow
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    /// Pays the rent for every account created here; may be the maker or a sponsor
+    #[account(mut)]
+    pub rent_payer: Signer<'info>,
+
+    /// Program config; deposits are refused while paused
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ EscrowError::ProgramPaused
+    )]
+    pub config: Account<'info, Config>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct FundEscrow<'info> {
+    /// Escrow must exist (PDA)
+    #[account(mut, has_one = maker)]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Taker funds the escrow
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    /// Maker (for has_one check)
+    /// CHECK: This account is used for has_one constraint validation on the escrow account
+    pub maker: UncheckedAccount<'info>,
+
+    /// Pyth price feed; required for oracle-priced escrows
+    /// CHECK: Must match `escrow.oracle.price_feed`; owner and layout are checked when read
+    pub price_feed: Option<UncheckedAccount<'info>>,
+
+    /// Frontend that routed the taker, recorded to receive the referral fee
+    /// CHECK: Only its key is stored, after checking it isn't the taker, attester or escrow
+    pub referrer: Option<UncheckedAccount<'info>>,
+
+    /// Taker's track record; required if the escrow sets `min_taker_completed`
+    #[account(constraint = taker_stats.wallet == taker.key() @ EscrowError::Unauthorized)]
+    pub taker_stats: Option<Account<'info, UserStats>>,
+
+    /// Program config; deposits are refused while paused
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ EscrowError::ProgramPaused
+    )]
+    pub config: Account<'info, Config>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CompleteSwap<'info> {
+    #[account(mut, has_one = maker, has_one = rent_payer)]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Taker's discovery index page listing this escrow
+    #[account(
+        mut,
+        seeds = [b"escrow_index", escrow.index_taker.as_ref(), &escrow.index_page.to_le_bytes()],
+        bump = escrow_index.bump
+    )]
+    pub escrow_index: Account<'info, EscrowIndex>,
+
+    /// Taker finalizes the swap (must equal escrow.taker)
+    /// Pays for either side's `UserStats` if it doesn't exist yet
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    /// Maker (not signer here)
+    /// CHECK: This account is validated through the escrow's maker field constraint
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+
+    /// Gets the escrow's rent back when it closes
+    /// CHECK: This account is validated through the escrow's rent_payer field constraint
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    /// Receives the referral fee; required if one was recorded at funding, refused otherwise
+    /// CHECK: Checked against `escrow.referrer` in `pay_referrer`; only credited
+    #[account(mut)]
+    pub referrer: Option<UncheckedAccount<'info>>,
+
+    /// SPL Memo program; pass it to echo the escrow's metadata on settlement
+    pub memo_program: Option<Program<'info, Memo>>,
+
+    /// Maker's track record: seeds = ["user_stats", maker]
+    #[account(
+        init_if_needed,
+        payer = taker,
+        space = UserStats::calculate_max_space(),
+        seeds = [b"user_stats", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_stats: Box<Account<'info, UserStats>>,
+
+    /// Taker's track record: seeds = ["user_stats", taker]
+    #[account(
+        init_if_needed,
+        payer = taker,
+        space = UserStats::calculate_max_space(),
+        seeds = [b"user_stats", taker.key().as_ref()],
+        bump
+    )]
+    pub taker_stats: Box<Account<'info, UserStats>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CancelEscrow<'info> {
+    #[account(mut, has_one = maker, has_one = rent_payer)]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Taker's discovery index page listing this escrow
+    #[account(
+        mut,
+        seeds = [b"escrow_index", escrow.index_taker.as_ref(), &escrow.index_page.to_le_bytes()],
+        bump = escrow_index.bump
+    )]
+    pub escrow_index: Account<'info, EscrowIndex>,
+
+    /// Maker receives the refund (not necessarily the signer)
+    /// CHECK: This account is validated through the escrow's maker field constraint
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+
+    /// Gets the escrow's rent back when it closes
+    /// CHECK: This account is validated through the escrow's rent_payer field constraint
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    /// Taker receives their deposit back plus the penalty; required to cancel a funded escrow
+    /// CHECK: Checked against `escrow.taker` before it is paid; only credited
+    #[account(mut)]
+    pub taker: Option<UncheckedAccount<'info>>,
+
+    /// The maker, or the escrow's operator with `OPERATOR_CAN_CANCEL` while it is unfunded
+    /// Pays for the maker's `UserStats` if it doesn't exist yet
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// Maker's track record: seeds = ["user_stats", maker]
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = UserStats::calculate_max_space(),
+        seeds = [b"user_stats", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_stats: Box<Account<'info, UserStats>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RefundAfterExpiry<'info> {
+    #[account(mut, has_one = maker, has_one = rent_payer)]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Taker's discovery index page listing this escrow
+    #[account(
+        mut,
+        seeds = [b"escrow_index", escrow.index_taker.as_ref(), &escrow.index_page.to_le_bytes()],
+        bump = escrow_index.bump
+    )]
+    pub escrow_index: Account<'info, EscrowIndex>,
+
+    /// Maker receives the refund (not necessarily the signer)
+    /// CHECK: This account is validated through the escrow's maker field constraint
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+
+    /// Gets the escrow's rent back when it closes
+    /// CHECK: This account is validated through the escrow's rent_payer field constraint
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    /// The maker, or the escrow's operator with `OPERATOR_CAN_REFUND`
+    /// Pays for the maker's `UserStats` if it doesn't exist yet
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// Maker's track record: seeds = ["user_stats", maker]
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = UserStats::calculate_max_space(),
+        seeds = [b"user_stats", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_stats: Box<Account<'info, UserStats>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CrankExpired<'info> {
+    #[account(mut, has_one = maker, has_one = rent_payer)]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Taker's discovery index page listing this escrow
+    #[account(
+        mut,
+        seeds = [b"escrow_index", escrow.index_taker.as_ref(), &escrow.index_page.to_le_bytes()],
+        bump = escrow_index.bump
+    )]
+    pub escrow_index: Account<'info, EscrowIndex>,
+
+    /// Maker receives the refund (not signer here)
+    /// CHECK: This account is validated through the escrow's maker field constraint
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+
+    /// Gets the escrow's rent back when it closes
+    /// CHECK: This account is validated through the escrow's rent_payer field constraint
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    /// Anyone may crank an expired escrow and collect the bounty
+    /// Pays for the maker's `UserStats` if it doesn't exist yet
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+
+    /// Maker's track record: seeds = ["user_stats", maker]
+    #[account(
+        init_if_needed,
+        payer = keeper,
+        space = UserStats::calculate_max_space(),
+        seeds = [b"user_stats", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_stats: Box<Account<'info, UserStats>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(
+    escrow_id: Option<u64>,
+    amount_b_expected: u64,
+    expiry_ts: i64,
+    taker_pubkey: Pubkey,
+    collection: Option<Pubkey>,
+    index_page: u32,
+)]
+pub struct CreateNftEscrow<'info> {
+    /// Maker profile PDA holding the automatic escrow ID counter: seeds = ["maker_profile", maker]
+    #[account(
+        init_if_needed,
+        payer = rent_payer,
+        space = MakerProfile::calculate_max_space(),
+        seeds = [b"maker_profile", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_profile: Box<Account<'info, MakerProfile>>,
+
+    /// Escrow PDA: seeds = ["escrow", maker, escrow_id]
+    /// When `escrow_id` is None the maker profile's next ID is used
+    #[account(
+        init,
+        payer = rent_payer,
+        space = EscrowAccount::calculate_max_space(),
+        seeds = [
+            b"escrow",
+            maker.key().as_ref(),
+            &escrow_id.unwrap_or(maker_profile.next_escrow_id).to_le_bytes()
+        ],
+        bump
+    )]
+    pub escrow: Box<Account<'info, EscrowAccount>>,
+
+    /// Taker's discovery index page: seeds = ["escrow_index", taker, index_page]
+    #[account(
+        init_if_needed,
+        payer = rent_payer,
+        space = EscrowIndex::calculate_max_space(),
+        seeds = [b"escrow_index", taker_pubkey.as_ref(), &index_page.to_le_bytes()],
+        bump
+    )]
+    pub escrow_index: Box<Account<'info, EscrowIndex>>,
+
+    /// The NFT being offered: exactly one token, no decimals, and no mint authority but its master edition
+    #[account(constraint = is_nft(&nft_mint) @ EscrowError::NotAnNft)]
+    pub nft_mint: Box<Account<'info, Mint>>,
+
+    /// Maker's token account currently holding the NFT
+    #[account(mut, token::mint = nft_mint, token::authority = maker)]
+    pub maker_nft_account: Box<Account<'info, TokenAccount>>,
+
+    /// Vault holding the NFT: the escrow PDA's associated token account
+    ///
+    /// `init_if_needed` because anyone can create this predictable address first.
+    #[account(
+        init_if_needed,
+        payer = rent_payer,
+        associated_token::mint = nft_mint,
+        associated_token::authority = escrow
+    )]
+    pub vault: Box<Account<'info, TokenAccount>>,
+
+    /// Metaplex metadata PDA for the mint; required only when `collection` is set
+    #[account(
+        seeds = [b"metadata", Metadata::id().as_ref(), nft_mint.key().as_ref()],
+        seeds::program = Metadata::id(),
+        bump
+    )]
+    pub nft_metadata: Option<Box<Account<'info, MetadataAccount>>>,
+
+    /// Maker creating the escrow
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    /// Pays the rent for every account created here; may be the maker or a sponsor
+    #[account(mut)]
+    pub rent_payer: Signer<'info>,
+
+    /// Program config; deposits are refused while paused
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ EscrowError::ProgramPaused
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CompleteNftSwap<'info> {
+    #[account(
+        mut,
+        has_one = maker,
+        has_one = rent_payer,
+        constraint = escrow.nft_mint == Some(nft_mint.key()) @ EscrowError::NftMintMismatch
+    )]
+    pub escrow: Box<Account<'info, EscrowAccount>>,
+
+    /// Taker's discovery index page listing this escrow
+    #[account(
+        mut,
+        seeds = [b"escrow_index", escrow.index_taker.as_ref(), &escrow.index_page.to_le_bytes()],
+        bump = escrow_index.bump
+    )]
+    pub escrow_index: Box<Account<'info, EscrowIndex>>,
+
+    /// The escrowed NFT's mint
+    pub nft_mint: Box<Account<'info, Mint>>,
+
+    /// Vault holding the NFT
+    #[account(
+        mut,
+        associated_token::mint = nft_mint,
+        associated_token::authority = escrow
+    )]
+    pub vault: Box<Account<'info, TokenAccount>>,
+
+    /// Taker's associated token account for the NFT, created if missing
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = nft_mint,
+        associated_token::authority = taker
+    )]
+    pub taker_nft_account: Box<Account<'info, TokenAccount>>,
+
+    /// Taker finalizes the swap (must equal escrow.taker)
+    /// Pays for either side's `UserStats` if it doesn't exist yet
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    /// Maker receives the SOL (not signer here)
+    /// CHECK: This account is validated through the escrow's maker field constraint
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+
+    /// Gets the vault's rent back
+    /// CHECK: This account is validated through the escrow's rent_payer field constraint
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    /// SPL Memo program; pass it to echo the escrow's metadata on settlement
+    pub memo_program: Option<Program<'info, Memo>>,
+
+    /// Maker's track record: seeds = ["user_stats", maker]
+    #[account(
+        init_if_needed,
+        payer = taker,
+        space = UserStats::calculate_max_space(),
+        seeds = [b"user_stats", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_stats: Box<Account<'info, UserStats>>,
+
+    /// Taker's track record: seeds = ["user_stats", taker]
+    #[account(
+        init_if_needed,
+        payer = taker,
+        space = UserStats::calculate_max_space(),
+        seeds = [b"user_stats", taker.key().as_ref()],
+        bump
+    )]
+    pub taker_stats: Box<Account<'info, UserStats>>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CancelNftEscrow<'info> {
+    #[account(
+        mut,
+        has_one = maker,
+        has_one = rent_payer,
+        constraint = escrow.nft_mint == Some(nft_mint.key()) @ EscrowError::NftMintMismatch
+    )]
+    pub escrow: Box<Account<'info, EscrowAccount>>,
+
+    /// Taker's discovery index page listing this escrow
+    #[account(
+        mut,
+        seeds = [b"escrow_index", escrow.index_taker.as_ref(), &escrow.index_page.to_le_bytes()],
+        bump = escrow_index.bump
+    )]
+    pub escrow_index: Box<Account<'info, EscrowIndex>>,
+
+    /// The escrowed NFT's mint
+    pub nft_mint: Box<Account<'info, Mint>>,
+
+    /// Vault holding the NFT
+    #[account(
+        mut,
+        associated_token::mint = nft_mint,
+        associated_token::authority = escrow
+    )]
+    pub vault: Box<Account<'info, TokenAccount>>,
+
+    /// Maker's associated token account for the NFT, created if missing
+    #[account(
+        init_if_needed,
+        payer = maker,
+        associated_token::mint = nft_mint,
+        associated_token::authority = maker
+    )]
+    pub maker_nft_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    /// Gets the vault's rent back
+    /// CHECK: This account is validated through the escrow's rent_payer field constraint
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    /// Maker's track record: seeds = ["user_stats", maker]
+    #[account(
+        init_if_needed,
+        payer = maker,
+        space = UserStats::calculate_max_space(),
+        seeds = [b"user_stats", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_stats: Box<Account<'info, UserStats>>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Attest<'info> {
+    #[account(
+        mut,
+        has_one = maker,
+        has_one = rent_payer,
+        constraint = escrow.taker == Some(taker.key()) @ EscrowError::Unauthorized
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Taker's discovery index page listing this escrow
+    #[account(
+        mut,
+        seeds = [b"escrow_index", escrow.index_taker.as_ref(), &escrow.index_page.to_le_bytes()],
+        bump = escrow_index.bump
+    )]
+    pub escrow_index: Account<'info, EscrowIndex>,
+
+    /// The escrow's designated attester (must equal escrow.attester)
+    /// Pays for either side's `UserStats` if it doesn't exist yet
+    #[account(mut)]
+    pub attester: Signer<'info>,
+
+    /// Taker receives `amount_a` on release or their payment back on refund (not signer here)
+    /// CHECK: This account is validated against the escrow's taker field
+    #[account(mut)]
+    pub taker: UncheckedAccount<'info>,
+
+    /// Maker receives the payment on release or their offer back on refund (not signer here)
+    /// CHECK: This account is validated through the escrow's maker field constraint
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+
+    /// Gets the escrow's rent back when it closes
+    /// CHECK: This account is validated through the escrow's rent_payer field constraint
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    /// Receives the referral fee on release; see `CompleteSwap::referrer`
+    /// CHECK: Checked against `escrow.referrer` in `pay_referrer`; only credited
+    #[account(mut)]
+    pub referrer: Option<UncheckedAccount<'info>>,
+
+    /// SPL Memo program; pass it to echo the escrow's metadata on settlement
+    pub memo_program: Option<Program<'info, Memo>>,
+
+    /// Maker's track record: seeds = ["user_stats", maker]
+    #[account(
+        init_if_needed,
+        payer = attester,
+        space = UserStats::calculate_max_space(),
+        seeds = [b"user_stats", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_stats: Box<Account<'info, UserStats>>,
+
+    /// Taker's track record: seeds = ["user_stats", taker]
+    #[account(
+        init_if_needed,
+        payer = attester,
+        space = UserStats::calculate_max_space(),
+        seeds = [b"user_stats", taker.key().as_ref()],
+        bump
+    )]
+    pub taker_stats: Box<Account<'info, UserStats>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SlashTakerBond<'info> {
+    #[account(
+        mut,
+        has_one = maker,
+        has_one = rent_payer,
+        constraint = escrow.taker == Some(taker.key()) @ EscrowError::Unauthorized
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Taker's discovery index page listing this escrow
+    #[account(
+        mut,
+        seeds = [b"escrow_index", escrow.index_taker.as_ref(), &escrow.index_page.to_le_bytes()],
+        bump = escrow_index.bump
+    )]
+    pub escrow_index: Account<'info, EscrowIndex>,
+
+    /// Maker receives their offer back plus the bond (not necessarily the signer)
+    /// CHECK: This account is validated through the escrow's maker field constraint
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+
+    /// Taker receives their payment back (not signer here)
+    /// CHECK: This account is validated against the escrow's taker field
+    #[account(mut)]
+    pub taker: UncheckedAccount<'info>,
+
+    /// Gets the escrow's rent back when it closes
+    /// CHECK: This account is validated through the escrow's rent_payer field constraint
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    /// The maker, or the escrow's operator with `OPERATOR_CAN_REFUND`
+    /// Pays for the taker's `UserStats` if it doesn't exist yet
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// SPL Memo program; pass it to echo the escrow's metadata on settlement
+    pub memo_program: Option<Program<'info, Memo>>,
+
+    /// Taker's track record: seeds = ["user_stats", taker]
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = UserStats::calculate_max_space(),
+        seeds = [b"user_stats", taker.key().as_ref()],
+        bump
+    )]
+    pub taker_stats: Box<Account<'info, UserStats>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AmendEscrow<'info> {
+    #[account(mut)]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// The maker, or the escrow's operator with `OPERATOR_CAN_AMEND`
+    pub authority: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetOperator<'info> {
+    #[account(mut, has_one = maker)]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Only the maker can appoint or revoke an operator
+    pub maker: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct TransferMakerRights<'info> {
+    #[account(mut, has_one = maker)]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// The current maker, handing over the position
+    pub maker: Signer<'info>,
+
+    /// The taker, co-signing when the escrow requires consent
+    pub counterparty: Option<Signer<'info>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(new_taker: Pubkey, index_page: u32)]
+pub struct TransferTakerRights<'info> {
+    #[account(mut)]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Discovery index page listing the escrow now
+    #[account(
+        mut,
+        seeds = [b"escrow_index", escrow.index_taker.as_ref(), &escrow.index_page.to_le_bytes()],
+        bump = escrow_index.bump
+    )]
+    pub escrow_index: Account<'info, EscrowIndex>,
+
+    /// New taker's discovery index page: seeds = ["escrow_index", new_taker, index_page]
+    #[account(
+        init_if_needed,
+        payer = taker,
+        space = EscrowIndex::calculate_max_space(),
+        seeds = [b"escrow_index", new_taker.as_ref(), &index_page.to_le_bytes()],
+        bump
+    )]
+    pub new_escrow_index: Account<'info, EscrowIndex>,
+
+    /// The current taker, handing over the position (must equal escrow.taker)
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    /// The maker, co-signing when the escrow requires consent
+    pub counterparty: Option<Signer<'info>>,
+
+    /// New taker's track record; required if the escrow sets `min_taker_completed`
+    #[account(constraint = new_taker_stats.wallet == new_taker @ EscrowError::Unauthorized)]
+    pub new_taker_stats: Option<Account<'info, UserStats>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(side: ReceiptSide)]
+pub struct MintReceipt<'info> {
+    #[account(mut)]
+    pub escrow: Box<Account<'info, EscrowAccount>>,
+
+    /// Receipt mint PDA: seeds = ["receipt", escrow, side]
+    #[account(
+        init,
+        payer = depositor,
+        seeds = [b"receipt", escrow.key().as_ref(), &[side as u8]],
+        bump,
+        mint::decimals = 0,
+        mint::authority = escrow
+    )]
+    pub receipt_mint: Box<Account<'info, Mint>>,
+
+    /// Depositor's associated token account for the receipt, created if missing
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        associated_token::mint = receipt_mint,
+        associated_token::authority = depositor
+    )]
+    pub depositor_receipt_account: Box<Account<'info, TokenAccount>>,
+
+    /// The maker, or the funded taker, tokenizing their payout
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(side: ReceiptSide)]
+pub struct RedeemReceipt<'info> {
+    #[account(mut, has_one = rent_payer)]
+    pub escrow: Box<Account<'info, EscrowAccount>>,
+
+    /// The escrow's receipt mint for `side`
+    #[account(
+        mut,
+        constraint = escrow.receipt(side) == Some(receipt_mint.key()) @ EscrowError::ReceiptMismatch
+    )]
+    pub receipt_mint: Box<Account<'info, Mint>>,
+
+    /// Holder's token account with the receipt
+    #[account(
+        mut,
+        token::mint = receipt_mint,
+        token::authority = holder
+    )]
+    pub holder_receipt_account: Box<Account<'info, TokenAccount>>,
+
+    /// Whoever holds the receipt now; receives the claim
+    #[account(mut)]
+    pub holder: Signer<'info>,
+
+    /// Gets the escrow's rent back when the last claim is redeemed
+    /// CHECK: This account is validated through the escrow's rent_payer field constraint
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CreateMultisig<'info> {
+    /// Multisig PDA: seeds = ["multisig", create_key]
+    #[account(
+        init,
+        payer = payer,
+        space = Multisig::calculate_max_space(),
+        seeds = [b"multisig", create_key.key().as_ref()],
+        bump
+    )]
+    pub multisig: Account<'info, Multisig>,
+
+    /// Throwaway key that makes the multisig's address unique
+    pub create_key: Signer<'info>,
+
+    /// Whoever pays the rent for the multisig
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(
+    escrow_id: Option<u64>,
+    amount_a: u64,
+    amount_b_expected: u64,
+    expiry_ts: i64,
+    taker_pubkey: Pubkey,
+    keeper_tip: u64,
+    index_page: u32,
+)]
+pub struct CreateMultisigEscrow<'info> {
+    /// The multisig acting as maker
+    #[account(seeds = [b"multisig", multisig.create_key.as_ref()], bump = multisig.bump)]
+    pub multisig: Account<'info, Multisig>,
+
+    /// The multisig's maker profile PDA: seeds = ["maker_profile", multisig]
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = MakerProfile::calculate_max_space(),
+        seeds = [b"maker_profile", multisig.key().as_ref()],
+        bump
+    )]
+    pub maker_profile: Account<'info, MakerProfile>,
+
+    /// Escrow PDA: seeds = ["escrow", multisig, escrow_id]
+    #[account(
+        init,
+        payer = owner,
+        space = EscrowAccount::calculate_max_space(),
+        seeds = [
+            b"escrow",
+            multisig.key().as_ref(),
+            &escrow_id.unwrap_or(maker_profile.next_escrow_id).to_le_bytes()
+        ],
+        bump
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Taker's discovery index page: seeds = ["escrow_index", taker, index_page]
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = EscrowIndex::calculate_max_space(),
+        seeds = [b"escrow_index", taker_pubkey.as_ref(), &index_page.to_le_bytes()],
+        bump
+    )]
+    pub escrow_index: Account<'info, EscrowIndex>,
+
+    /// Owner depositing the offer on the multisig's behalf
+    #[account(
+        mut,
+        constraint = multisig.is_owner(&owner.key()) @ EscrowError::NotMultisigOwner
+    )]
+    pub owner: Signer<'info>,
+
+    /// Program config; deposits are refused while paused
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ EscrowError::ProgramPaused
+    )]
+    pub config: Account<'info, Config>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Propose<'info> {
+    #[account(mut, seeds = [b"multisig", multisig.create_key.as_ref()], bump = multisig.bump)]
+    pub multisig: Account<'info, Multisig>,
+
+    /// Proposal PDA: seeds = ["proposal", multisig, multisig.proposal_count]
+    #[account(
+        init,
+        payer = proposer,
+        space = Proposal::calculate_max_space(),
+        seeds = [
+            b"proposal",
+            multisig.key().as_ref(),
+            &multisig.proposal_count.to_le_bytes()
+        ],
+        bump
+    )]
+    pub proposal: Account<'info, Proposal>,
+
+    /// The escrow the action targets; omit for `Withdraw`
+    pub escrow: Option<Account<'info, EscrowAccount>>,
+
+    /// Owner opening the proposal; pays its rent
+    #[account(
+        mut,
+        constraint = multisig.is_owner(&proposer.key()) @ EscrowError::NotMultisigOwner
+    )]
+    pub proposer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveProposal<'info> {
+    #[account(seeds = [b"multisig", multisig.create_key.as_ref()], bump = multisig.bump)]
+    pub multisig: Account<'info, Multisig>,
+
+    #[account(mut, has_one = multisig)]
+    pub proposal: Account<'info, Proposal>,
+
+    /// The escrow the proposal targets; omit for `Withdraw`
+    pub escrow: Option<Account<'info, EscrowAccount>>,
+
+    #[account(constraint = multisig.is_owner(&owner.key()) @ EscrowError::NotMultisigOwner)]
+    pub owner: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ExecuteProposal<'info> {
+    /// The multisig, whose balance is the treasury
+    #[account(mut, seeds = [b"multisig", multisig.create_key.as_ref()], bump = multisig.bump)]
+    pub multisig: Account<'info, Multisig>,
+
+    /// Closed once executed, rent back to the proposer
+    #[account(mut, has_one = multisig, has_one = proposer, close = proposer)]
+    pub proposal: Account<'info, Proposal>,
+
+    /// The escrow the proposal targets; omit for `Withdraw`
+    #[account(mut)]
+    pub escrow: Option<Account<'info, EscrowAccount>>,
+
+    /// Taker's discovery index page listing the escrow; needed for `Cancel` and `Refund`,
+    /// and checked against the escrow's `index_taker` and `index_page`
+    #[account(mut)]
+    pub escrow_index: Option<Account<'info, EscrowIndex>>,
+
+    /// Gets the escrow's rent back on `Cancel` and `Refund`; must be the escrow's `rent_payer`
+    /// CHECK: Checked against `escrow.rent_payer` before the escrow is closed to it
+    #[account(mut)]
+    pub rent_payer: Option<UncheckedAccount<'info>>,
+
+    /// The multisig's track record as maker: seeds = ["user_stats", multisig]
+    /// Needed for `Cancel` and `Refund`
+    #[account(
+        init_if_needed,
+        payer = executor,
+        space = UserStats::calculate_max_space(),
+        seeds = [b"user_stats", multisig.key().as_ref()],
+        bump
+    )]
+    pub maker_stats: Option<Box<Account<'info, UserStats>>>,
+
+    /// Where a `Withdraw` sends the lamports
+    /// CHECK: Must match the destination recorded in the proposal
+    #[account(mut)]
+    pub destination: Option<UncheckedAccount<'info>>,
+
+    /// Receives the proposal's rent
+    /// CHECK: Validated through the proposal's proposer field constraint
+    #[account(mut)]
+    pub proposer: UncheckedAccount<'info>,
+
+    /// Any owner may execute once the threshold is met
+    /// Pays for the multisig's `UserStats` if it doesn't exist yet
+    #[account(mut, constraint = multisig.is_owner(&executor.key()) @ EscrowError::NotMultisigOwner)]
+    pub executor: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct BatchCreateEscrows<'info> {
+    /// Maker profile PDA holding the automatic escrow ID counter: seeds = ["maker_profile", maker]
+    #[account(
+        init_if_needed,
+        payer = rent_payer,
+        space = MakerProfile::calculate_max_space(),
+        seeds = [b"maker_profile", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_profile: Account<'info, MakerProfile>,
+
+    /// Maker creating every escrow in the batch
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    /// Pays the rent for every account created here; may be the maker or a sponsor
+    #[account(mut)]
+    pub rent_payer: Signer<'info>,
+
+    /// Program config; deposits are refused while paused
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ EscrowError::ProgramPaused
+    )]
+    pub config: Account<'info, Config>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct BatchCancelEscrows<'info> {
+    /// Maker of every escrow in the batch; receives the refunds (not necessarily the signer)
+    /// CHECK: Each escrow's maker field is checked against this key
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+
+    /// The maker, or an operator with `OPERATOR_CAN_CANCEL` on every escrow in the batch
+    /// Pays for the maker's `UserStats` if it doesn't exist yet
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// Maker's track record: seeds = ["user_stats", maker]
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = UserStats::calculate_max_space(),
+        seeds = [b"user_stats", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_stats: Box<Account<'info, UserStats>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(subscription_id: u64)]
+pub struct CreateSubscription<'info> {
+    /// Subscription PDA: seeds = ["subscription", payer, subscription_id]
+    #[account(
+        init,
+        payer = payer,
+        space = Subscription::calculate_max_space(),
+        seeds = [b"subscription", payer.key().as_ref(), &subscription_id.to_le_bytes()],
+        bump
+    )]
+    pub subscription: Account<'info, Subscription>,
+
+    /// Payer pre-funding the periods
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// Program config; deposits are refused while paused
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ EscrowError::ProgramPaused
+    )]
+    pub config: Account<'info, Config>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CollectPeriod<'info> {
+    #[account(mut, has_one = payer, has_one = payee)]
+    pub subscription: Account<'info, Subscription>,
+
+    /// Payee pulling the period
+    #[account(mut)]
+    pub payee: Signer<'info>,
+
+    /// Payer; gets the rent back after the last period (not signer here)
+    /// CHECK: This account is validated through the subscription's payer field constraint
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CancelSubscription<'info> {
+    #[account(mut, has_one = payer, has_one = payee)]
+    pub subscription: Account<'info, Subscription>,
+
+    /// Payer ending the subscription
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// Payee; receives periods already earned (not signer here)
+    /// CHECK: This account is validated through the subscription's payee field constraint
+    #[account(mut)]
+    pub payee: UncheckedAccount<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct PlaceBid<'info> {
+    #[account(mut)]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Bidder's bid PDA: seeds = ["bid", escrow, bidder]
+    #[account(
+        init_if_needed,
+        payer = bidder,
+        space = Bid::calculate_max_space(),
+        seeds = [b"bid", escrow.key().as_ref(), bidder.key().as_ref()],
+        bump
+    )]
+    pub bid: Account<'info, Bid>,
+
+    #[account(mut)]
+    pub bidder: Signer<'info>,
+
+    /// The leading bid being outbid; required unless there is none or it's the bidder's own
+    #[account(mut)]
+    pub previous_bid: Option<Account<'info, Bid>>,
+
+    /// The outbid leader, refunded their bid and its rent
+    /// CHECK: Checked against `escrow.highest_bidder`; only credited
+    #[account(mut)]
+    pub previous_bidder: Option<UncheckedAccount<'info>>,
+
+    /// Program config; deposits are refused while paused
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ EscrowError::ProgramPaused
+    )]
+    pub config: Account<'info, Config>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CommitBid<'info> {
+    #[account(mut)]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Bidder's bid PDA: seeds = ["bid", escrow, bidder]
+    #[account(
+        init,
+        payer = bidder,
+        space = Bid::calculate_max_space(),
+        seeds = [b"bid", escrow.key().as_ref(), bidder.key().as_ref()],
+        bump
+    )]
+    pub bid: Account<'info, Bid>,
+
+    #[account(mut)]
+    pub bidder: Signer<'info>,
+
+    /// Program config; deposits are refused while paused
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ EscrowError::ProgramPaused
+    )]
+    pub config: Account<'info, Config>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RevealBid<'info> {
+    #[account(mut)]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        mut,
+        has_one = escrow,
+        has_one = bidder,
+        seeds = [b"bid", escrow.key().as_ref(), bidder.key().as_ref()],
+        bump = bid.bump
+    )]
+    pub bid: Account<'info, Bid>,
+
+    pub bidder: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct WithdrawBid<'info> {
+    #[account(mut, has_one = rent_payer)]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        mut,
+        has_one = escrow,
+        has_one = bidder,
+        seeds = [b"bid", escrow.key().as_ref(), bidder.key().as_ref()],
+        bump = bid.bump
+    )]
+    pub bid: Account<'info, Bid>,
+
+    /// Gets the deposit and the bid's rent back
+    #[account(mut)]
+    pub bidder: Signer<'info>,
+
+    /// Gets the escrow's rent back when the last bid on a finished auction is withdrawn
+    /// CHECK: This account is validated through the escrow's rent_payer field constraint
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SettleAuction<'info> {
+    #[account(mut, has_one = maker, has_one = rent_payer)]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Listing index page for this escrow
+    #[account(
+        mut,
+        seeds = [b"escrow_index", escrow.index_taker.as_ref(), &escrow.index_page.to_le_bytes()],
+        bump = escrow_index.bump
+    )]
+    pub escrow_index: Account<'info, EscrowIndex>,
+
+    /// The leading bid, paid to the maker
+    #[account(mut)]
+    pub winning_bid: Account<'info, Bid>,
+
+    /// The leading bidder; receives `amount_a` (not signer here)
+    /// CHECK: Checked against `escrow.highest_bidder`; only credited
+    #[account(mut)]
+    pub winner: UncheckedAccount<'info>,
+
+    /// Maker (not signer here)
+    /// CHECK: This account is validated through the escrow's maker field constraint
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+
+    /// Gets the escrow's rent back when it closes
+    /// CHECK: This account is validated through the escrow's rent_payer field constraint
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    /// Anyone may settle
+    /// Pays for either side's `UserStats` if it doesn't exist yet
+    #[account(mut)]
+    pub settler: Signer<'info>,
+
+    /// SPL Memo program; pass it to echo the escrow's metadata on settlement
+    pub memo_program: Option<Program<'info, Memo>>,
+
+    /// Maker's track record: seeds = ["user_stats", maker]
+    #[account(
+        init_if_needed,
+        payer = settler,
+        space = UserStats::calculate_max_space(),
+        seeds = [b"user_stats", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_stats: Box<Account<'info, UserStats>>,
+
+    /// Winner's track record: seeds = ["user_stats", winner]
+    #[account(
+        init_if_needed,
+        payer = settler,
+        space = UserStats::calculate_max_space(),
+        seeds = [b"user_stats", winner.key().as_ref()],
+        bump
+    )]
+    pub winner_stats: Box<Account<'info, UserStats>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+
+/// # Escrow Account Structure
+///
+/// This is the **main data structure** that stores all information about an escrow transaction.
+/// Think of it as a digital "contract" that both parties agree to follow.
+///
+/// ## What It Stores
+///
+/// - **Unique ID**: Each escrow has a unique identifier (like a transaction number)
+/// - **SOL Details**: How much SOL is being exchanged
+/// - **Party Information**: Who created the escrow and who can take it
+/// - **Time Controls**: When the escrow expires and when it was created
+/// - **Current State**: Whether it's waiting for funding, ready to complete, or expired
+///
+/// ## Security Importance
+///
+/// Only a few fields change after creation, each through one instruction:
+/// - **Price and expiry**: `amend_escrow` or a multisig `Amend` proposal, while unfunded,
+///   bumping `terms_version`
+/// - **Maker and taker**: `transfer_maker_rights` and `transfer_taker_rights`
+/// - **Operator**: `set_operator` and `revoke_operator`
+///
+/// Everything else is fixed at creation, apart from status updates.
+#[account]
+pub struct EscrowAccount {
+    /// The person who created this escrow (Party A)
+    /// This person deposits SOL and sets the terms
+    /// CHECK: The maker is validated as signer in the CreateEscrow instruction.
+    pub maker: Pubkey,
+
+    /// The person who can take this escrow offer (Party B)
+    /// If Some(key), only that specific person can. None until funded for allowlisted escrows
+    /// CHECK: The taker is set from the validated taker account in FundEscrow.
+    pub taker: Option<Pubkey>,
+
+    /// Unique identifier for this escrow among the maker's (like a transaction ID)
+    /// Assigned from `MakerProfile.next_escrow_id` unless the creator passes one
+    pub escrow_id: u64,
+
+    /// How much SOL the maker is offering
+    /// This amount is locked in the escrow until the swap completes
+    pub amount_a: u64,
+
+    /// How much SOL the taker must provide
+    /// The taker must deposit exactly this amount to complete the swap
+    pub amount_b_expected: u64,
+
+    /// Whether the taker has deposited their SOL
+    /// True when taker has funded, false when waiting for taker
+    pub is_funded: bool,
+
+    /// Whether this escrow is still active and can be used
+    /// Set to false when completed, cancelled, or refunded
+    pub is_active: bool,
+
+    /// Whether this escrow was successfully completed
+    /// Set to true only when the swap is completed successfully
+    pub is_completed: bool,
+
+    /// When this escrow expires (Unix timestamp)
+    /// After this time, only the maker can refund their SOL
+    /// This protects both parties from funds being stuck forever
+    pub expiry_ts: i64,
+
+    /// Bump seed for the PDA derivation
+    /// Used to recreate the escrow account address when needed
+    pub bump: u8,
+
+    /// Lamports the maker set aside to reward a keeper for cranking
+    /// Returned to the maker on every path except `crank_expired`
+    pub keeper_tip: u64,
+
+    /// Which of the taker's `EscrowIndex` pages lists this escrow
+    /// Lets terminal instructions find the page to remove it from
+    pub index_page: u32,
+
+    /// The NFT held in the vault when the maker offers an NFT instead of SOL
+    /// None for SOL escrows; when set, `amount_a` is 1 and no SOL is locked for it
+    pub nft_mint: Option<Pubkey>,
+
+    /// Price feed terms when the taker's payment is set by an oracle at funding time
+    /// None for fixed-price escrows; `amount_b_expected` stays 0 until funded
+    pub oracle: Option<OracleTerms>,
+
+    /// Who must attest before a funded escrow can settle
+    /// None means the taker completes as usual; Some means only `attest_release`/`attest_refund`
+    pub attester: Option<Pubkey>,
+
+    /// Bumped every time the price or expiry is amended
+    /// Multisig proposals record it so approvals for old terms can't be reused
+    pub terms_version: u64,
+
+    /// Key the maker delegated management of this escrow to, if any
+    /// Acts only within `operator_permissions`; funds still go to the maker
+    pub operator: Option<Pubkey>,
+
+    /// Which `OPERATOR_CAN_*` flags the operator holds
+    pub operator_permissions: u8,
+
+    /// Whose `EscrowIndex` page lists this escrow
+    /// The taker, or for allowlisted escrows the listing key the maker chose
+    pub index_taker: Pubkey,
+
+    /// Merkle root of the wallets allowed to fund, replacing the single `taker` check
+    /// None for escrows addressed to one taker
+    pub taker_allowlist: Option<[u8; 32]>,
+
+    /// Share of the taker's payment the maker pays to the referrer, in basis points
+    /// Zero means no referral fee; capped by `MAX_REFERRAL_FEE_BPS`
+    pub referral_fee_bps: u16,
+
+    /// Frontend recorded at funding to receive the referral fee
+    pub referrer: Option<Pubkey>,
+
+    /// The maker the escrow PDA was derived from
+    /// Equal to `maker` unless maker rights were transferred; signs for the NFT vault
+    pub creator: Pubkey,
+
+    /// Whether position transfers need the other side's signature
+    pub require_transfer_consent: bool,
+
+    /// Receipt mint for the maker's payout, if tokenized with `mint_receipt`
+    pub maker_receipt: Option<Pubkey>,
+
+    /// Receipt mint for the taker's payout, if tokenized with `mint_receipt`
+    pub taker_receipt: Option<Pubkey>,
+
+    /// Lamports held for the maker receipt's holder until `redeem_receipt`
+    pub maker_claim: u64,
+
+    /// Lamports held for the taker receipt's holder until `redeem_receipt`
+    pub taker_claim: u64,
+
+    /// How the price falls over time when the escrow is a Dutch auction
+    /// None for every other escrow; `amount_b_expected` holds the start price until funded
+    pub dutch_auction: Option<DutchAuctionTerms>,
+
+    /// How bids are taken when the escrow auctions `amount_a`
+    /// None for every other escrow; `taker` then stays unset until settlement
+    pub auction: Option<AuctionTerms>,
+
+    /// Leading bid so far, in lamports (0 until an auction has one)
+    pub highest_bid: u64,
+
+    /// Who placed the leading bid
+    pub highest_bidder: Option<Pubkey>,
+
+    /// How many `Bid` PDAs are still open against this escrow
+    pub bid_count: u32,
+
+    /// Maker's reference to an off-chain record (invoice, order ID), if any
+    pub metadata: Option<EscrowMetadata>,
+
+    /// Who paid the escrow PDA's rent (and an NFT vault's); it gets the rent back on close
+    /// The maker unless a relayer sponsored the creation
+    pub rent_payer: Pubkey,
+
+    /// Bond the taker must post at funding, and how long they then have to settle
+    pub taker_bond: Option<TakerBond>,
+
+    /// When the bond becomes slashable (Unix timestamp); 0 until a bonded escrow is funded
+    pub settle_by_ts: i64,
+
+    /// Share of `amount_a` (in bps) the maker pays the taker to cancel once funded
+    /// 0 means the maker can't cancel a funded escrow at all
+    pub cancel_penalty_bps: u16,
+
+    /// Settled swaps the taker's `UserStats` must show before they can fund
+    /// 0 accepts any taker
+    pub min_taker_completed: u32,
+
+    /// Sequence number the escrow's next event will carry
+    /// Starts at the block the maker profile reserved for this escrow
+    pub event_seq: u64,
+}
+
+impl EscrowAccount {
+    
+    pub fn calculate_max_space() -> usize {
+        // Anchor discriminator
+        let mut size = 8;
+        // maker
+        size += 32;
+        // taker (Option<Pubkey>) -> 1 + 32
+        size += 1 + 32;
+        // escrow_id
+        size += 8;
+        // amounts
+        size += 8 + 8;
+        // bools (is_funded, is_active, is_completed)
+        size += 1 + 1 + 1;
+        // expiry
+        size += 8;
+        // bump
+        size += 1;
+        // keeper_tip
+        size += 8;
+        // index_page
+        size += 4;
+        // nft_mint (Option<Pubkey>) -> 1 + 32
+        size += 1 + 32;
+        // oracle (Option<OracleTerms>)
+        size += 1 + OracleTerms::SIZE;
+        // attester (Option<Pubkey>) -> 1 + 32
+        size += 1 + 32;
+        // terms_version
+        size += 8;
+        // operator (Option<Pubkey>) -> 1 + 32
+        size += 1 + 32;
+        // operator_permissions
+        size += 1;
+        // index_taker
+        size += 32;
+        // taker_allowlist (Option<[u8; 32]>) -> 1 + 32
+        size += 1 + 32;
+        // referral_fee_bps
+        size += 2;
+        // referrer (Option<Pubkey>) -> 1 + 32
+        size += 1 + 32;
+        // creator
+        size += 32;
+        // require_transfer_consent
+        size += 1;
+        // maker_receipt, taker_receipt (Option<Pubkey>) -> 2 * (1 + 32)
+        size += 2 * (1 + 32);
+        // maker_claim, taker_claim
+        size += 8 + 8;
+        // dutch_auction (Option<DutchAuctionTerms>)
+        size += 1 + DutchAuctionTerms::SIZE;
+        // auction (Option<AuctionTerms>)
+        size += 1 + AuctionTerms::SIZE;
+        // highest_bid
+        size += 8;
+        // highest_bidder (Option<Pubkey>) -> 1 + 32
+        size += 1 + 32;
+        // bid_count
+        size += 4;
+        // metadata (Option<EscrowMetadata>)
+        size += 1 + EscrowMetadata::MAX_SIZE;
+        // rent_payer
+        size += 32;
+        // taker_bond (Option<TakerBond>)
+        size += 1 + TakerBond::SIZE;
+        // settle_by_ts
+        size += 8;
+        // cancel_penalty_bps
+        size += 2;
+        // min_taker_completed
+        size += 4;
+        // event_seq
+        size += 8;
+        // padding
+        size += 128;
+        size
+    }
+
+    /// Lamports held for the trade (excluding rent) in the escrow's current state
+    /// Reported in every event so indexers can follow balances without extra reads
+    pub fn locked_lamports(&self) -> u64 {
+        // Receipt claims stay locked after the escrow settles, until redeemed
+        let claims = self.maker_claim + self.taker_claim;
+        if !self.is_active {
+            return claims;
+        }
+        let mut locked = claims + self.keeper_tip;
+        if self.nft_mint.is_none() {
+            locked += self.amount_a;
+        }
+        if self.is_funded {
+            locked += self.amount_b_expected + self.taker_bond.map_or(0, |bond| bond.amount);
+        }
+        locked
+    }
+
+    /// The receipt mint for one side's payout, if it was tokenized
+    pub fn receipt(&self, side: ReceiptSide) -> Option<Pubkey> {
+        match side {
+            ReceiptSide::Maker => self.maker_receipt,
+            ReceiptSide::Taker => self.taker_receipt,
+        }
+    }
+
+    /// The referrer's share of `amount_b_expected`, rounded down
+    pub fn referral_fee(&self) -> u64 {
+        (self.amount_b_expected as u128 * self.referral_fee_bps as u128 / 10_000) as u64
+    }
+
+    /// The taker's compensation out of `amount_a` if the maker cancels once funded, rounded down
+    pub fn cancel_penalty(&self) -> u64 {
+        (self.amount_a as u128 * self.cancel_penalty_bps as u128 / 10_000) as u64
+    }
+
+    /// Checks `authority` is the maker, or the operator holding `permission`
+    pub fn check_authority(&self, authority: &Pubkey, permission: u8) -> Result<()> {
+        if *authority == self.maker {
+            return Ok(());
+        }
+        require!(
+            self.operator == Some(*authority) && self.operator_permissions & permission != 0,
+            EscrowError::Unauthorized
+        );
+        Ok(())
+    }
+
+    /// When bidding, including any reveal phase, is over; `expiry_ts` for non-auctions
+    pub fn bidding_end_ts(&self) -> i64 {
+        match &self.auction {
+            Some(terms) if terms.kind == AuctionKind::SealedBid => {
+                self.expiry_ts.saturating_add(i64::from(terms.reveal_secs))
+            }
+            _ => self.expiry_ts,
+        }
+    }
+
+    /// Changes the price and expiry of an escrow no taker has funded yet
+    /// Oracle-priced escrows keep pricing at funding time, so only their expiry changes;
+    /// for Dutch auctions that moves when the floor is reached
+    pub fn amend(&mut self, amount_b_expected: u64, expiry_ts: i64, now: i64) -> Result<()> {
+        require!(self.is_active, EscrowError::NotActive);
+        require!(!self.is_funded, EscrowError::AlreadyFunded);
+        require!(expiry_ts > now, EscrowError::InvalidExpiry);
+        require!(self.auction.is_none(), EscrowError::AuctionEscrow);
+        if let Some(terms) = &self.dutch_auction {
+            terms.validate(expiry_ts)?;
+        } else if self.oracle.is_none() {
+            require!(amount_b_expected > 0, EscrowError::InvalidAmount);
+            self.amount_b_expected = amount_b_expected;
+        }
+        self.expiry_ts = expiry_ts;
+        self.terms_version += 1;
+        Ok(())
+    }
+
+    /// # Validate Terms
+    ///
+    /// The checks every create instruction runs before an escrow exists; returns
+    /// the `amount_b_expected` to store, which auctions and oracle pricing replace.
+    fn validate_terms(terms: &NewEscrow, now: i64) -> Result<u64> {
+        let options = &terms.options;
+        require!(terms.amount_a > 0, EscrowError::InvalidAmount);
+        require!(
+            options.referral_fee_bps <= MAX_REFERRAL_FEE_BPS,
+            EscrowError::InvalidReferralFee
+        );
+        require!(terms.expiry_ts > now, EscrowError::InvalidExpiry);
+        if let Some(metadata) = &options.metadata {
+            metadata.validate()?;
+        }
+
+        // Bonds hold the taker to settling, so only where the taker settles
+        if let Some(bond) = &options.taker_bond {
+            require!(
+                options.attester.is_none() && options.auction.is_none(),
+                EscrowError::InvalidTakerBond
+            );
+            bond.validate()?;
+        }
+
+        // A penalty lets the maker back out of a funded escrow, which only the taker may settle
+        if options.cancel_penalty_bps > 0 {
+            require!(
+                options.cancel_penalty_bps <= 10_000
+                    && options.attester.is_none()
+                    && options.auction.is_none(),
+                EscrowError::InvalidCancelPenalty
+            );
+        }
+
+        // Auctions are priced by their bids, so they take no other pricing or taker terms
+        if let Some(auction) = &options.auction {
+            require!(
+                options.oracle.is_none()
+                    && options.dutch_auction.is_none()
+                    && options.attester.is_none()
+                    && options.taker_allowlist.is_none()
+                    && options.min_taker_completed == 0,
+                EscrowError::InvalidAuctionTerms
+            );
+            auction.validate()?;
+        }
+
+        // Oracle-priced and Dutch auction escrows learn their payment at funding time
+        match (&options.oracle, &options.dutch_auction) {
+            (Some(_), Some(_)) => err!(EscrowError::InvalidAuctionTerms),
+            (Some(oracle), None) => {
+                require!(
+                    oracle.min_price > 0 && oracle.min_price <= oracle.max_price,
+                    EscrowError::InvalidPriceBounds
+                );
+                Ok(0)
+            }
+            (None, Some(dutch)) => {
+                dutch.validate(terms.expiry_ts)?;
+                Ok(dutch.start_price)
+            }
+            (None, None) => {
+                // Auctions show their reserve price until settled
+                let amount_b_expected = options
+                    .auction
+                    .as_ref()
+                    .map_or(terms.amount_b_expected, |auction| auction.reserve_price);
+                require!(amount_b_expected > 0, EscrowError::InvalidAmount);
+                Ok(amount_b_expected)
+            }
+        }
+    }
+
+    /// # Init
+    ///
+    /// Checks the terms and builds the state of a new, unfunded escrow. Shared by
+    /// `create_escrow`, `create_nft_escrow`, `create_multisig_escrow` and
+    /// `batch_create_escrows` so they can't drift apart.
+    pub fn init(terms: NewEscrow, now: i64) -> Result<Self> {
+        let amount_b_expected = Self::validate_terms(&terms, now)?;
+        let options = terms.options;
+        Ok(Self {
+            maker: terms.maker,
+            // Allowlisted escrows learn their taker when funded, auctions when settled
+            taker: match (&options.taker_allowlist, &options.auction) {
+                (None, None) => Some(terms.taker_pubkey),
+                _ => None,
+            },
+            escrow_id: terms.escrow_id,
+            amount_a: terms.amount_a,
+            amount_b_expected,
+            is_funded: false,
+            is_active: true,
+            is_completed: false,
+            expiry_ts: terms.expiry_ts,
+            bump: terms.bump,
+            keeper_tip: terms.keeper_tip,
+            index_page: terms.index_page,
+            nft_mint: terms.nft_mint,
+            oracle: options.oracle,
+            dutch_auction: options.dutch_auction,
+            auction: options.auction,
+            highest_bid: 0,
+            highest_bidder: None,
+            bid_count: 0,
+            metadata: options.metadata,
+            rent_payer: terms.rent_payer,
+            taker_bond: options.taker_bond,
+            settle_by_ts: 0,
+            cancel_penalty_bps: options.cancel_penalty_bps,
+            min_taker_completed: options.min_taker_completed,
+            attester: options.attester,
+            terms_version: 0,
+            operator: None,
+            operator_permissions: 0,
+            index_taker: terms.taker_pubkey,
+            taker_allowlist: options.taker_allowlist,
+            referral_fee_bps: options.referral_fee_bps,
+            referrer: None,
+            creator: terms.maker,
+            require_transfer_consent: options.require_transfer_consent,
+            maker_receipt: None,
+            taker_receipt: None,
+            maker_claim: 0,
+            taker_claim: 0,
+            event_seq: terms.event_seq,
+        })
+    }
+
+    /// Hands out the sequence number for the escrow's next event
+    pub fn next_event_seq(&mut self) -> Result<u64> {
+        let seq = self.event_seq;
+        self.event_seq = seq.checked_add(1).ok_or(EscrowError::EventSeqOverflow)?;
+        Ok(seq)
+    }
+
+    /// The `EscrowCreated` event for an escrow `init` just built, numbered as its first event
+    pub fn created_event(&mut self, escrow: Pubkey, ts: i64) -> Result<EscrowCreated> {
+        Ok(EscrowCreated {
+            seq: self.next_event_seq()?,
+            escrow,
+            maker: self.maker,
+            taker: self.index_taker,
+            escrow_id: self.escrow_id,
+            amount_a: self.amount_a,
+            amount_b_expected: self.amount_b_expected,
+            expiry_ts: self.expiry_ts,
+            keeper_tip: self.keeper_tip,
+            nft_mint: self.nft_mint,
+            oracle: self.oracle,
+            dutch_auction: self.dutch_auction,
+            auction: self.auction,
+            attester: self.attester,
+            taker_allowlist: self.taker_allowlist,
+            metadata: self.metadata.clone(),
+            rent_payer: self.rent_payer,
+            taker_bond: self.taker_bond,
+            cancel_penalty_bps: self.cancel_penalty_bps,
+            min_taker_completed: self.min_taker_completed,
+            locked_lamports: self.locked_lamports(),
+            ts,
+        })
+    }
+}
+
+
+/// # Oracle Terms
+///
+/// How an oracle-priced escrow turns a Pyth price into the taker's payment.
+///
+/// ## The Payment
+///
+/// The feed quotes lamports of payment per unit of `amount_a`, so
+/// `payment = amount_a * price * 10^expo`, rounded down.
+///
+/// ## Checks at Funding Time
+///
+/// - **Staleness**: The price was published at most `max_price_age` seconds ago
+/// - **Confidence**: `conf` is at most `max_confidence_bps` of the price
+/// - **Slippage**: `min_price <= price <= max_price`, in the feed's own units
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OracleTerms {
+    /// The Pyth price account to read
+    pub price_feed: Pubkey,
+
+    /// Lowest acceptable price (protects the maker)
+    pub min_price: i64,
+
+    /// Highest acceptable price
+    pub max_price: i64,
+
+    /// Oldest acceptable price, in seconds
+    pub max_price_age: u32,
+
+    /// Widest acceptable confidence interval, in basis points of the price
+    pub max_confidence_bps: u16,
+}
+
+impl OracleTerms {
+    /// Serialized size: price_feed + min_price + max_price + max_price_age + max_confidence_bps
+    pub const SIZE: usize = 32 + 8 + 8 + 4 + 2;
+
+    /// Checks `price` against these terms and computes the taker's payment for `amount_a`
+    pub fn payment_for(&self, amount_a: u64, price: &OraclePrice, now: i64) -> Result<u64> {
+        require!(
+            now.saturating_sub(price.publish_time) <= i64::from(self.max_price_age),
+            EscrowError::StalePrice
+        );
+        require!(
+            price.price >= self.min_price && price.price <= self.max_price,
+            EscrowError::PriceOutOfBounds
+        );
+
+        // min_price > 0, so the price is positive here
+        let price_value = price.price as u128;
+        require!(
+            u128::from(price.conf) * 10_000 <= price_value * u128::from(self.max_confidence_bps),
+            EscrowError::PriceConfidenceTooWide
+        );
+
+        let scale = 10u128
+            .checked_pow(price.expo.unsigned_abs())
+            .ok_or(EscrowError::InvalidOracle)?;
+        let gross = u128::from(amount_a)
+            .checked_mul(price_value)
+            .ok_or(EscrowError::InvalidAmount)?;
+        let payment = if price.expo < 0 {
+            gross / scale
+        } else {
+            gross.checked_mul(scale).ok_or(EscrowError::InvalidAmount)?
+        };
+
+        let payment = u64::try_from(payment).map_err(|_| EscrowError::InvalidAmount)?;
+        require!(payment > 0, EscrowError::InvalidAmount);
+        Ok(payment)
+    }
+}
+
+/// # Dutch Auction Terms
+///
+/// How a Dutch auction escrow's price falls from `start_price` at `start_ts` to
+/// `floor_price` at the escrow's `expiry_ts`.
+///
+/// ## The Price
+///
+/// - **Before `start_ts`**: `start_price`
+/// - **Linear** (`step_secs` = 0): falls continuously, rounded up to the lamport
+/// - **Stepwise** (`step_secs` > 0): only drops at each whole `step_secs` after `start_ts`,
+///   to where the linear price would be at that moment
+/// - **At or after `expiry_ts`**: `floor_price` (the escrow can no longer be funded by then)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DutchAuctionTerms {
+    /// Price in lamports when the auction starts
+    pub start_price: u64,
+
+    /// Lowest price, reached at `expiry_ts`
+    pub floor_price: u64,
+
+    /// When the price starts falling (Unix timestamp)
+    pub start_ts: i64,
+
+    /// Seconds between price drops; 0 for a continuous decline
+    pub step_secs: u32,
+}
+
+impl DutchAuctionTerms {
+    /// Serialized size: start_price + floor_price + start_ts + step_secs
+    pub const SIZE: usize = 8 + 8 + 8 + 4;
+
+    /// Checks the price falls from a positive start to a positive floor before `expiry_ts`
+    pub fn validate(&self, expiry_ts: i64) -> Result<()> {
+        require!(
+            self.floor_price > 0 && self.start_price > self.floor_price,
+            EscrowError::InvalidAuctionTerms
+        );
+        require!(self.start_ts < expiry_ts, EscrowError::InvalidAuctionTerms);
+        Ok(())
+    }
+
+    /// The price at `now` for an auction ending at `expiry_ts`
+    pub fn price_at(&self, now: i64, expiry_ts: i64) -> u64 {
+        if now <= self.start_ts {
+            return self.start_price;
+        }
+        if now >= expiry_ts {
+            return self.floor_price;
+        }
+
+        let mut elapsed = (now - self.start_ts) as u128;
+        if self.step_secs > 0 {
+            elapsed -= elapsed % u128::from(self.step_secs);
+        }
+        let duration = (expiry_ts - self.start_ts) as u128;
+        let drop = u128::from(self.start_price - self.floor_price) * elapsed / duration;
+        // elapsed < duration, so drop < start_price - floor_price
+        self.start_price - drop as u64
+    }
+}
+
+/// # Taker Bond
+///
+/// A performance bond the maker requires from whoever funds the escrow.
+///
+/// ## Lifecycle
+///
+/// - **Funding**: The taker deposits `amount` on top of the payment, and has until
+///   `settle_by_ts` (funding time plus `settle_secs`) to complete the swap
+/// - **Completed**: The bond goes back to the taker with `amount_a`
+/// - **Deadline missed**: The maker can `slash_taker_bond` to unwind the trade and keep the bond
+///
+/// Only for escrows the taker settles themselves, so not with an attester or an auction.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TakerBond {
+    /// Lamports the taker deposits at funding
+    pub amount: u64,
+
+    /// Seconds after funding the taker has to complete the swap
+    pub settle_secs: u32,
+}
+
+impl TakerBond {
+    /// Serialized size: amount + settle_secs
+    pub const SIZE: usize = 8 + 4;
+
+    /// Checks the bond and the settlement window are both non-zero
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.amount > 0 && self.settle_secs > 0,
+            EscrowError::InvalidTakerBond
+        );
+        Ok(())
+    }
+}
+
+/// # Escrow Metadata
+///
+/// Ties an escrow to a record kept elsewhere, e.g. an ERP invoice or order.
+///
+/// ## The Memo
+///
+/// Settlement instructions given the SPL Memo program write
+/// `escrow <escrow> ref <content_hash as hex>`, followed by ` <uri>` when set.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct EscrowMetadata {
+    /// Hash of the off-chain record, in whatever scheme the maker uses (e.g. sha256)
+    pub content_hash: [u8; 32],
+
+    /// Where to find the record, at most `MAX_METADATA_URI_LEN` bytes
+    pub uri: Option<String>,
+}
+
+impl EscrowMetadata {
+    /// Largest serialized size: content_hash + uri (Option<String>) at its longest
+    pub const MAX_SIZE: usize = 32 + 1 + 4 + MAX_METADATA_URI_LEN as usize;
+
+    /// Checks the URI fits
+    pub fn validate(&self) -> Result<()> {
+        let uri_len = self.uri.as_ref().map_or(0, String::len);
+        require!(
+            uri_len <= MAX_METADATA_URI_LEN as usize,
+            EscrowError::InvalidMetadata
+        );
+        Ok(())
+    }
+
+    /// The memo text echoed at settlement
+    pub fn memo(&self, escrow: &Pubkey) -> String {
+        let content_hash: String = self.content_hash.iter().map(|byte| format!("{:02x}", byte)).collect();
+        match &self.uri {
+            Some(uri) => format!("escrow {} ref {} {}", escrow, content_hash, uri),
+            None => format!("escrow {} ref {}", escrow, content_hash),
+        }
+    }
+}
+
+/// # Auction Terms
+///
+/// How an auction escrow sells `amount_a` to the highest bidder.
+///
+/// ## Kinds
+///
+/// - **English**: Open bids with `place_bid` until `expiry_ts`; each must beat the leader by
+///   `min_increment`, and the outbid leader is refunded on the spot
+/// - **Sealed-bid**: Hashed bids with `commit_bid` until `expiry_ts`, opened with `reveal_bid`
+///   during the next `reveal_secs`; the highest revealed bid wins
+///
+/// Either way no bid below `reserve_price` can win.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AuctionTerms {
+    pub kind: AuctionKind,
+
+    /// Lowest bid that can win, in lamports
+    pub reserve_price: u64,
+
+    /// How much each English bid must beat the leading one by (ignored for sealed bids)
+    pub min_increment: u64,
+
+    /// Length of the reveal phase after `expiry_ts` (ignored for English auctions)
+    pub reveal_secs: u32,
+}
+
+impl AuctionTerms {
+    /// Serialized size: kind + reserve_price + min_increment + reveal_secs
+    pub const SIZE: usize = 1 + 8 + 8 + 4;
+
+    /// Checks the reserve is positive and the kind's own parameter is set
+    pub fn validate(&self) -> Result<()> {
+        require!(self.reserve_price > 0, EscrowError::InvalidAuctionTerms);
+        match self.kind {
+            AuctionKind::English => {
+                require!(self.min_increment > 0, EscrowError::InvalidAuctionTerms)
+            }
+            AuctionKind::SealedBid => {
+                require!(self.reveal_secs > 0, EscrowError::InvalidAuctionTerms)
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuctionKind {
+    English,
+    SealedBid,
+}
+
+/// # Oracle Price
+///
+/// The aggregate price read from a Pyth price account, as recorded in `EscrowFunded`.
+/// The real value is `price * 10^expo`, give or take `conf * 10^expo`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OraclePrice {
+    pub price: i64,
+    pub conf: u64,
+    pub expo: i32,
+    pub publish_time: i64,
+}
+
+impl OraclePrice {
+    /// Reads the aggregate price from a Pyth (v2) price account
+    ///
+    /// Only the fixed header is read, so test feeds may omit the publisher components.
+    pub fn from_pyth_account(feed: &AccountInfo) -> Result<Self> {
+        // Offsets into Pyth's `PriceAccount` (repr(C), little endian)
+        const MAGIC: u32 = 0xa1b2_c3d4;
+        const VERSION: u32 = 2;
+        const ACCOUNT_TYPE_PRICE: u32 = 3;
+        const STATUS_TRADING: u32 = 1;
+        const EXPO_OFFSET: usize = 20;
+        const TIMESTAMP_OFFSET: usize = 96;
+        const AGG_PRICE_OFFSET: usize = 208;
+        const AGG_CONF_OFFSET: usize = 216;
+        const AGG_STATUS_OFFSET: usize = 224;
+        const HEADER_LEN: usize = 240;
+
+        require!(PYTH_PROGRAM_IDS.contains(feed.owner), EscrowError::InvalidOracle);
+        let data = feed.try_borrow_data()?;
+        require!(data.len() >= HEADER_LEN, EscrowError::InvalidOracle);
+
+        let read_u32 = |offset: usize| {
+            let mut bytes = [0u8; 4];
+            bytes.copy_from_slice(&data[offset..offset + 4]);
+            u32::from_le_bytes(bytes)
+        };
+        let read_u64 = |offset: usize| {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&data[offset..offset + 8]);
+            u64::from_le_bytes(bytes)
+        };
+
+        require!(
+            read_u32(0) == MAGIC && read_u32(4) == VERSION && read_u32(8) == ACCOUNT_TYPE_PRICE,
+            EscrowError::InvalidOracle
+        );
+        require!(read_u32(AGG_STATUS_OFFSET) == STATUS_TRADING, EscrowError::StalePrice);
+
+        Ok(Self {
+            price: read_u64(AGG_PRICE_OFFSET) as i64,
+            conf: read_u64(AGG_CONF_OFFSET),
+            expo: read_u32(EXPO_OFFSET) as i32,
+            publish_time: read_u64(TIMESTAMP_OFFSET) as i64,
+        })
+    }
+}
+
+
+/// # Config Structure
+///
+/// Program-wide singleton at `["config"]` holding the emergency pause switch.
+///
+/// ## The Pause
+///
+/// While `paused` is set, instructions that take new deposits fail with `ProgramPaused`.
+/// Nothing that returns or settles funds checks it, so a pause can never trap anyone's SOL.
+#[account]
+pub struct Config {
+    /// Who can pause and unpause (the upgrade authority at initialization)
+    pub admin: Pubkey,
+
+    /// Whether new deposits are refused
+    pub paused: bool,
+
+    /// Bump seed for the PDA derivation
+    pub bump: u8,
+
+    /// Sequence number the next `Paused` or `Unpaused` event will carry
+    pub event_seq: u64,
+}
+
+impl Config {
+    pub fn calculate_max_space() -> usize {
+        // Anchor discriminator
+        let mut size = 8;
+        // admin
+        size += 32;
+        // paused
+        size += 1;
+        // bump
+        size += 1;
+        // event_seq
+        size += 8;
+        // padding
+        size += 64;
+        size
+    }
+
+    /// Hands out the sequence number for the next pause or unpause event
+    pub fn next_event_seq(&mut self) -> Result<u64> {
+        let seq = self.event_seq;
+        self.event_seq = seq.checked_add(1).ok_or(EscrowError::EventSeqOverflow)?;
+        Ok(seq)
+    }
+}
+
+
+/// # Maker Profile Structure
+///
+/// One per maker. Hands out escrow IDs so clients don't have to invent unique ones.
+///
+/// ## Deriving the Next Escrow Address
+///
+/// Read `next_escrow_id`, then derive `["escrow", maker, next_escrow_id]`. That is the
+/// address `create_escrow` will initialize if called with `escrow_id = None`.
+#[account]
+pub struct MakerProfile {
+    /// The maker this profile belongs to
+    pub maker: Pubkey,
+
+    /// The ID the next automatically numbered escrow will use
+    /// Only ever increases, so automatic IDs are never reused
+    pub next_escrow_id: u64,
+
+    /// Bump seed for the PDA derivation
+    pub bump: u8,
+
+    /// How many escrows this maker has created, which picks each one's event sequence block
+    pub escrows_created: u64,
+}
+
+impl MakerProfile {
+    pub fn calculate_max_space() -> usize {
+        // Anchor discriminator
+        let mut size = 8;
+        // maker
+        size += 32;
+        // next_escrow_id
+        size += 8;
+        // bump
+        size += 1;
+        // escrows_created
+        size += 8;
+        // padding
+        size += 64;
+        size
+    }
+
+    /// Picks the ID for a new escrow and advances the counter past it
+    ///
+    /// Uses `requested` if given, otherwise `next_escrow_id`.
+    pub fn assign_escrow_id(&mut self, requested: Option<u64>) -> Result<u64> {
+        let escrow_id = requested.unwrap_or(self.next_escrow_id);
+        if escrow_id >= self.next_escrow_id {
+            self.next_escrow_id = escrow_id
+                .checked_add(1)
+                .ok_or(EscrowError::EscrowIdOverflow)?;
+        }
+        Ok(escrow_id)
+    }
+
+    /// Reserves the next `EVENT_SEQ_BLOCK` sequence numbers for a new escrow, returning the first
+    ///
+    /// Blocks only move up, so an escrow re-created at a closed one's address numbers its
+    /// events above everything the old escrow emitted.
+    pub fn reserve_event_seqs(&mut self) -> Result<u64> {
+        let block = self.escrows_created;
+        self.escrows_created = block.checked_add(1).ok_or(EscrowError::EventSeqOverflow)?;
+        block
+            .checked_mul(EVENT_SEQ_BLOCK)
+            .ok_or_else(|| error!(EscrowError::EventSeqOverflow))
+    }
+}
+
+/// # User Stats Structure
+///
+/// One per wallet: its track record across escrows, kept by the program so a counterparty's
+/// history is a single account read away.
+///
+/// ## What Updates It
+///
+/// - **completed / volume**: Both sides of `complete_swap`, `complete_nft_swap`,
+///   `attest_release` and `settle_auction`; `volume` counts the lamports the wallet paid in,
+///   except that an NFT seller, who paid in none, is credited the lamports it was paid
+/// - **cancelled**: The maker, on `cancel_escrow`, `batch_cancel_escrows`, a multisig's
+///   `Cancel` proposal, and `cancel_nft_escrow` before expiry
+/// - **expired**: The maker, on `refund_after_expiry`, `crank_expired`, a multisig's `Refund`
+///   proposal, and `cancel_nft_escrow` after expiry
+/// - **disputes_lost**: The maker when the attester refunds (`attest_refund`), the taker
+///   when their bond is slashed (`slash_taker_bond`)
+///
+/// The instructions above create the record on first use (`init_if_needed`, paid by their
+/// signer) unless the wallet opened it with `open_user_stats`. Either way no outcome goes
+/// unrecorded, and closing a record isn't possible, so a wallet can't shed the cancels and
+/// disputes on it.
+///
+/// ## Gaming the Count
+///
+/// `completed` counts swaps, not their size: two wallets can push it up with 1-lamport swaps
+/// between themselves for a few transaction fees each. Makers gating on `min_taker_completed`
+/// should read it alongside `volume` (and who the counterparties were) before trusting a
+/// high count.
+#[account]
+pub struct UserStats {
+    /// The wallet this record belongs to
+    pub wallet: Pubkey,
+
+    /// Swaps settled with this wallet on either side
+    pub completed: u32,
+
+    /// Lamports this wallet paid into settled swaps, or was paid for an NFT it sold
+    pub volume: u64,
+
+    /// Escrows this wallet cancelled as maker
+    pub cancelled: u32,
+
+    /// Escrows this wallet made that ran out without a taker
+    pub expired: u32,
+
+    /// Attester refunds against this wallet as maker, plus bonds slashed from it as taker
+    pub disputes_lost: u32,
+
+    /// Bump seed for the PDA derivation
+    pub bump: u8,
+}
+
+impl UserStats {
+    pub fn calculate_max_space() -> usize {
+        // Anchor discriminator
+        let mut size = 8;
+        // wallet
+        size += 32;
+        // completed
+        size += 4;
+        // volume
+        size += 8;
+        // cancelled
+        size += 4;
+        // expired
+        size += 4;
+        // disputes_lost
+        size += 4;
+        // bump
+        size += 1;
+        // padding
+        size += 64;
+        size
+    }
+
+    /// Fills in the wallet and bump, which a record just created by `init_if_needed` lacks
+    pub fn for_wallet(&mut self, wallet: Pubkey, bump: u8) -> &mut Self {
+        self.wallet = wallet;
+        self.bump = bump;
+        self
+    }
+
+    pub fn record_completed(&mut self, volume: u64) {
+        self.completed = self.completed.saturating_add(1);
+        self.volume = self.volume.saturating_add(volume);
+    }
+
+    pub fn record_cancelled(&mut self) {
+        self.cancelled = self.cancelled.saturating_add(1);
+    }
+
+    pub fn record_expired(&mut self) {
+        self.expired = self.expired.saturating_add(1);
+    }
+
+    pub fn record_dispute_lost(&mut self) {
+        self.disputes_lost = self.disputes_lost.saturating_add(1);
+    }
+}
+
+
+/// # Escrow Index Structure
+///
+/// A page of open escrows addressed to one taker, so takers can find their offers
+/// by reading a handful of accounts instead of scanning every `EscrowAccount`.
+///
+/// ## How Pages Work
+///
+/// - Pages live at `["escrow_index", taker, page]` and are numbered from 0
+/// - Every create instruction (`create_escrow`, `create_nft_escrow`, `create_multisig_escrow`,
+///   `batch_create_escrows`) appends to the page the client picks, creating it if needed
+/// - Every terminal instruction removes the entry: settling (`complete_swap`,
+///   `complete_nft_swap`, `attest_release`, `settle_auction`), cancelling (`cancel_escrow`,
+///   `cancel_nft_escrow`, `batch_cancel_escrows`, `attest_refund`, `slash_taker_bond`, a
+///   multisig's `execute_proposal`) and expiring (`refund_after_expiry`, `crank_expired`)
+/// - `transfer_taker_rights` moves the entry to the new taker's page
+/// - To enumerate, read pages 0, 1, 2, ... until one doesn't exist
+///
+/// The escrow records its page as `index_taker` and `index_page`, and every instruction that
+/// touches the page checks it against those, so a page can't be swapped for another.
+///
+/// The taker is the key because it's the one party every escrow names up front: the asset
+/// the maker offers may be SOL or an NFT, but the taker pays SOL either way. Allowlisted
+/// escrows and auctions, which learn their taker later, are listed under the `taker_pubkey`
+/// given at creation.
+#[account]
+pub struct EscrowIndex {
+    /// The taker every listed escrow is addressed to
+    pub taker: Pubkey,
+
+    /// This page's number
+    pub page: u32,
+
+    /// Escrows that have not reached a terminal state yet
+    /// Holds at most `MAX_ESCROWS_PER_INDEX_PAGE` entries; order is not preserved
+    pub escrows: Vec<Pubkey>,
+
+    /// Bump seed for the PDA derivation
+    pub bump: u8,
+}
+
+impl EscrowIndex {
+    pub fn calculate_max_space() -> usize {
+        // Anchor discriminator
+        let mut size = 8;
+        // taker
+        size += 32;
+        // page
+        size += 4;
+        // escrows (Vec<Pubkey>) -> 4 + 32 * max entries
+        size += 4 + 32 * MAX_ESCROWS_PER_INDEX_PAGE as usize;
+        // bump
+        size += 1;
+        size
+    }
+
+    /// Lists an escrow on this page
+    pub fn push(&mut self, escrow: Pubkey) -> Result<()> {
+        require!(
+            self.escrows.len() < MAX_ESCROWS_PER_INDEX_PAGE as usize,
+            EscrowError::IndexPageFull
+        );
+        self.escrows.push(escrow);
+        Ok(())
+    }
+
+    /// Unlists an escrow from this page
+    pub fn remove(&mut self, escrow: &Pubkey) -> Result<()> {
+        let position = self
+            .escrows
+            .iter()
+            .position(|listed| listed == escrow)
+            .ok_or(EscrowError::NotInIndex)?;
+        self.escrows.swap_remove(position);
+        Ok(())
+    }
+}
+
+
+/// Which side of an escrow a receipt pays out
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReceiptSide {
+    /// The maker's payment, or their offer back on cancel and refund
+    Maker,
+    /// The taker's `amount_a`, or their payment back on `attest_refund`
+    Taker,
+}
+
+
+/// One escrow's terms in `batch_create_escrows`: `create_escrow`'s arguments, with only
+/// `referral_fee_bps` of its `EscrowOptions`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BatchEscrowTerms {
+    /// None to use the maker profile's next ID
+    pub escrow_id: Option<u64>,
+    pub amount_a: u64,
+    pub amount_b_expected: u64,
+    pub expiry_ts: i64,
+    pub taker_pubkey: Pubkey,
+    pub keeper_tip: u64,
+    pub index_page: u32,
+    pub referral_fee_bps: u16,
+}
+
+
+/// # Escrow Options
+///
+/// The optional terms of `create_escrow`, passed as one argument; the default (every
+/// field unset or zero) is a plain fixed-price swap with the named taker. The other
+/// create instructions fill in the subset they support. Each field is described under
+/// its heading in `create_escrow`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct EscrowOptions {
+    /// Price the payment from a Pyth feed at funding time
+    pub oracle: Option<OracleTerms>,
+    /// Let the price fall over time
+    pub dutch_auction: Option<DutchAuctionTerms>,
+    /// Sell to the highest bidder instead of the named taker
+    pub auction: Option<AuctionTerms>,
+    /// Only this key's attestation can settle the escrow once funded
+    pub attester: Option<Pubkey>,
+    /// Merkle root of the wallets allowed to fund
+    pub taker_allowlist: Option<[u8; 32]>,
+    /// Share of the payment (in bps) for the referring frontend
+    pub referral_fee_bps: u16,
+    /// Make position transfers need the other side's signature
+    pub require_transfer_consent: bool,
+    /// Reference to an off-chain record
+    pub metadata: Option<EscrowMetadata>,
+    /// Bond the taker posts at funding and forfeits by not settling in time
+    pub taker_bond: Option<TakerBond>,
+    /// Share of `amount_a` (in bps) the maker forfeits by cancelling once funded
+    pub cancel_penalty_bps: u16,
+    /// Settled swaps a taker needs before they can fund
+    pub min_taker_completed: u32,
+}
+
+/// # New Escrow
+///
+/// Everything `EscrowAccount::init` needs to build a fresh escrow, whichever
+/// instruction is creating it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NewEscrow {
+    /// The wallet (or multisig) the escrow belongs to
+    pub maker: Pubkey,
+    /// The ID the maker profile assigned
+    pub escrow_id: u64,
+    pub bump: u8,
+    pub rent_payer: Pubkey,
+    /// 1 for NFT escrows
+    pub amount_a: u64,
+    /// Ignored for oracle-priced escrows, replaced by the start or reserve price for auctions
+    pub amount_b_expected: u64,
+    pub expiry_ts: i64,
+    pub taker_pubkey: Pubkey,
+    pub keeper_tip: u64,
+    pub index_page: u32,
+    pub nft_mint: Option<Pubkey>,
+    pub options: EscrowOptions,
+    /// From `MakerProfile::reserve_event_seqs`
+    pub event_seq: u64,
+}
+
+
+/// # Subscription Structure
+///
+/// Pre-funded recurring payments: the PDA holds `periods - periods_collected` periods'
+/// worth of lamports, released to the payee one period at a time as they elapse.
+#[account]
+pub struct Subscription {
+    /// Who funded the periods and gets the unused ones back on cancel
+    pub payer: Pubkey,
+
+    /// Who may collect each period once it has elapsed
+    pub payee: Pubkey,
+
+    /// Payer-chosen ID, part of the PDA seeds
+    pub subscription_id: u64,
+
+    /// Lamports released per period
+    pub amount_per_period: u64,
+
+    /// Length of one period in seconds
+    pub period_secs: i64,
+
+    /// When the first period started (Unix timestamp)
+    pub start_ts: i64,
+
+    /// How many periods were pre-funded
+    pub periods: u32,
+
+    /// How many periods the payee has collected
+    pub periods_collected: u32,
+
+    /// Bump seed for the PDA derivation
+    pub bump: u8,
+
+    /// Sequence number the subscription's next event will carry, from 0
+    pub event_seq: u64,
+}
+
+impl Subscription {
+    pub fn calculate_max_space() -> usize {
+        // Anchor discriminator
+        let mut size = 8;
+        // payer, payee
+        size += 32 + 32;
+        // subscription_id
+        size += 8;
+        // amount_per_period
+        size += 8;
+        // period_secs, start_ts
+        size += 8 + 8;
+        // periods, periods_collected
+        size += 4 + 4;
+        // bump
+        size += 1;
+        // event_seq
+        size += 8;
+        // padding
+        size += 64;
+        size
+    }
+
+    /// Hands out the sequence number for the subscription's next event
+    pub fn next_event_seq(&mut self) -> Result<u64> {
+        let seq = self.event_seq;
+        self.event_seq = seq.checked_add(1).ok_or(EscrowError::EventSeqOverflow)?;
+        Ok(seq)
+    }
+
+    /// How many periods have fully elapsed at `now`, capped at `periods`
+    pub fn periods_elapsed(&self, now: i64) -> u32 {
+        let elapsed = (now - self.start_ts).max(0) / self.period_secs;
+        elapsed.min(self.periods as i64) as u32
+    }
+
+    /// Lamports still held for uncollected periods (excluding rent)
+    pub fn locked_lamports(&self) -> u64 {
+        self.amount_per_period * (self.periods - self.periods_collected) as u64
+    }
+}
+
+
+/// # Bid Structure
+///
+/// One bidder's bid on an auction escrow, holding its SOL until it wins or is withdrawn.
+/// PDA seeds are `["bid", escrow, bidder]`, so each bidder has one bid per auction.
+#[account]
+pub struct Bid {
+    /// The auction escrow bid on
+    pub escrow: Pubkey,
+
+    /// Who placed the bid and gets it back if it loses
+    pub bidder: Pubkey,
+
+    /// The bid in lamports; 0 for sealed bids until revealed
+    pub amount: u64,
+
+    /// Lamports locked in this PDA on top of its rent
+    /// Equals `amount` for English bids; at least the bid for sealed ones
+    pub deposit: u64,
+
+    /// Hash of the sealed bid, checked by `reveal_bid`; None for English bids
+    pub commitment: Option<[u8; 32]>,
+
+    /// Whether `amount` is known (always for English bids)
+    pub revealed: bool,
+
+    /// Bump seed for the PDA derivation
+    pub bump: u8,
+}
+
+impl Bid {
+    pub fn calculate_max_space() -> usize {
+        // Anchor discriminator
+        let mut size = 8;
+        // escrow, bidder
+        size += 32 + 32;
+        // amount, deposit
+        size += 8 + 8;
+        // commitment (Option<[u8; 32]>)
+        size += 1 + 32;
+        // revealed
+        size += 1;
+        // bump
+        size += 1;
+        // padding
+        size += 32;
+        size
+    }
+}
+
+/// # Multisig Structure
 ///
-/// ## Security Importance
+/// An M-of-N set of owners that can be an escrow's maker. Its address is what
+/// `escrow.maker` holds, and its lamports above rent are the treasury that
+/// receives every maker payout.
 ///
-/// This account is **immutable once created** (except for status updates).
-/// All critical information is stored here and cannot be changed after creation.
+/// ## What Needs Approval
+///
+/// Anything a single maker would sign for: cancelling, refunding and amending its
+/// escrows, plus withdrawing from the treasury. Each goes through a `Proposal` that
+/// needs `threshold` owner approvals before any owner can execute it.
 #[account]
-pub struct EscrowAccount {
-    /// The person who created this escrow (Party A)
-    /// This person deposits SOL and sets the terms
-    /// CHECK: The maker is validated as signer in the CreateEscrow instruction.
-    pub maker: Pubkey,
+pub struct Multisig {
+    /// Throwaway key the PDA is derived from
+    pub create_key: Pubkey,
 
-    /// The person who can take this escrow offer (Party B)
-    /// If None, anyone can take it. If Some(key), only that specific person can
-    /// CHECK: The taker is set from the validated taker account in FundEscrow.
-    pub taker: Option<Pubkey>,
+    /// Keys that can propose, approve and execute
+    /// Holds at most `MAX_MULTISIG_OWNERS` distinct entries and never changes
+    pub owners: Vec<Pubkey>,
 
-    /// Unique identifier for this escrow (like a transaction ID)
-    /// Generated from maker's public key + timestamp for uniqueness
-    pub escrow_id: u64,
+    /// Approvals a proposal needs before it can execute
+    pub threshold: u8,
 
-    /// How much SOL the maker is offering
-    /// This amount is locked in the escrow until the swap completes
-    pub amount_a: u64,
+    /// Index the next proposal will use
+    pub proposal_count: u64,
 
-    /// How much SOL the taker must provide
-    /// The taker must deposit exactly this amount to complete the swap
-    pub amount_b_expected: u64,
+    /// Bump seed for the PDA derivation
+    pub bump: u8,
+}
 
-    /// Whether the taker has deposited their SOL
-    /// True when taker has funded, false when waiting for taker
-    pub is_funded: bool,
+impl Multisig {
+    pub fn calculate_max_space() -> usize {
+        // Anchor discriminator
+        let mut size = 8;
+        // create_key
+        size += 32;
+        // owners (Vec<Pubkey>) -> 4 + 32 * max owners
+        size += 4 + 32 * MAX_MULTISIG_OWNERS as usize;
+        // threshold
+        size += 1;
+        // proposal_count
+        size += 8;
+        // bump
+        size += 1;
+        // padding
+        size += 64;
+        size
+    }
 
-    /// Whether this escrow is still active and can be used
-    /// Set to false when completed, cancelled, or refunded
-    pub is_active: bool,
+    pub fn is_owner(&self, key: &Pubkey) -> bool {
+        self.owners.contains(key)
+    }
+}
 
-    /// Whether this escrow was successfully completed
-    /// Set to true only when the swap is completed successfully
-    pub is_completed: bool,
 
-    /// When this escrow expires (Unix timestamp)
-    /// After this time, only the maker can refund their SOL
-    /// This protects both parties from funds being stuck forever
-    pub expiry_ts: i64,
+/// What a multisig `Proposal` will do once approved
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProposalAction {
+    /// `cancel_escrow` on the proposal's escrow
+    Cancel,
+    /// `refund_after_expiry` on the proposal's escrow
+    Refund,
+    /// `amend_escrow` on the proposal's escrow with these terms
+    Amend { amount_b_expected: u64, expiry_ts: i64 },
+    /// Pay `lamports` from the treasury to `destination`
+    Withdraw { destination: Pubkey, lamports: u64 },
+}
+
+impl ProposalAction {
+    /// Borsh size of the largest variant, tag included
+    pub const SIZE: usize = 1 + 32 + 8;
+}
+
+
+/// # Proposal Structure
+///
+/// One pending multisig action, collecting approvals across transactions.
+///
+/// ## Staleness
+///
+/// Escrow actions record the escrow's `terms_version` when proposed. Once the escrow
+/// is amended the number no longer matches and the proposal can neither be approved
+/// nor executed, so owners never approve a cancel or amendment against terms they
+/// didn't see. Open a new proposal instead.
+#[account]
+pub struct Proposal {
+    /// The multisig this proposal belongs to
+    pub multisig: Pubkey,
+
+    /// Owner who opened it and paid its rent
+    pub proposer: Pubkey,
+
+    /// Position in the multisig's proposal sequence (part of the PDA seeds)
+    pub index: u64,
+
+    /// The escrow the action targets; default for `Withdraw`
+    pub escrow: Pubkey,
+
+    /// What happens on execution
+    pub action: ProposalAction,
+
+    /// The escrow's `terms_version` when proposed
+    pub terms_version: u64,
+
+    /// Owners who approved so far, proposer first
+    pub approvals: Vec<Pubkey>,
 
     /// Bump seed for the PDA derivation
-    /// Used to recreate the escrow account address when needed
     pub bump: u8,
 }
 
-impl EscrowAccount {
-    
+impl Proposal {
     pub fn calculate_max_space() -> usize {
         // Anchor discriminator
         let mut size = 8;
-        // maker
+        // multisig
         size += 32;
-        // taker (Option<Pubkey>) -> 1 + 32
-        size += 1 + 32;
-        // escrow_id
+        // proposer
+        size += 32;
+        // index
         size += 8;
-        // amounts
-        size += 8 + 8;
-        // bools (is_funded, is_active, is_completed)
-        size += 1 + 1 + 1;
-        // expiry
+        // escrow
+        size += 32;
+        // action
+        size += ProposalAction::SIZE;
+        // terms_version
         size += 8;
+        // approvals (Vec<Pubkey>) -> 4 + 32 * max owners
+        size += 4 + 32 * MAX_MULTISIG_OWNERS as usize;
         // bump
         size += 1;
         // padding
-        size += 128;
+        size += 64;
         size
     }
+
+    /// Checks an escrow action is still about the escrow and terms it was proposed for
+    pub fn check_terms(&self, escrow: Option<&Account<EscrowAccount>>) -> Result<()> {
+        if let ProposalAction::Withdraw { .. } = self.action {
+            return Ok(());
+        }
+        let escrow = escrow.ok_or(EscrowError::ProposalMismatch)?;
+        require_keys_eq!(escrow.key(), self.escrow, EscrowError::ProposalMismatch);
+        require!(
+            escrow.terms_version == self.terms_version,
+            EscrowError::StaleProposal
+        );
+        Ok(())
+    }
 }
 
 
+/// Every event starts with `seq`, counted per account: the escrow's, the subscription's
+/// or the config's for pauses. Every event reports `locked_lamports`, the lamports still
+/// held for the trade once the transition has happened.
 #[event]
 pub struct EscrowCreated {
+    pub seq: u64,
     pub escrow: Pubkey,
     pub maker: Pubkey,
+    pub taker: Pubkey,
     pub escrow_id: u64,
     pub amount_a: u64,
     pub amount_b_expected: u64,
     pub expiry_ts: i64,
+    pub keeper_tip: u64,
+    /// Set when the maker offered an NFT; `amount_a` is then 1
+    pub nft_mint: Option<Pubkey>,
+    /// Set when the payment is priced by an oracle at funding time
+    pub oracle: Option<OracleTerms>,
+    /// Set when the price falls over time; `amount_b_expected` is then the start price
+    pub dutch_auction: Option<DutchAuctionTerms>,
+    /// Set when `amount_a` is auctioned; `amount_b_expected` is then the reserve price
+    pub auction: Option<AuctionTerms>,
+    /// Set when only this key's attestation can settle the escrow once funded
+    pub attester: Option<Pubkey>,
+    /// Set when any wallet on this Merkle allowlist may fund; `taker` is then the listing key
+    pub taker_allowlist: Option<[u8; 32]>,
+    /// The maker's reference to an off-chain record
+    pub metadata: Option<EscrowMetadata>,
+    /// Who paid the rent, and gets it back when the escrow closes
+    pub rent_payer: Pubkey,
+    /// Set when the taker must post a bond at funding and settle in time
+    pub taker_bond: Option<TakerBond>,
+    /// Share of `amount_a` (in bps) the maker forfeits to the taker by cancelling once funded
+    pub cancel_penalty_bps: u16,
+    /// Settled swaps a taker needs before they can fund
+    pub min_taker_completed: u32,
+    pub locked_lamports: u64,
     pub ts: i64,
 }
 
 #[event]
 pub struct EscrowFunded {
+    pub seq: u64,
     pub escrow: Pubkey,
     pub taker: Pubkey,
+    pub amount_a: u64,
     pub amount_b: u64,
+    /// The oracle price `amount_b` was computed from, for oracle-priced escrows
+    pub oracle_price: Option<OraclePrice>,
+    /// Bond the taker deposited on top of `amount_b` (0 if none was required)
+    pub taker_bond: u64,
+    /// When the bond can be slashed if the swap hasn't settled, for bonded escrows
+    pub settle_by_ts: Option<i64>,
+    pub locked_lamports: u64,
     pub ts: i64,
 }
 
 #[event]
 pub struct EscrowCompleted {
+    pub seq: u64,
     pub escrow: Pubkey,
     pub maker: Pubkey,
     pub taker: Pubkey,
+    pub amount_a: u64,
+    pub amount_b_expected: u64,
+    pub keeper_tip: u64,
+    /// The attester that released the escrow, if it was attestation-gated
+    pub attester: Option<Pubkey>,
+    /// Who was paid the referral fee, if anyone
+    pub referrer: Option<Pubkey>,
+    /// Lamports of the taker's payment that went to the referrer instead of the maker
+    pub referral_fee: u64,
+    pub locked_lamports: u64,
     pub ts: i64,
 }
 
 #[event]
 pub struct EscrowCancelled {
+    pub seq: u64,
     pub escrow: Pubkey,
     pub maker: Pubkey,
+    pub amount_a: u64,
+    pub amount_b_expected: u64,
+    pub keeper_tip: u64,
+    /// Paid to the taker out of `amount_a` when a funded escrow was cancelled
+    pub penalty: u64,
+    pub locked_lamports: u64,
     pub ts: i64,
 }
 
 #[event]
 pub struct EscrowRefunded {
+    pub seq: u64,
+    pub escrow: Pubkey,
+    pub maker: Pubkey,
+    pub amount_a: u64,
+    pub amount_b_expected: u64,
+    pub keeper_tip: u64,
+    /// The attester that refunded both parties, if the refund came from `attest_refund`
+    pub attester: Option<Pubkey>,
+    pub locked_lamports: u64,
+    pub ts: i64,
+}
+
+#[event]
+pub struct EscrowAmended {
+    pub seq: u64,
+    pub escrow: Pubkey,
+    pub maker: Pubkey,
+    pub amount_b_expected: u64,
+    pub expiry_ts: i64,
+    /// The escrow's `terms_version` after this amendment
+    pub terms_version: u64,
+    pub locked_lamports: u64,
+    pub ts: i64,
+}
+
+/// The maker handed their position in an escrow to someone else
+#[event]
+pub struct MakerRightsTransferred {
+    pub seq: u64,
+    pub escrow: Pubkey,
+    pub old_maker: Pubkey,
+    pub new_maker: Pubkey,
+    /// Whether the taker co-signed the transfer
+    pub counterparty_consent: bool,
+    pub locked_lamports: u64,
+    pub ts: i64,
+}
+
+/// The taker handed their position in an escrow to someone else
+#[event]
+pub struct TakerRightsTransferred {
+    pub seq: u64,
+    pub escrow: Pubkey,
+    pub old_taker: Pubkey,
+    pub new_taker: Pubkey,
+    /// Whether the maker co-signed the transfer
+    pub counterparty_consent: bool,
+    pub locked_lamports: u64,
+    pub ts: i64,
+}
+
+/// Bids sit in their own PDAs, so bid events' `locked_lamports` is still just the escrow's
+#[event]
+pub struct BidPlaced {
+    pub seq: u64,
+    pub escrow: Pubkey,
+    pub bidder: Pubkey,
+    /// The bid for English auctions; the deposit for sealed ones
+    pub amount: u64,
+    pub sealed: bool,
+    /// The outbid leader refunded by this bid, if any
+    pub refunded: Option<Pubkey>,
+    pub locked_lamports: u64,
+    pub ts: i64,
+}
+
+#[event]
+pub struct BidRevealed {
+    pub seq: u64,
+    pub escrow: Pubkey,
+    pub bidder: Pubkey,
+    pub amount: u64,
+    /// Whether this bid now leads the auction
+    pub leading: bool,
+    pub locked_lamports: u64,
+    pub ts: i64,
+}
+
+#[event]
+pub struct BidWithdrawn {
+    pub seq: u64,
+    pub escrow: Pubkey,
+    pub bidder: Pubkey,
+    /// Deposit returned, excluding the bid account's rent
+    pub amount: u64,
+    pub locked_lamports: u64,
+    pub ts: i64,
+}
+
+/// A subscription was pre-funded; subscription events report it in place of an escrow
+#[event]
+pub struct SubscriptionCreated {
+    pub seq: u64,
+    pub subscription: Pubkey,
+    pub payer: Pubkey,
+    pub payee: Pubkey,
+    pub subscription_id: u64,
+    pub amount_per_period: u64,
+    pub period_secs: i64,
+    pub periods: u32,
+    pub locked_lamports: u64,
+    pub ts: i64,
+}
+
+#[event]
+pub struct PeriodCollected {
+    pub seq: u64,
+    pub subscription: Pubkey,
+    pub payee: Pubkey,
+    /// Which period was paid, counting from 0
+    pub period: u32,
+    pub amount: u64,
+    pub locked_lamports: u64,
+    pub ts: i64,
+}
+
+#[event]
+pub struct SubscriptionCancelled {
+    pub seq: u64,
+    pub subscription: Pubkey,
+    pub payer: Pubkey,
+    /// Elapsed but uncollected periods paid out on cancel
+    pub paid_to_payee: u64,
+    /// Unelapsed periods returned to the payer
+    pub refunded: u64,
+    pub locked_lamports: u64,
+    pub ts: i64,
+}
+
+/// A side's payout was tokenized
+#[event]
+pub struct ReceiptMinted {
+    pub seq: u64,
+    pub escrow: Pubkey,
+    pub side: ReceiptSide,
+    pub mint: Pubkey,
+    /// Who the receipt was minted to
+    pub owner: Pubkey,
+    pub locked_lamports: u64,
+    pub ts: i64,
+}
+
+/// A receipt was burned and its claim paid
+#[event]
+pub struct ReceiptRedeemed {
+    pub seq: u64,
+    pub escrow: Pubkey,
+    pub side: ReceiptSide,
+    pub holder: Pubkey,
+    pub amount: u64,
+    pub locked_lamports: u64,
+    pub ts: i64,
+}
+
+#[event]
+pub struct OperatorChanged {
+    pub seq: u64,
+    pub escrow: Pubkey,
+    pub maker: Pubkey,
+    /// The new operator, or None when revoked
+    pub operator: Option<Pubkey>,
+    /// The operator's `OPERATOR_CAN_*` flags; 0 when revoked
+    pub permissions: u8,
+    pub locked_lamports: u64,
+    pub ts: i64,
+}
+
+#[event]
+pub struct KeeperCranked {
+    pub seq: u64,
+    pub escrow: Pubkey,
+    pub maker: Pubkey,
+    pub keeper: Pubkey,
+    pub amount_a: u64,
+    pub amount_b_expected: u64,
+    pub bounty: u64,
+    pub locked_lamports: u64,
+    pub ts: i64,
+}
+
+#[event]
+pub struct TakerBondSlashed {
+    pub seq: u64,
     pub escrow: Pubkey,
     pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub amount_a: u64,
+    pub amount_b_expected: u64,
+    /// Bond paid to the maker
+    pub bond: u64,
+    pub locked_lamports: u64,
+    pub ts: i64,
+}
+
+/// Program-wide events are about the `Config` account rather than an escrow,
+/// so they carry no `locked_lamports`.
+#[event]
+pub struct Paused {
+    pub seq: u64,
+    pub config: Pubkey,
+    pub admin: Pubkey,
+    pub ts: i64,
+}
+
+#[event]
+pub struct Unpaused {
+    pub seq: u64,
+    pub config: Pubkey,
+    pub admin: Pubkey,
     pub ts: i64,
 }
 
@@ -658,4 +6342,289 @@ pub enum EscrowError {
     /// Protects takers from funding expired escrows
     #[msg("Escrow has expired and cannot be funded")]
     EscrowExpired,
+
+    /// The maker has used up every escrow ID
+    /// Only reachable by explicitly creating an escrow with ID u64::MAX
+    #[msg("No escrow IDs left for this maker")]
+    EscrowIdOverflow,
+
+    /// The chosen index page already lists the maximum number of escrows
+    /// Pick the next page number instead
+    #[msg("Escrow index page is full")]
+    IndexPageFull,
+
+    /// The index page passed in does not list this escrow
+    /// Use the page recorded in the escrow's `index_page`
+    #[msg("Escrow is not listed on this index page")]
+    NotInIndex,
+
+    /// The global event counter cannot go any higher
+    /// Practically unreachable, but checked rather than wrapped
+    #[msg("Event sequence number overflow")]
+    EventSeqOverflow,
+
+    /// The mint offered for an NFT escrow isn't a one-of-one token
+    /// Requires a supply of exactly 1, 0 decimals, and no mint authority other than the master edition
+    #[msg("Mint is not an NFT")]
+    NotAnNft,
+
+    /// The NFT isn't a verified member of the collection the maker required
+    /// Pass the mint's Metaplex metadata account when requiring a collection
+    #[msg("NFT is not a verified member of the required collection")]
+    NftCollectionMismatch,
+
+    /// SOL-only instructions cannot move an escrowed NFT
+    /// Use `complete_nft_swap` or `cancel_nft_escrow` instead
+    #[msg("Escrow holds an NFT; use the NFT instruction")]
+    NftEscrow,
+
+    /// The mint passed in is not the one this escrow holds
+    /// Also returned when an NFT instruction is used on a SOL escrow
+    #[msg("NFT mint does not match the escrow")]
+    NftMintMismatch,
+
+    /// Oracle price bounds must be positive with min <= max
+    /// Checked when an oracle-priced escrow is created
+    #[msg("Invalid oracle price bounds")]
+    InvalidPriceBounds,
+
+    /// The price feed passed in is not the one the escrow was created with
+    /// Oracle-priced escrows must be funded with their `price_feed`
+    #[msg("Price feed does not match the escrow's oracle")]
+    OracleMismatch,
+
+    /// The price feed is not a Pyth price account
+    /// Wrong owner, magic number, version or account type
+    #[msg("Price feed is not a valid Pyth price account")]
+    InvalidOracle,
+
+    /// The oracle price is older than the escrow allows, or the feed is not trading
+    /// Wait for a fresh price update and fund again
+    #[msg("Oracle price is stale or not trading")]
+    StalePrice,
+
+    /// The oracle's confidence interval is wider than the escrow allows
+    /// Protects both parties from pricing during volatile or thin markets
+    #[msg("Oracle price confidence interval is too wide")]
+    PriceConfidenceTooWide,
+
+    /// The oracle price is outside the maker's min/max bounds
+    /// The escrow can't be funded until the price comes back in range
+    #[msg("Oracle price is outside the escrow's bounds")]
+    PriceOutOfBounds,
+
+    /// The signer is not the attester this escrow names, or the escrow has none
+    /// Only the designated attester can release or refund a gated escrow
+    #[msg("Signer is not this escrow's attester")]
+    InvalidAttester,
+
+    /// The escrow is attestation-gated, so the taker can't complete it directly
+    /// Wait for the attester to call `attest_release`
+    #[msg("Escrow can only be released by its attester")]
+    AttestationRequired,
+
+    /// Multisig owners must be distinct and at most `MAX_MULTISIG_OWNERS`
+    /// The threshold must be between 1 and the number of owners
+    #[msg("Invalid multisig owners or threshold")]
+    InvalidMultisig,
+
+    /// The signer is not one of the multisig's owners
+    /// Only owners can propose, approve or execute
+    #[msg("Signer is not a multisig owner")]
+    NotMultisigOwner,
+
+    /// This owner has already approved the proposal
+    /// Each owner counts once toward the threshold
+    #[msg("Owner already approved this proposal")]
+    AlreadyApproved,
+
+    /// The proposal doesn't have enough approvals yet
+    /// Gather `threshold` approvals before executing
+    #[msg("Proposal has not reached the approval threshold")]
+    ThresholdNotMet,
+
+    /// The escrow's terms were amended after the proposal was opened
+    /// Its approvals no longer count; open a new proposal
+    #[msg("Escrow terms changed since this proposal was made")]
+    StaleProposal,
+
+    /// The escrow or destination passed in isn't the one the proposal names
+    /// Also returned when an escrow action targets an escrow the multisig didn't make
+    #[msg("Account does not match the proposal")]
+    ProposalMismatch,
+
+    /// The treasury can't pay the withdrawal and stay rent exempt
+    /// Withdraw less, or wait for more escrows to settle
+    #[msg("Multisig treasury has insufficient funds")]
+    InsufficientTreasury,
+
+    /// Operator permissions must be a non-empty set of `OPERATOR_CAN_*` flags
+    /// Use `revoke_operator` to remove every permission
+    #[msg("Invalid operator permissions")]
+    InvalidOperatorPermissions,
+
+    /// The escrow has no operator to revoke
+    /// Nothing was delegated, or it was already revoked
+    #[msg("Escrow has no operator")]
+    NoOperator,
+
+    /// The funder's Merkle proof doesn't lead to the escrow's allowlist root
+    /// Also returned for proofs longer than `MAX_ALLOWLIST_PROOF_LEN`
+    #[msg("Taker is not on the escrow's allowlist")]
+    NotOnAllowlist,
+
+    /// The admin has paused new deposits program-wide
+    /// Cancels, refunds and settlements still work; also returned when pausing twice
+    #[msg("Program is paused")]
+    ProgramPaused,
+
+    /// The program isn't paused, so there is nothing to unpause
+    /// Check `Config.paused` before calling `unpause_program`
+    #[msg("Program is not paused")]
+    NotPaused,
+
+    /// The referral fee is above `MAX_REFERRAL_FEE_BPS`
+    /// Declare a smaller share of the taker's payment
+    #[msg("Referral fee too high")]
+    InvalidReferralFee,
+
+    /// The referrer passed at settlement isn't the one recorded at funding
+    /// Also returned when a recorded referrer is left out, or one is passed but none was recorded
+    #[msg("Referrer does not match the escrow")]
+    ReferrerMismatch,
+
+    /// A batch is empty, longer than `MAX_BATCH_SIZE`, or its accounts don't line up
+    /// Pass the escrow and its index page per escrow, plus its rent payer when cancelling
+    #[msg("Invalid batch size")]
+    InvalidBatchSize,
+
+    /// The escrow requires the counterparty to co-sign position transfers
+    /// Pass the taker (for maker transfers) or maker (for taker transfers) as `counterparty`
+    #[msg("Counterparty consent required")]
+    ConsentRequired,
+
+    /// A position can't be transferred to the key that already holds it
+    /// Pass a different `new_maker` or `new_taker`
+    #[msg("Invalid position transfer")]
+    InvalidTransfer,
+
+    /// This side of the escrow already has a receipt
+    /// Each side can be tokenized once
+    #[msg("Receipt already minted")]
+    ReceiptAlreadyMinted,
+
+    /// The mint passed in isn't the escrow's receipt for that side
+    /// Check `maker_receipt` / `taker_receipt` on the escrow
+    #[msg("Receipt does not match the escrow")]
+    ReceiptMismatch,
+
+    /// The receipt's side has no claim to pay yet
+    /// Wait for the escrow to settle, be cancelled or be refunded
+    #[msg("Nothing to redeem")]
+    NothingToRedeem,
+
+    /// A subscription's period length must be positive
+    /// Pass `period_secs` of at least 1
+    #[msg("Invalid subscription period")]
+    InvalidPeriod,
+
+    /// The next uncollected period hasn't elapsed yet
+    /// Wait until `start_ts + (periods_collected + 1) * period_secs`
+    #[msg("Subscription period not due yet")]
+    PeriodNotDue,
+
+    /// Dutch auction prices must fall from a start above a positive floor, starting before expiry;
+    /// auctions need a positive reserve and no other pricing, attester or allowlist terms
+    #[msg("Invalid auction terms")]
+    InvalidAuctionTerms,
+
+    /// The price at funding time is above the taker's `max_amount_b`
+    /// Re-read the current price and retry with a higher limit, or wait for it to fall
+    #[msg("Price is above the taker's maximum")]
+    PriceAboveMax,
+
+    /// Auction escrows are bid on and settled, not funded or amended
+    /// Use `place_bid` or `commit_bid`, then `settle_auction`
+    #[msg("This escrow is an auction")]
+    AuctionEscrow,
+
+    /// The escrow isn't an auction of the kind this instruction bids in
+    /// English auctions take `place_bid`, sealed-bid ones `commit_bid` and `reveal_bid`
+    #[msg("Wrong auction kind")]
+    WrongAuctionKind,
+
+    /// The bid is below the reserve price, or doesn't beat the leader by `min_increment`
+    /// Sealed-bid deposits must also cover the reserve
+    #[msg("Bid too low")]
+    BidTooLow,
+
+    /// Sealed bids can only be revealed between `expiry_ts` and the end of the reveal phase
+    /// Bids can't be revealed early or late
+    #[msg("Not in the reveal phase")]
+    NotInRevealPhase,
+
+    /// The amount and salt don't match the commitment, exceed the deposit, or were revealed already
+    /// Reveal with exactly what was hashed at `commit_bid`
+    #[msg("Invalid bid reveal")]
+    InvalidReveal,
+
+    /// Cancels and refunds aren't allowed once an auction has bids (or a winning bid)
+    /// Settle the auction instead, or wait for losing bidders to withdraw
+    #[msg("Auction has bids")]
+    AuctionHasBids,
+
+    /// The leading bid stays locked until the auction is settled
+    /// Only bids that aren't leading can be withdrawn
+    #[msg("Bid is still leading")]
+    BidStillLeading,
+
+    /// The auction ended without a bid at or above the reserve
+    /// Refund it with `refund_after_expiry` or `crank_expired`
+    #[msg("Auction has no winning bid")]
+    NoWinningBid,
+
+    /// The bid or bidder passed isn't the one the escrow records
+    /// Pass the leading bid and its bidder's wallet
+    #[msg("Bid does not match the escrow")]
+    BidMismatch,
+
+    /// The metadata URI is longer than `MAX_METADATA_URI_LEN` bytes
+    /// Shorten it, or point to the record by hash alone
+    #[msg("Invalid escrow metadata")]
+    InvalidMetadata,
+
+    /// The taker bond is zero, has no settlement window, or is combined with an attester or auction
+    /// Bonds need a positive amount and window, on escrows the taker settles themselves
+    #[msg("Invalid taker bond")]
+    InvalidTakerBond,
+
+    /// Only funded escrows that required a taker bond can have it slashed
+    /// Check `escrow.taker_bond` and `escrow.is_funded`
+    #[msg("Escrow has no taker bond to slash")]
+    NoTakerBond,
+
+    /// The taker still has time to complete the swap
+    /// Wait until after `escrow.settle_by_ts`
+    #[msg("Settlement window still open")]
+    SettlementWindowOpen,
+
+    /// The cancellation penalty is above 10,000 bps, or combined with an attester or auction
+    /// Use at most 10,000 bps, on escrows the taker settles themselves
+    #[msg("Invalid cancellation penalty")]
+    InvalidCancelPenalty,
+
+    /// The taker's `UserStats` show fewer settled swaps than the escrow's `min_taker_completed`
+    /// Pass the taker's stats account, or trade with a more established wallet
+    #[msg("Taker has too few completed swaps")]
+    TakerReputationTooLow,
+
+    /// The referrer passed at funding is the taker, the attester or the escrow itself
+    /// They could otherwise collect the fee out of the maker's payment
+    #[msg("Referrer cannot be a party to the escrow")]
+    SelfReferral,
+
+    /// A payout or deposit sum doesn't fit in a `u64`, or a deduction exceeds its base
+    /// The escrow's amounts are inconsistent; nothing was transferred
+    #[msg("Arithmetic overflow")]
+    ArithmeticOverflow,
 }
@@ -3,10 +3,25 @@
 
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
-import { PublicKey, Keypair, SystemProgram } from "@solana/web3.js";
+import { PublicKey, Keypair, SystemProgram, Transaction } from "@solana/web3.js";
+import {
+  createMint,
+  getAccount,
+  getAssociatedTokenAddressSync,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+  transfer,
+} from "@solana/spl-token";
+import {
+  PROGRAM_ID as TOKEN_METADATA_PROGRAM_ID,
+  createCreateMasterEditionV3Instruction,
+  createCreateMetadataAccountV3Instruction,
+  createVerifySizedCollectionItemInstruction,
+} from "@metaplex-foundation/mpl-token-metadata";
 import { assert } from "chai";
 import { Escrow } from "../target/types/escrow";
 import { BN } from "bn.js";
+import { createHash } from "crypto";
 
 describe("escrow", () => {
   const provider = anchor.AnchorProvider.env();
@@ -20,11 +35,65 @@ describe("escrow", () => {
 
   let escrowPda: PublicKey;
   let escrowBump: number;
+  let takerIndexPda: PublicKey;
+  let configPda: PublicKey;
 
   const escrowId = new anchor.BN(1);
   const amountA = new anchor.BN(1000); // lamports
   const amountB = new anchor.BN(500); // lamports
   const expiryTs = new anchor.BN(Math.floor(Date.now() / 1000) + 3600); // 1 hour from now
+  const noTip = new anchor.BN(0); // no keeper bounty
+  const indexPage = 0; // taker's first discovery index page
+  const noDutchAuction = null; // price doesn't fall over time
+  const noProof: number[][] = []; // no allowlist proof needed to fund
+  const noMaxPrice = null; // taker accepts the escrow's price
+  const noReferralFee = 0; // no share of the payment for a referring frontend
+
+  // create_escrow's options for a plain fixed-price swap with the named taker, as EscrowOptions::default()
+  type EscrowOptions = anchor.IdlTypes<Escrow>["escrowOptions"];
+  const defaultEscrowOptions = (): EscrowOptions => ({
+    oracle: null, // no oracle: amountB is fixed at creation
+    dutchAuction: noDutchAuction,
+    auction: null, // sold to the named taker, not auctioned
+    attester: null, // taker completes without an attestation
+    takerAllowlist: null, // escrow is addressed to a single taker
+    referralFeeBps: noReferralFee,
+    requireTransferConsent: false, // either side may transfer its position alone
+    metadata: null, // no off-chain reference
+    takerBond: null, // taker posts no performance bond
+    cancelPenaltyBps: 0, // maker can't cancel once funded
+    minTakerCompleted: 0, // any taker may fund, whatever their history
+  });
+
+  // create_escrow from maker to taker of amountA for amountB, expiring in an hour under the
+  // maker's next ID; tests pass only the arguments and options they're about
+  const createEscrow = ({
+    escrowId = null,
+    amountA: offered = amountA,
+    amountB: expected = amountB,
+    expiryTs: expiry = expiryTs,
+    taker: takerPubkey = taker.publicKey,
+    keeperTip = noTip,
+    ...options
+  }: Partial<EscrowOptions> & {
+    escrowId?: BN | null;
+    amountA?: BN;
+    amountB?: BN;
+    expiryTs?: BN;
+    taker?: PublicKey;
+    keeperTip?: BN;
+  } = {}) =>
+    program.methods.createEscrow(escrowId, offered, expected, expiry, takerPubkey, keeperTip, indexPage, {
+      ...defaultEscrowOptions(),
+      ...options,
+    });
+
+  const userStatsPda = (wallet: PublicKey) =>
+    PublicKey.findProgramAddressSync([Buffer.from("user_stats"), wallet.toBuffer()], program.programId)[0];
+
+  // What closing an account hands back to whoever paid its rent
+  const rentOf = async (account: PublicKey) =>
+    provider.connection.getMinimumBalanceForRentExemption((await provider.connection.getAccountInfo(account))!.data.length);
 
   before(async () => {
     // Airdrop SOL to users
@@ -43,16 +112,44 @@ describe("escrow", () => {
       [Buffer.from("escrow"), maker.publicKey.toBuffer(), new BN(escrowId.toString()).toArrayLike(Buffer, "le", 8)],
       program.programId
     );
+
+    // The pause switch can only be set up by the upgrade authority (the provider wallet here)
+    [configPda] = PublicKey.findProgramAddressSync([Buffer.from("config")], program.programId);
+    if (!(await provider.connection.getAccountInfo(configPda))) {
+      const [programData] = PublicKey.findProgramAddressSync(
+        [program.programId.toBuffer()],
+        new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+      );
+      await program.methods
+        .initializeConfig()
+        .accounts({ config: configPda, escrowProgram: program.programId, programData, admin: provider.wallet.publicKey })
+        .rpc();
+    }
+
+    // Derive the taker's discovery index page
+    [takerIndexPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow_index"), taker.publicKey.toBuffer(), new BN(indexPage).toArrayLike(Buffer, "le", 4)],
+      program.programId
+    );
+
+    // Maker and taker keep a track record, each paying for their own
+    for (const wallet of [maker, taker]) {
+      await program.methods
+        .openUserStats()
+        .accounts({ wallet: wallet.publicKey, payer: wallet.publicKey })
+        .signers([wallet])
+        .rpc();
+    }
   });
 
   it("Creates escrow successfully", async () => {
     const makerBalanceBefore = await provider.connection.getBalance(maker.publicKey);
 
-    await program.methods
-      .createEscrow(escrowId, amountA, amountB, expiryTs, taker.publicKey)
+    await createEscrow({ escrowId })
       .accounts({
         escrow: escrowPda,
         maker: maker.publicKey,
+        rentPayer: maker.publicKey,
         systemProgram: SystemProgram.programId,
       })
       .signers([maker])
@@ -76,7 +173,7 @@ describe("escrow", () => {
     const escrowBalanceBefore = await provider.connection.getBalance(escrowPda);
 
     await program.methods
-      .fundEscrow()
+      .fundEscrow(noProof, noMaxPrice)
       .accounts({
         escrow: escrowPda,
         taker: taker.publicKey,
@@ -97,11 +194,13 @@ describe("escrow", () => {
   it("Completes swap successfully", async () => {
     const makerBalanceBefore = await provider.connection.getBalance(maker.publicKey);
     const takerBalanceBefore = await provider.connection.getBalance(taker.publicKey);
+    const escrowBalance = await provider.connection.getBalance(escrowPda);
 
     await program.methods
       .completeSwap()
       .accounts({
         escrow: escrowPda,
+        escrowIndex: takerIndexPda,
         taker: taker.publicKey,
         maker: maker.publicKey,
         systemProgram: SystemProgram.programId,
@@ -109,32 +208,37 @@ describe("escrow", () => {
       .signers([taker])
       .rpc();
 
-    // Verify escrow is completed
-    const escrowAccount = await program.account.escrowAccount.fetch(escrowPda);
-    assert(!escrowAccount.isActive);
-    assert(!escrowAccount.isFunded);
+    // The completed escrow is closed
+    assert.isNull(await provider.connection.getAccountInfo(escrowPda));
 
     // Verify SOL transfers
     const makerBalanceAfter = await provider.connection.getBalance(maker.publicKey);
     const takerBalanceAfter = await provider.connection.getBalance(taker.publicKey);
 
-    // Maker should receive amountB (500 lamports)
-    assert.equal(makerBalanceAfter, makerBalanceBefore + amountB.toNumber());
+    // Maker should receive amountB (500 lamports), plus the escrow's rent they paid
+    assert.equal(makerBalanceAfter, makerBalanceBefore + escrowBalance - amountA.toNumber());
     // Taker should receive amountA (1000 lamports)
     assert.equal(takerBalanceAfter, takerBalanceBefore + amountA.toNumber());
+
+    // Both sides now have a settled swap on record
+    const takerStats = await program.account.userStats.fetch(userStatsPda(taker.publicKey));
+    assert.equal(takerStats.completed, 1);
+    assert.equal(takerStats.volume.toNumber(), amountB.toNumber());
+    const makerStats = await program.account.userStats.fetch(userStatsPda(maker.publicKey));
+    assert.equal(makerStats.volume.toNumber(), amountA.toNumber());
   });
 
   // Error cases
   it("Fails to create escrow with zero amountA", async () => {
     try {
-      await program.methods
-        .createEscrow(new BN(2), new BN(0), amountB, expiryTs, taker.publicKey)
+      await createEscrow({ escrowId: new BN(2), amountA: new BN(0) })
         .accounts({
           escrow: PublicKey.findProgramAddressSync(
             [Buffer.from("escrow"), maker.publicKey.toBuffer(), new BN(2).toArrayLike(Buffer, "le", 8)],
             program.programId
           )[0],
           maker: maker.publicKey,
+          rentPayer: maker.publicKey,
           systemProgram: SystemProgram.programId,
         })
         .signers([maker])
@@ -147,14 +251,14 @@ describe("escrow", () => {
 
   it("Fails to create escrow with zero amountB", async () => {
     try {
-      await program.methods
-        .createEscrow(new BN(3), amountA, new BN(0), expiryTs, taker.publicKey)
+      await createEscrow({ escrowId: new BN(3), amountB: new BN(0) })
         .accounts({
           escrow: PublicKey.findProgramAddressSync(
             [Buffer.from("escrow"), maker.publicKey.toBuffer(), new BN(3).toArrayLike(Buffer, "le", 8)],
             program.programId
           )[0],
           maker: maker.publicKey,
+          rentPayer: maker.publicKey,
           systemProgram: SystemProgram.programId,
         })
         .signers([maker])
@@ -168,14 +272,14 @@ describe("escrow", () => {
   it("Fails to create escrow with past expiry", async () => {
     const pastExpiry = new BN(Math.floor(Date.now() / 1000) - 3600);
     try {
-      await program.methods
-        .createEscrow(new BN(4), amountA, amountB, pastExpiry, taker.publicKey)
+      await createEscrow({ escrowId: new BN(4), expiryTs: pastExpiry })
         .accounts({
           escrow: PublicKey.findProgramAddressSync(
             [Buffer.from("escrow"), maker.publicKey.toBuffer(), new BN(4).toArrayLike(Buffer, "le", 8)],
             program.programId
           )[0],
           maker: maker.publicKey,
+          rentPayer: maker.publicKey,
           systemProgram: SystemProgram.programId,
         })
         .signers([maker])
@@ -196,11 +300,11 @@ describe("escrow", () => {
 
     const makerBalanceBefore = await provider.connection.getBalance(maker.publicKey);
 
-    await program.methods
-      .createEscrow(cancelEscrowId, amountA, amountB, expiryTs, taker.publicKey)
+    await createEscrow({ escrowId: cancelEscrowId })
       .accounts({
         escrow: cancelEscrowPda,
         maker: maker.publicKey,
+        rentPayer: maker.publicKey,
         systemProgram: SystemProgram.programId,
       })
       .signers([maker])
@@ -211,20 +315,16 @@ describe("escrow", () => {
       .cancelEscrow()
       .accounts({
         escrow: cancelEscrowPda,
+        escrowIndex: takerIndexPda,
         maker: maker.publicKey,
+        authority: maker.publicKey,
         systemProgram: SystemProgram.programId,
       })
       .signers([maker])
       .rpc();
 
-    // Verify escrow is cancelled
-    const escrowAccount = await program.account.escrowAccount.fetch(cancelEscrowPda);
-    assert(!escrowAccount.isActive);
-
-    // Verify maker got their SOL back (the escrow PDA maintains rent-exempt balance)
-    const makerBalanceAfter = await provider.connection.getBalance(maker.publicKey);
-    // The maker's balance may decrease due to transaction fees, but escrow should be inactive
-    assert(!escrowAccount.isActive);
+    // The cancelled escrow is closed, its rent back with the maker's SOL
+    assert.isNull(await provider.connection.getAccountInfo(cancelEscrowPda));
   });
 
   it("Refunds after expiry successfully", async () => {
@@ -238,11 +338,11 @@ describe("escrow", () => {
     const shortExpiry = new BN(Math.floor(Date.now() / 1000) + 5); // 5 seconds from now
     const makerBalanceBefore = await provider.connection.getBalance(maker.publicKey);
 
-    await program.methods
-      .createEscrow(refundEscrowId, amountA, amountB, shortExpiry, taker.publicKey)
+    await createEscrow({ escrowId: refundEscrowId, expiryTs: shortExpiry })
       .accounts({
         escrow: refundEscrowPda,
         maker: maker.publicKey,
+        rentPayer: maker.publicKey,
         systemProgram: SystemProgram.programId,
       })
       .signers([maker])
@@ -256,19 +356,2026 @@ describe("escrow", () => {
       .refundAfterExpiry()
       .accounts({
         escrow: refundEscrowPda,
+        escrowIndex: takerIndexPda,
+        maker: maker.publicKey,
+        authority: maker.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker])
+      .rpc();
+
+    // The refunded escrow is closed, its rent back with the maker's SOL
+    assert.isNull(await provider.connection.getAccountInfo(refundEscrowPda));
+  });
+
+  it("Fails to crank escrow before expiry", async () => {
+    const crankEscrowId = new BN(7);
+    const crankEscrowPda = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.publicKey.toBuffer(), crankEscrowId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+
+    await createEscrow({ escrowId: crankEscrowId, keeperTip: new BN(100) })
+      .accounts({
+        escrow: crankEscrowPda,
+        maker: maker.publicKey,
+        rentPayer: maker.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker])
+      .rpc();
+
+    try {
+      await program.methods
+        .crankExpired()
+        .accounts({
+          escrow: crankEscrowPda,
+          escrowIndex: takerIndexPda,
+          maker: maker.publicKey,
+          rentPayer: maker.publicKey,
+          keeper: otherUser.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([otherUser])
+        .rpc();
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert(err.message.includes("NotExpired"));
+    }
+  });
+
+  it("Keeper cranks expired escrow and collects tip", async () => {
+    const crankEscrowId = new BN(8);
+    const crankEscrowPda = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.publicKey.toBuffer(), crankEscrowId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+    const keeperTip = new BN(100);
+    const shortExpiry = new BN(Math.floor(Date.now() / 1000) + 5); // 5 seconds from now
+
+    await createEscrow({ escrowId: crankEscrowId, expiryTs: shortExpiry, keeperTip })
+      .accounts({
+        escrow: crankEscrowPda,
+        maker: maker.publicKey,
+        rentPayer: maker.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker])
+      .rpc();
+
+    // Wait for expiry
+    await new Promise(resolve => setTimeout(resolve, 10000)); // Wait 10 seconds
+
+    const escrowBalance = await provider.connection.getBalance(crankEscrowPda);
+    const makerBalanceBefore = await provider.connection.getBalance(maker.publicKey);
+
+    // Keeper pays the fee, so the maker's balance only moves by the refund
+    await program.methods
+      .crankExpired()
+      .accounts({
+        escrow: crankEscrowPda,
+        escrowIndex: takerIndexPda,
+        maker: maker.publicKey,
+        rentPayer: maker.publicKey,
+        keeper: otherUser.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([otherUser])
+      .rpc();
+
+    // Escrow account is closed
+    assert.isNull(await provider.connection.getAccountInfo(crankEscrowPda));
+
+    // Maker gets amountA plus the rent; the tip went to the keeper
+    const makerBalanceAfter = await provider.connection.getBalance(maker.publicKey);
+    assert.equal(makerBalanceAfter, makerBalanceBefore + escrowBalance - keeperTip.toNumber());
+  });
+
+  it("Assigns the next escrow ID from the maker profile", async () => {
+    const makerProfilePda = PublicKey.findProgramAddressSync(
+      [Buffer.from("maker_profile"), maker.publicKey.toBuffer()],
+      program.programId
+    )[0];
+
+    // Explicit IDs used above have pushed the counter past the highest one
+    const profileBefore = await program.account.makerProfile.fetch(makerProfilePda);
+    const nextId = profileBefore.nextEscrowId;
+    assert.equal(nextId.toNumber(), 9);
+
+    const autoEscrowPda = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.publicKey.toBuffer(), nextId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+
+    await createEscrow()
+      .accounts({
+        escrow: autoEscrowPda,
+        maker: maker.publicKey,
+        rentPayer: maker.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker])
+      .rpc();
+
+    const escrowAccount = await program.account.escrowAccount.fetch(autoEscrowPda);
+    assert.equal(escrowAccount.escrowId.toNumber(), nextId.toNumber());
+
+    const profileAfter = await program.account.makerProfile.fetch(makerProfilePda);
+    assert.equal(profileAfter.nextEscrowId.toNumber(), nextId.toNumber() + 1);
+  });
+
+  it("Lists open escrows on the taker's index and unlists them when settled", async () => {
+    const indexEscrowId = new BN(10);
+    const indexEscrowPda = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.publicKey.toBuffer(), indexEscrowId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+
+    await createEscrow({ escrowId: indexEscrowId })
+      .accounts({
+        escrow: indexEscrowPda,
+        maker: maker.publicKey,
+        rentPayer: maker.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker])
+      .rpc();
+
+    // A taker finds the escrow by reading a single index page
+    let takerIndex = await program.account.escrowIndex.fetch(takerIndexPda);
+    assert.equal(takerIndex.taker.toString(), taker.publicKey.toString());
+    assert(takerIndex.escrows.some((e: PublicKey) => e.equals(indexEscrowPda)));
+
+    // Another taker's page can't stand in for the one the escrow is listed on
+    const otherEscrowId = new BN(43);
+    const otherEscrowPda = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.publicKey.toBuffer(), otherEscrowId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+    const otherIndexPda = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow_index"), otherUser.publicKey.toBuffer(), new BN(indexPage).toArrayLike(Buffer, "le", 4)],
+      program.programId
+    )[0];
+    await createEscrow({ escrowId: otherEscrowId, taker: otherUser.publicKey })
+      .accounts({
+        escrow: otherEscrowPda,
+        escrowIndex: otherIndexPda,
+        maker: maker.publicKey,
+        rentPayer: maker.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker])
+      .rpc();
+    try {
+      await program.methods
+        .cancelEscrow()
+        .accounts({
+          escrow: indexEscrowPda,
+          escrowIndex: otherIndexPda,
+          maker: maker.publicKey,
+          authority: maker.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([maker])
+        .rpc();
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert(err.message.includes("ConstraintSeeds"));
+    }
+    await program.methods
+      .cancelEscrow()
+      .accounts({
+        escrow: otherEscrowPda,
+        escrowIndex: otherIndexPda,
+        maker: maker.publicKey,
+        authority: maker.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker])
+      .rpc();
+
+    await program.methods
+      .cancelEscrow()
+      .accounts({
+        escrow: indexEscrowPda,
+        escrowIndex: takerIndexPda,
+        maker: maker.publicKey,
+        authority: maker.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker])
+      .rpc();
+
+    takerIndex = await program.account.escrowIndex.fetch(takerIndexPda);
+    assert(!takerIndex.escrows.some((e: PublicKey) => e.equals(indexEscrowPda)));
+  });
+
+  it("Emits CPI events numbered per escrow", async () => {
+    const seqEscrowId = new BN(11);
+    const seqEscrowPda = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.publicKey.toBuffer(), seqEscrowId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+    const makerProfilePda = PublicKey.findProgramAddressSync(
+      [Buffer.from("maker_profile"), maker.publicKey.toBuffer()],
+      program.programId
+    )[0];
+    // The escrow numbers its events from the block its maker's profile reserves for it
+    const { escrowsCreated } = await program.account.makerProfile.fetch(makerProfilePda);
+    const firstSeq = escrowsCreated.mul(new BN(2).pow(new BN(32)));
+
+    const tx = await createEscrow({ escrowId: seqEscrowId })
+      .accounts({
+        escrow: seqEscrowPda,
         maker: maker.publicKey,
+        rentPayer: maker.publicKey,
         systemProgram: SystemProgram.programId,
       })
       .signers([maker])
+      .rpc({ commitment: "confirmed" });
+
+    const seqEscrow = await program.account.escrowAccount.fetch(seqEscrowPda);
+    assert.equal(seqEscrow.eventSeq.toString(), firstSeq.addn(1).toString());
+
+    // The event travels as self-CPI instruction data: 8-byte tag, then the event itself
+    const txDetails = await provider.connection.getTransaction(tx, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const eventIx = txDetails.meta.innerInstructions[0].instructions.find(
+      (ix) => txDetails.transaction.message.staticAccountKeys[ix.programIdIndex].equals(program.programId)
+    );
+    const eventData = Buffer.from(anchor.utils.bytes.bs58.decode(eventIx.data)).subarray(8);
+    const event = program.coder.events.decode(eventData.toString("base64"));
+
+    assert.equal(event.data.seq.toString(), firstSeq.toString());
+    assert.equal(event.data.amountA.toNumber(), amountA.toNumber());
+    assert.equal(event.data.lockedLamports.toNumber(), amountA.toNumber());
+  });
+
+  // NFT-for-SOL escrows. The Metaplex token metadata program is loaded from
+  // tests/fixtures/mpl_token_metadata.so (see Anchor.toml).
+
+  const metadataPda = (mint: PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("metadata"), TOKEN_METADATA_PROGRAM_ID.toBuffer(), mint.toBuffer()],
+      TOKEN_METADATA_PROGRAM_ID
+    )[0];
+
+  const editionPda = (mint: PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("metadata"), TOKEN_METADATA_PROGRAM_ID.toBuffer(), mint.toBuffer(), Buffer.from("edition")],
+      TOKEN_METADATA_PROGRAM_ID
+    )[0];
+
+  // Mints a one-of-one NFT to the maker with metadata and a master edition,
+  // optionally claiming (and verifying) membership of a collection
+  const mintNft = async (opts: { collection?: PublicKey; verify?: boolean; isCollection?: boolean } = {}) => {
+    const mint = await createMint(provider.connection, maker, maker.publicKey, maker.publicKey, 0);
+    const makerAta = await getOrCreateAssociatedTokenAccount(provider.connection, maker, mint, maker.publicKey);
+    await mintTo(provider.connection, maker, mint, makerAta.address, maker, 1);
+
+    const accounts = {
+      metadata: metadataPda(mint),
+      mint,
+      mintAuthority: maker.publicKey,
+      payer: maker.publicKey,
+      updateAuthority: maker.publicKey,
+    };
+    const tx = new Transaction().add(
+      createCreateMetadataAccountV3Instruction(accounts, {
+        createMetadataAccountArgsV3: {
+          data: {
+            name: "Escrow Test",
+            symbol: "ESC",
+            uri: "",
+            sellerFeeBasisPoints: 0,
+            creators: null,
+            collection: opts.collection ? { key: opts.collection, verified: false } : null,
+            uses: null,
+          },
+          isMutable: true,
+          collectionDetails: opts.isCollection ? { __kind: "V1", size: 0 } : null,
+        },
+      }),
+      createCreateMasterEditionV3Instruction(
+        { ...accounts, edition: editionPda(mint) },
+        { createMasterEditionArgs: { maxSupply: 0 } }
+      )
+    );
+    if (opts.collection && opts.verify) {
+      tx.add(
+        createVerifySizedCollectionItemInstruction({
+          metadata: metadataPda(mint),
+          collectionAuthority: maker.publicKey,
+          payer: maker.publicKey,
+          collectionMint: opts.collection,
+          collection: metadataPda(opts.collection),
+          collectionMasterEditionAccount: editionPda(opts.collection),
+        })
+      );
+    }
+    await provider.sendAndConfirm(tx, [maker]);
+
+    return { mint, makerAta: makerAta.address };
+  };
+
+  const nftEscrowPda = (id: BN) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.publicKey.toBuffer(), id.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+
+  let collectionMint: PublicKey;
+
+  it("Sells a verified collection NFT for SOL", async () => {
+    ({ mint: collectionMint } = await mintNft({ isCollection: true }));
+    const { mint, makerAta } = await mintNft({ collection: collectionMint, verify: true });
+    const nftEscrowId = new BN(12);
+    const price = new BN(anchor.web3.LAMPORTS_PER_SOL / 10);
+
+    await program.methods
+      .createNftEscrow(nftEscrowId, price, expiryTs, taker.publicKey, collectionMint, indexPage, noDutchAuction)
+      .accounts({
+        escrow: nftEscrowPda(nftEscrowId),
+        nftMint: mint,
+        makerNftAccount: makerAta,
+        nftMetadata: metadataPda(mint),
+        maker: maker.publicKey,
+        rentPayer: maker.publicKey,
+      })
+      .signers([maker])
+      .rpc();
+
+    const escrowAccount = await program.account.escrowAccount.fetch(nftEscrowPda(nftEscrowId));
+    assert.equal(escrowAccount.nftMint.toString(), mint.toString());
+    assert.equal(escrowAccount.amountA.toNumber(), 1);
+    assert.equal(Number((await getAccount(provider.connection, makerAta)).amount), 0);
+
+    await program.methods
+      .fundEscrow(noProof, noMaxPrice)
+      .accounts({
+        escrow: nftEscrowPda(nftEscrowId),
+        taker: taker.publicKey,
+        maker: maker.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([taker])
+      .rpc();
+
+    const makerBalanceBefore = await provider.connection.getBalance(maker.publicKey);
+    const { volume: makerVolumeBefore } = await program.account.userStats.fetch(userStatsPda(maker.publicKey));
+    await program.methods
+      .completeNftSwap()
+      .accounts({
+        escrow: nftEscrowPda(nftEscrowId),
+        escrowIndex: takerIndexPda,
+        nftMint: mint,
+        taker: taker.publicKey,
+        maker: maker.publicKey,
+        rentPayer: maker.publicKey,
+      })
+      .signers([taker])
       .rpc();
 
-    // Verify escrow is inactive
-    const escrowAccount = await program.account.escrowAccount.fetch(refundEscrowPda);
-    assert(!escrowAccount.isActive);
+    const takerAta = await getOrCreateAssociatedTokenAccount(provider.connection, taker, mint, taker.publicKey);
+    assert.equal(Number(takerAta.amount), 1);
 
-    // Verify maker got their SOL back (the escrow PDA maintains rent-exempt balance)
+    // Maker gets the price plus the closed vault's rent
     const makerBalanceAfter = await provider.connection.getBalance(maker.publicKey);
-    // The maker's balance may decrease due to transaction fees, but escrow should be inactive
-    assert(!escrowAccount.isActive);
+    assert(makerBalanceAfter >= makerBalanceBefore + price.toNumber());
+
+    // The maker's volume counts what the NFT sold for
+    const { volume: makerVolumeAfter } = await program.account.userStats.fetch(userStatsPda(maker.publicKey));
+    assert.equal(makerVolumeAfter.toNumber(), makerVolumeBefore.toNumber() + price.toNumber());
+  });
+
+  it("Rejects NFTs that are not verified members of the collection", async () => {
+    const { mint, makerAta } = await mintNft({ collection: collectionMint, verify: false });
+    const nftEscrowId = new BN(13);
+
+    try {
+      await program.methods
+        .createNftEscrow(nftEscrowId, amountB, expiryTs, taker.publicKey, collectionMint, indexPage, noDutchAuction)
+        .accounts({
+          escrow: nftEscrowPda(nftEscrowId),
+          nftMint: mint,
+          makerNftAccount: makerAta,
+          nftMetadata: metadataPda(mint),
+          maker: maker.publicKey,
+          rentPayer: maker.publicKey,
+        })
+        .signers([maker])
+        .rpc();
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert(err.message.includes("NftCollectionMismatch"));
+    }
+  });
+
+  it("Rejects mints that are not one-of-one", async () => {
+    const mint = await createMint(provider.connection, maker, maker.publicKey, null, 0);
+    const makerAta = await getOrCreateAssociatedTokenAccount(provider.connection, maker, mint, maker.publicKey);
+    await mintTo(provider.connection, maker, mint, makerAta.address, maker, 2);
+    const nftEscrowId = new BN(14);
+
+    try {
+      await program.methods
+        .createNftEscrow(nftEscrowId, amountB, expiryTs, taker.publicKey, null, indexPage, noDutchAuction)
+        .accounts({
+          escrow: nftEscrowPda(nftEscrowId),
+          nftMint: mint,
+          makerNftAccount: makerAta.address,
+          nftMetadata: null,
+          maker: maker.publicKey,
+          rentPayer: maker.publicKey,
+        })
+        .signers([maker])
+        .rpc();
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert(err.message.includes("NotAnNft"));
+    }
+
+    // A single token whose mint authority could still mint a second one
+    const mintable = await createMint(provider.connection, maker, maker.publicKey, null, 0);
+    const mintableAta = await getOrCreateAssociatedTokenAccount(provider.connection, maker, mintable, maker.publicKey);
+    await mintTo(provider.connection, maker, mintable, mintableAta.address, maker, 1);
+
+    try {
+      await program.methods
+        .createNftEscrow(nftEscrowId, amountB, expiryTs, taker.publicKey, null, indexPage, noDutchAuction)
+        .accounts({
+          escrow: nftEscrowPda(nftEscrowId),
+          nftMint: mintable,
+          makerNftAccount: mintableAta.address,
+          nftMetadata: null,
+          maker: maker.publicKey,
+          rentPayer: maker.publicKey,
+        })
+        .signers([maker])
+        .rpc();
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert(err.message.includes("NotAnNft"));
+    }
+  });
+
+  it("Returns an unfunded NFT to the maker on cancel", async () => {
+    const { mint, makerAta } = await mintNft();
+    const nftEscrowId = new BN(15);
+
+    // Someone creating the vault's associated token account first doesn't block the escrow
+    await getOrCreateAssociatedTokenAccount(provider.connection, taker, mint, nftEscrowPda(nftEscrowId), true);
+
+    await program.methods
+      .createNftEscrow(nftEscrowId, amountB, expiryTs, taker.publicKey, null, indexPage, noDutchAuction)
+      .accounts({
+        escrow: nftEscrowPda(nftEscrowId),
+        nftMint: mint,
+        makerNftAccount: makerAta,
+        nftMetadata: null,
+        maker: maker.publicKey,
+        rentPayer: maker.publicKey,
+      })
+      .signers([maker])
+      .rpc();
+
+    // The SOL-only instruction refuses to touch an NFT escrow
+    try {
+      await program.methods
+        .cancelEscrow()
+        .accounts({
+          escrow: nftEscrowPda(nftEscrowId),
+          escrowIndex: takerIndexPda,
+          maker: maker.publicKey,
+          authority: maker.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([maker])
+        .rpc();
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert(err.message.includes("NftEscrow"));
+    }
+
+    const { cancelled: cancelledBefore } = await program.account.userStats.fetch(userStatsPda(maker.publicKey));
+    await program.methods
+      .cancelNftEscrow()
+      .accounts({
+        escrow: nftEscrowPda(nftEscrowId),
+        escrowIndex: takerIndexPda,
+        nftMint: mint,
+        maker: maker.publicKey,
+        rentPayer: maker.publicKey,
+      })
+      .signers([maker])
+      .rpc();
+
+    assert.equal(Number((await getAccount(provider.connection, makerAta)).amount), 1);
+    assert.isNull(await provider.connection.getAccountInfo(nftEscrowPda(nftEscrowId)));
+    assert.equal((await program.account.userStats.fetch(userStatsPda(maker.publicKey))).cancelled, cancelledBefore + 1);
+  });
+
+  // Oracle-priced escrows. Mock Pyth price accounts are loaded from tests/fixtures
+  // (see Anchor.toml); both quote 0.5 lamports per lamport of amountA at expo -8.
+
+  const mockPriceFeed = new PublicKey("CEipeAWGMrzukAyte1RAeyttSiLYeAHCp1jxUgp13Dwh");
+  const mockWidePriceFeed = new PublicKey("B5vUNkwxJVV8eJrsNDMPtHbUgfbHhy7yRZD5TnSD3zxF");
+
+  const oracleTerms = (overrides: Record<string, any> = {}) => ({
+    priceFeed: mockPriceFeed,
+    minPrice: new BN(40_000_000),
+    maxPrice: new BN(60_000_000),
+    maxPriceAge: 0xffffffff, // the fixture's publish time never moves
+    maxConfidenceBps: 100,
+    ...overrides,
+  });
+
+  const createOracleEscrow = async (id: BN, terms: any) => {
+    const pda = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.publicKey.toBuffer(), id.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+    await createEscrow({ escrowId: id, amountB: new BN(0), oracle: terms })
+      .accounts({ escrow: pda, maker: maker.publicKey, rentPayer: maker.publicKey, systemProgram: SystemProgram.programId })
+      .signers([maker])
+      .rpc();
+    return pda;
+  };
+
+  const fundWithFeed = (escrow: PublicKey, priceFeed: PublicKey) =>
+    program.methods
+      .fundEscrow(noProof, noMaxPrice)
+      .accounts({
+        escrow,
+        taker: taker.publicKey,
+        maker: maker.publicKey,
+        priceFeed,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([taker])
+      .rpc();
+
+  it("Prices the taker's payment from the oracle at funding", async () => {
+    const oracleEscrowPda = await createOracleEscrow(new BN(16), oracleTerms());
+    let escrowAccount = await program.account.escrowAccount.fetch(oracleEscrowPda);
+    assert.equal(escrowAccount.amountBExpected.toNumber(), 0);
+
+    const takerBalanceBefore = await provider.connection.getBalance(taker.publicKey);
+    await fundWithFeed(oracleEscrowPda, mockPriceFeed);
+
+    // 1000 lamports * 0.5 = 500 lamports
+    escrowAccount = await program.account.escrowAccount.fetch(oracleEscrowPda);
+    assert.equal(escrowAccount.amountBExpected.toNumber(), 500);
+    assert.equal(escrowAccount.isFunded, true);
+    const takerBalanceAfter = await provider.connection.getBalance(taker.publicKey);
+    assert(takerBalanceAfter <= takerBalanceBefore - 500);
+  });
+
+  it("Rejects oracle prices outside the maker's bounds", async () => {
+    const oracleEscrowPda = await createOracleEscrow(new BN(17), oracleTerms({ maxPrice: new BN(45_000_000) }));
+    try {
+      await fundWithFeed(oracleEscrowPda, mockPriceFeed);
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert(err.message.includes("PriceOutOfBounds"));
+    }
+  });
+
+  it("Rejects stale oracle prices", async () => {
+    const oracleEscrowPda = await createOracleEscrow(new BN(18), oracleTerms({ maxPriceAge: 60 }));
+    try {
+      await fundWithFeed(oracleEscrowPda, mockPriceFeed);
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert(err.message.includes("StalePrice"));
+    }
+  });
+
+  it("Rejects oracle prices with a wide confidence interval", async () => {
+    const oracleEscrowPda = await createOracleEscrow(new BN(19), oracleTerms({ priceFeed: mockWidePriceFeed }));
+    try {
+      await fundWithFeed(oracleEscrowPda, mockWidePriceFeed);
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert(err.message.includes("PriceConfidenceTooWide"));
+    }
+
+    // Swapping in a different feed than the escrow names is refused outright
+    try {
+      await fundWithFeed(oracleEscrowPda, mockPriceFeed);
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert(err.message.includes("OracleMismatch"));
+    }
+  });
+
+  // Attestation-gated escrows: otherUser plays the shipping verifier
+
+  const createAttestedEscrow = async (id: BN) => {
+    const pda = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.publicKey.toBuffer(), id.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+    await createEscrow({ escrowId: id, attester: otherUser.publicKey })
+      .accounts({ escrow: pda, maker: maker.publicKey, rentPayer: maker.publicKey, systemProgram: SystemProgram.programId })
+      .signers([maker])
+      .rpc();
+    await program.methods
+      .fundEscrow(noProof, noMaxPrice)
+      .accounts({ escrow: pda, taker: taker.publicKey, maker: maker.publicKey, systemProgram: SystemProgram.programId })
+      .signers([taker])
+      .rpc();
+    return pda;
+  };
+
+  const attestAccounts = (escrow: PublicKey, attester: PublicKey) => ({
+    escrow,
+    escrowIndex: takerIndexPda,
+    attester,
+    taker: taker.publicKey,
+    maker: maker.publicKey,
+    systemProgram: SystemProgram.programId,
+  });
+
+  it("Releases an attestation-gated escrow only on the attester's signature", async () => {
+    const attestedEscrowPda = await createAttestedEscrow(new BN(20));
+
+    // The taker can't settle it directly...
+    try {
+      await program.methods
+        .completeSwap()
+        .accounts({
+          escrow: attestedEscrowPda,
+          escrowIndex: takerIndexPda,
+          taker: taker.publicKey,
+          maker: maker.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([taker])
+        .rpc();
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert(err.message.includes("AttestationRequired"));
+    }
+
+    // ...nor pose as the attester
+    try {
+      await program.methods
+        .attestRelease()
+        .accounts(attestAccounts(attestedEscrowPda, taker.publicKey))
+        .signers([taker])
+        .rpc();
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert(err.message.includes("InvalidAttester"));
+    }
+
+    const takerBalanceBefore = await provider.connection.getBalance(taker.publicKey);
+    const makerBalanceBefore = await provider.connection.getBalance(maker.publicKey);
+    const rent = await rentOf(attestedEscrowPda);
+
+    await program.methods
+      .attestRelease()
+      .accounts(attestAccounts(attestedEscrowPda, otherUser.publicKey))
+      .signers([otherUser])
+      .rpc();
+
+    // The maker paid the escrow's rent, so it comes back with the payment
+    assert.isNull(await provider.connection.getAccountInfo(attestedEscrowPda));
+    assert.equal(await provider.connection.getBalance(taker.publicKey), takerBalanceBefore + amountA.toNumber());
+    assert.equal(await provider.connection.getBalance(maker.publicKey), makerBalanceBefore + amountB.toNumber() + rent);
+  });
+
+  it("Returns both deposits when the attester refunds", async () => {
+    const attestedEscrowPda = await createAttestedEscrow(new BN(21));
+
+    const takerBalanceBefore = await provider.connection.getBalance(taker.publicKey);
+    const makerBalanceBefore = await provider.connection.getBalance(maker.publicKey);
+    const rent = await rentOf(attestedEscrowPda);
+
+    await program.methods
+      .attestRefund()
+      .accounts(attestAccounts(attestedEscrowPda, otherUser.publicKey))
+      .signers([otherUser])
+      .rpc();
+
+    assert.isNull(await provider.connection.getAccountInfo(attestedEscrowPda));
+    assert.equal(await provider.connection.getBalance(taker.publicKey), takerBalanceBefore + amountB.toNumber());
+    assert.equal(await provider.connection.getBalance(maker.publicKey), makerBalanceBefore + amountA.toNumber() + rent);
+  });
+
+  it("Lets the maker amend an unfunded escrow", async () => {
+    const id = new BN(22);
+    const pda = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.publicKey.toBuffer(), id.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+    await createEscrow({ escrowId: id })
+      .accounts({ escrow: pda, maker: maker.publicKey, rentPayer: maker.publicKey, systemProgram: SystemProgram.programId })
+      .signers([maker])
+      .rpc();
+
+    await program.methods
+      .amendEscrow(new BN(700), expiryTs.addn(60))
+      .accounts({ escrow: pda, authority: maker.publicKey })
+      .signers([maker])
+      .rpc();
+
+    const escrowAccount = await program.account.escrowAccount.fetch(pda);
+    assert.equal(escrowAccount.amountBExpected.toNumber(), 700);
+    assert.equal(escrowAccount.expiryTs.toNumber(), expiryTs.toNumber() + 60);
+    assert.equal(escrowAccount.termsVersion.toNumber(), 1);
+  });
+
+  // Delegated operators: otherUser plays the market-making bot
+
+  const OPERATOR_CAN_CANCEL = 1;
+  const OPERATOR_CAN_AMEND = 2;
+
+  it("Lets an operator cancel within its permissions while funds return to the maker", async () => {
+    const id = new BN(23);
+    const pda = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.publicKey.toBuffer(), id.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+    await createEscrow({ escrowId: id })
+      .accounts({ escrow: pda, maker: maker.publicKey, rentPayer: maker.publicKey, systemProgram: SystemProgram.programId })
+      .signers([maker])
+      .rpc();
+    await program.methods
+      .setOperator(otherUser.publicKey, OPERATOR_CAN_CANCEL)
+      .accounts({ escrow: pda, maker: maker.publicKey })
+      .signers([maker])
+      .rpc();
+
+    // Amending wasn't delegated
+    try {
+      await program.methods
+        .amendEscrow(new BN(700), expiryTs)
+        .accounts({ escrow: pda, authority: otherUser.publicKey })
+        .signers([otherUser])
+        .rpc();
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert(err.message.includes("Unauthorized"));
+    }
+
+    const makerBalanceBefore = await provider.connection.getBalance(maker.publicKey);
+    const rent = await rentOf(pda);
+    await program.methods
+      .cancelEscrow()
+      .accounts({
+        escrow: pda,
+        escrowIndex: takerIndexPda,
+        maker: maker.publicKey,
+        authority: otherUser.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([otherUser])
+      .rpc();
+
+    // The refund and the rent go to the maker, never the operator
+    assert.isNull(await provider.connection.getAccountInfo(pda));
+    assert.equal(await provider.connection.getBalance(maker.publicKey), makerBalanceBefore + amountA.toNumber() + rent);
+  });
+
+  it("Revokes an operator's delegation", async () => {
+    const id = new BN(24);
+    const pda = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.publicKey.toBuffer(), id.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+    await createEscrow({ escrowId: id })
+      .accounts({ escrow: pda, maker: maker.publicKey, rentPayer: maker.publicKey, systemProgram: SystemProgram.programId })
+      .signers([maker])
+      .rpc();
+    await program.methods
+      .setOperator(otherUser.publicKey, OPERATOR_CAN_CANCEL | OPERATOR_CAN_AMEND)
+      .accounts({ escrow: pda, maker: maker.publicKey })
+      .signers([maker])
+      .rpc();
+    await program.methods
+      .revokeOperator()
+      .accounts({ escrow: pda, maker: maker.publicKey })
+      .signers([maker])
+      .rpc();
+
+    const escrowAccount = await program.account.escrowAccount.fetch(pda);
+    assert.equal(escrowAccount.operator, null);
+    try {
+      await program.methods
+        .amendEscrow(new BN(700), expiryTs)
+        .accounts({ escrow: pda, authority: otherUser.publicKey })
+        .signers([otherUser])
+        .rpc();
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert(err.message.includes("Unauthorized"));
+    }
+  });
+
+  // Taker allowlists: leaves are sha256(0x00 || key), nodes sha256(0x01 || lo || hi)
+
+  const sha256 = (...parts: Buffer[]) => createHash("sha256").update(Buffer.concat(parts)).digest();
+  const allowlistNode = (a: Buffer, b: Buffer) =>
+    Buffer.compare(a, b) <= 0 ? sha256(Buffer.from([1]), a, b) : sha256(Buffer.from([1]), b, a);
+
+  // Builds the tree bottom-up, carrying an odd node up unchanged; returns the root and each key's proof
+  const allowlistTree = (keys: PublicKey[]) => {
+    let level = keys.map((key) => sha256(Buffer.from([0]), key.toBuffer()));
+    let positions = keys.map((_, i) => i);
+    const proofs: Buffer[][] = keys.map(() => []);
+    while (level.length > 1) {
+      positions.forEach((pos, k) => {
+        if ((pos ^ 1) < level.length) proofs[k].push(level[pos ^ 1]);
+      });
+      const next: Buffer[] = [];
+      for (let i = 0; i < level.length; i += 2) {
+        next.push(i + 1 < level.length ? allowlistNode(level[i], level[i + 1]) : level[i]);
+      }
+      level = next;
+      positions = positions.map((pos) => pos >> 1);
+    }
+    return { root: Array.from(level[0]), proofs: proofs.map((proof) => proof.map((node) => Array.from(node))) };
+  };
+
+  it("Lets any allowlisted wallet take the escrow with a Merkle proof", async () => {
+    const allowlist = [Keypair.generate().publicKey, otherUser.publicKey, Keypair.generate().publicKey, Keypair.generate().publicKey, Keypair.generate().publicKey];
+    const { root, proofs } = allowlistTree(allowlist);
+
+    // The sale is listed on the maker's own index page
+    const id = new BN(25);
+    const pda = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.publicKey.toBuffer(), id.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+    const [listingIndexPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow_index"), maker.publicKey.toBuffer(), new BN(indexPage).toArrayLike(Buffer, "le", 4)],
+      program.programId
+    );
+    await createEscrow({ escrowId: id, taker: maker.publicKey, takerAllowlist: root })
+      .accounts({ escrow: pda, maker: maker.publicKey, rentPayer: maker.publicKey, systemProgram: SystemProgram.programId })
+      .signers([maker])
+      .rpc();
+    const created = await program.account.escrowAccount.fetch(pda);
+    assert.equal(created.taker, null);
+    assert.equal(created.indexTaker.toString(), maker.publicKey.toString());
+
+    // Someone else's proof doesn't work for a wallet that isn't on the list
+    try {
+      await program.methods
+        .fundEscrow(proofs[1], noMaxPrice)
+        .accounts({ escrow: pda, taker: taker.publicKey, maker: maker.publicKey, systemProgram: SystemProgram.programId })
+        .signers([taker])
+        .rpc();
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert(err.message.includes("NotOnAllowlist"));
+    }
+
+    await program.methods
+      .fundEscrow(proofs[1], noMaxPrice)
+      .accounts({ escrow: pda, taker: otherUser.publicKey, maker: maker.publicKey, systemProgram: SystemProgram.programId })
+      .signers([otherUser])
+      .rpc();
+    const funded = await program.account.escrowAccount.fetch(pda);
+    assert.equal(funded.taker.toString(), otherUser.publicKey.toString());
+
+    await program.methods
+      .completeSwap()
+      .accounts({
+        escrow: pda,
+        escrowIndex: listingIndexPda,
+        taker: otherUser.publicKey,
+        maker: maker.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([otherUser])
+      .rpc();
+    assert.isNull(await provider.connection.getAccountInfo(pda));
+
+    // otherUser never opened a track record, so settling opened one, at their expense as the signer
+    const otherUserStats = await program.account.userStats.fetch(userStatsPda(otherUser.publicKey));
+    assert.equal(otherUserStats.wallet.toString(), otherUser.publicKey.toString());
+    assert.equal(otherUserStats.completed, 1);
+  });
+
+  // Multisig makers: maker, otherUser and treasuryOwner, any two of three
+
+  const treasuryOwner = Keypair.generate();
+  const createKey = Keypair.generate();
+  const [multisigPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("multisig"), createKey.publicKey.toBuffer()],
+    program.programId
+  );
+  const [multisigEscrowPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("escrow"), multisigPda.toBuffer(), new BN(0).toArrayLike(Buffer, "le", 8)],
+    program.programId
+  );
+  const proposalPda = (index: number) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("proposal"), multisigPda.toBuffer(), new BN(index).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+
+  const propose = (index: number, action: any, proposer: Keypair, escrow: PublicKey | null) =>
+    program.methods
+      .propose(action)
+      .accounts({ multisig: multisigPda, proposal: proposalPda(index), escrow, proposer: proposer.publicKey })
+      .signers([proposer])
+      .rpc();
+
+  const approve = (index: number, owner: Keypair, escrow: PublicKey | null) =>
+    program.methods
+      .approveProposal()
+      .accounts({ multisig: multisigPda, proposal: proposalPda(index), escrow, owner: owner.publicKey })
+      .signers([owner])
+      .rpc();
+
+  const execute = (index: number, executor: Keypair, accounts: Record<string, PublicKey | null>) =>
+    program.methods
+      .executeProposal()
+      .accounts({
+        multisig: multisigPda,
+        proposal: proposalPda(index),
+        escrow: null,
+        escrowIndex: null,
+        rentPayer: null,
+        makerStats: null,
+        destination: null,
+        proposer: maker.publicKey,
+        executor: executor.publicKey,
+        ...accounts,
+      })
+      .signers([executor])
+      .rpc();
+
+  it("Cancels a multisig escrow only with threshold approvals for its current terms", async () => {
+    await program.methods
+      .createMultisig([maker.publicKey, otherUser.publicKey, treasuryOwner.publicKey], 2)
+      .accounts({ multisig: multisigPda, createKey: createKey.publicKey, payer: maker.publicKey })
+      .signers([createKey, maker])
+      .rpc();
+
+    await program.methods
+      .createMultisigEscrow(null, amountA, amountB, expiryTs, taker.publicKey, noTip, indexPage)
+      .accounts({ multisig: multisigPda, escrow: multisigEscrowPda, owner: maker.publicKey })
+      .signers([maker])
+      .rpc();
+    const created = await program.account.escrowAccount.fetch(multisigEscrowPda);
+    assert.equal(created.maker.toString(), multisigPda.toString());
+
+    // One approval isn't enough
+    await propose(0, { cancel: {} }, maker, multisigEscrowPda);
+    try {
+      await execute(0, maker, { escrow: multisigEscrowPda, escrowIndex: takerIndexPda });
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert(err.message.includes("ThresholdNotMet"));
+    }
+
+    // An approved amendment changes the terms...
+    await propose(1, { amend: { amountBExpected: new BN(600), expiryTs } }, maker, multisigEscrowPda);
+    await approve(1, otherUser, multisigEscrowPda);
+    await execute(1, otherUser, { escrow: multisigEscrowPda });
+    const amended = await program.account.escrowAccount.fetch(multisigEscrowPda);
+    assert.equal(amended.amountBExpected.toNumber(), 600);
+    assert.equal(amended.termsVersion.toNumber(), 1);
+
+    // ...which voids the cancel proposal made against the old terms
+    try {
+      await approve(0, otherUser, multisigEscrowPda);
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert(err.message.includes("StaleProposal"));
+    }
+
+    // Outsiders can't vote
+    await propose(2, { cancel: {} }, maker, multisigEscrowPda);
+    try {
+      await approve(2, taker, multisigEscrowPda);
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert(err.message.includes("NotMultisigOwner"));
+    }
+
+    const treasuryBefore = await provider.connection.getBalance(multisigPda);
+    await approve(2, treasuryOwner, multisigEscrowPda);
+
+    // An owner opens the multisig's track record, since its PDA can't sign
+    await program.methods
+      .openUserStats()
+      .accounts({ wallet: multisigPda, payer: maker.publicKey })
+      .signers([maker])
+      .rpc();
+
+    // The owner who created the escrow paid its rent, so it goes back to them
+    const rent = await rentOf(multisigEscrowPda);
+    const ownerBefore = await provider.connection.getBalance(maker.publicKey);
+    await execute(2, treasuryOwner, {
+      escrow: multisigEscrowPda,
+      escrowIndex: takerIndexPda,
+      rentPayer: maker.publicKey,
+      makerStats: userStatsPda(multisigPda),
+    });
+
+    assert.isNull(await provider.connection.getAccountInfo(multisigEscrowPda));
+    assert.equal(await provider.connection.getBalance(multisigPda), treasuryBefore + amountA.toNumber());
+    assert.equal(await provider.connection.getBalance(maker.publicKey), ownerBefore + rent);
+    assert.equal((await program.account.userStats.fetch(userStatsPda(multisigPda))).cancelled, 1);
+  });
+
+  it("Pays out of the multisig treasury with threshold approvals", async () => {
+    const takerBalanceBefore = await provider.connection.getBalance(taker.publicKey);
+
+    await propose(3, { withdraw: { destination: taker.publicKey, lamports: amountA } }, otherUser, null);
+    await approve(3, maker, null);
+    await execute(3, maker, { destination: taker.publicKey, proposer: otherUser.publicKey });
+
+    assert.equal(await provider.connection.getBalance(taker.publicKey), takerBalanceBefore + amountA.toNumber());
+  });
+
+  it("Blocks new deposits while paused but still lets makers exit", async () => {
+    const id = new BN(26);
+    const pda = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.publicKey.toBuffer(), id.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+    await createEscrow({ escrowId: id })
+      .accounts({ escrow: pda, maker: maker.publicKey, rentPayer: maker.publicKey, systemProgram: SystemProgram.programId })
+      .signers([maker])
+      .rpc();
+
+    // Only the admin holds the switch
+    try {
+      await program.methods.pauseProgram().accounts({ config: configPda, admin: maker.publicKey }).signers([maker]).rpc();
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert(err.message.includes("ConstraintHasOne") || err.message.includes("has one"));
+    }
+    await program.methods.pauseProgram().accounts({ config: configPda, admin: provider.wallet.publicKey }).rpc();
+
+    try {
+      await createEscrow({ escrowId: new BN(27) })
+        .accounts({ maker: maker.publicKey, rentPayer: maker.publicKey, systemProgram: SystemProgram.programId })
+        .signers([maker])
+        .rpc();
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert(err.message.includes("ProgramPaused"));
+    }
+    try {
+      await program.methods
+        .fundEscrow(noProof, noMaxPrice)
+        .accounts({ escrow: pda, taker: taker.publicKey, maker: maker.publicKey, systemProgram: SystemProgram.programId })
+        .signers([taker])
+        .rpc();
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert(err.message.includes("ProgramPaused"));
+    }
+
+    // Cancelling isn't a deposit, so it still works
+    await program.methods
+      .cancelEscrow()
+      .accounts({
+        escrow: pda,
+        escrowIndex: takerIndexPda,
+        maker: maker.publicKey,
+        authority: maker.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker])
+      .rpc();
+    assert.isNull(await provider.connection.getAccountInfo(pda));
+
+    await program.methods.unpauseProgram().accounts({ config: configPda, admin: provider.wallet.publicKey }).rpc();
+    const config = await program.account.config.fetch(configPda);
+    assert.equal(config.paused, false);
+  });
+
+  it("Pays the referring frontend its share of the payment at settlement", async () => {
+    const id = new BN(28);
+    const pda = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.publicKey.toBuffer(), id.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+
+    // Shares above the cap are rejected
+    try {
+      await createEscrow({ escrowId: id, referralFeeBps: 501 })
+        .accounts({ escrow: pda, maker: maker.publicKey, rentPayer: maker.publicKey, systemProgram: SystemProgram.programId })
+        .signers([maker])
+        .rpc();
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert(err.message.includes("InvalidReferralFee"));
+    }
+
+    await createEscrow({ escrowId: id, referralFeeBps: 200 })
+      .accounts({ escrow: pda, maker: maker.publicKey, rentPayer: maker.publicKey, systemProgram: SystemProgram.programId })
+      .signers([maker])
+      .rpc();
+    // The taker can't refer themselves, nor record the escrow as referrer
+    for (const selfReferrer of [taker.publicKey, pda]) {
+      try {
+        await program.methods
+          .fundEscrow(noProof, noMaxPrice)
+          .accounts({
+            escrow: pda,
+            taker: taker.publicKey,
+            maker: maker.publicKey,
+            referrer: selfReferrer,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([taker])
+          .rpc();
+        assert.fail("Should have thrown error");
+      } catch (err: any) {
+        assert(err.message.includes("SelfReferral"));
+      }
+    }
+
+    await program.methods
+      .fundEscrow(noProof, noMaxPrice)
+      .accounts({
+        escrow: pda,
+        taker: taker.publicKey,
+        maker: maker.publicKey,
+        referrer: otherUser.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([taker])
+      .rpc();
+    assert((await program.account.escrowAccount.fetch(pda)).referrer.equals(otherUser.publicKey));
+
+    // The recorded referrer can't be swapped out at settlement
+    try {
+      await program.methods
+        .completeSwap()
+        .accounts({
+          escrow: pda,
+          escrowIndex: takerIndexPda,
+          taker: taker.publicKey,
+          maker: maker.publicKey,
+          referrer: taker.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([taker])
+        .rpc();
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert(err.message.includes("ReferrerMismatch"));
+    }
+
+    const makerBalanceBefore = await provider.connection.getBalance(maker.publicKey);
+    const referrerBalanceBefore = await provider.connection.getBalance(otherUser.publicKey);
+    const rent = await rentOf(pda);
+    await program.methods
+      .completeSwap()
+      .accounts({
+        escrow: pda,
+        escrowIndex: takerIndexPda,
+        taker: taker.publicKey,
+        maker: maker.publicKey,
+        referrer: otherUser.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([taker])
+      .rpc();
+
+    // 2% of the 500 lamport payment
+    const fee = 10;
+    assert.equal(await provider.connection.getBalance(otherUser.publicKey), referrerBalanceBefore + fee);
+    assert.equal(await provider.connection.getBalance(maker.publicKey), makerBalanceBefore + amountB.toNumber() - fee + rent);
+
+    // Funded without a referrer: the maker keeps the fee, and the taker can't claim it at settlement
+    const unreferredId = new BN(44);
+    const unreferredPda = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.publicKey.toBuffer(), unreferredId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+    await createEscrow({ escrowId: unreferredId, referralFeeBps: 200 })
+      .accounts({ escrow: unreferredPda, maker: maker.publicKey, rentPayer: maker.publicKey, systemProgram: SystemProgram.programId })
+      .signers([maker])
+      .rpc();
+    await program.methods
+      .fundEscrow(noProof, noMaxPrice)
+      .accounts({
+        escrow: unreferredPda,
+        taker: taker.publicKey,
+        maker: maker.publicKey,
+        referrer: null,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([taker])
+      .rpc();
+
+    try {
+      await program.methods
+        .completeSwap()
+        .accounts({
+          escrow: unreferredPda,
+          escrowIndex: takerIndexPda,
+          taker: taker.publicKey,
+          maker: maker.publicKey,
+          referrer: taker.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([taker])
+        .rpc();
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert(err.message.includes("ReferrerMismatch"));
+    }
+
+    const makerBalanceBeforeUnreferred = await provider.connection.getBalance(maker.publicKey);
+    const unreferredRent = await rentOf(unreferredPda);
+    await program.methods
+      .completeSwap()
+      .accounts({
+        escrow: unreferredPda,
+        escrowIndex: takerIndexPda,
+        taker: taker.publicKey,
+        maker: maker.publicKey,
+        referrer: null,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([taker])
+      .rpc();
+    assert.equal(
+      await provider.connection.getBalance(maker.publicKey),
+      makerBalanceBeforeUnreferred + amountB.toNumber() + unreferredRent
+    );
+  });
+
+  it("Creates and cancels a batch of quotes atomically", async () => {
+    const ids = [29, 30, 31].map((id) => new BN(id));
+    const escrowPda = (id: BN) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), maker.publicKey.toBuffer(), id.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      )[0];
+    const batchPage = 1;
+    const [batchIndexPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow_index"), taker.publicKey.toBuffer(), new BN(batchPage).toArrayLike(Buffer, "le", 4)],
+      program.programId
+    );
+    const terms = ids.map((escrowId) => ({
+      escrowId,
+      amountA,
+      amountBExpected: amountB,
+      expiryTs,
+      takerPubkey: taker.publicKey,
+      keeperTip: noTip,
+      indexPage: batchPage,
+      referralFeeBps: noReferralFee,
+    }));
+    const writable = (pubkey: PublicKey) => ({ pubkey, isSigner: false, isWritable: true });
+    const batchAccounts = (pdas: PublicKey[]) => pdas.flatMap((pda) => [writable(pda), writable(batchIndexPda)]);
+    // Cancelling also needs each escrow's rent payer, here the maker, to close it back to
+    const cancelAccounts = (pdas: PublicKey[]) =>
+      pdas.flatMap((pda) => [writable(pda), writable(batchIndexPda), writable(maker.publicKey)]);
+
+    // A wrong PDA for the second quote fails the whole batch, naming its index
+    try {
+      await program.methods
+        .batchCreateEscrows(terms)
+        .accounts({ maker: maker.publicKey })
+        .remainingAccounts(batchAccounts([escrowPda(ids[0]), escrowPda(new BN(99)), escrowPda(ids[2])]))
+        .signers([maker])
+        .rpc();
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert(err.message.includes("batch[1]"));
+      assert(err.message.includes("ConstraintSeeds"));
+    }
+    assert.isNull(await provider.connection.getAccountInfo(escrowPda(ids[0])));
+
+    await program.methods
+      .batchCreateEscrows(terms)
+      .accounts({ maker: maker.publicKey })
+      .remainingAccounts(batchAccounts(ids.map(escrowPda)))
+      .signers([maker])
+      .rpc();
+    let page = await program.account.escrowIndex.fetch(batchIndexPda);
+    assert.equal(page.escrows.length, 3);
+    for (const id of ids) {
+      const escrowAccount = await program.account.escrowAccount.fetch(escrowPda(id));
+      assert.equal(escrowAccount.escrowId.toNumber(), id.toNumber());
+      assert.equal(escrowAccount.isActive, true);
+    }
+
+    const makerBalanceBefore = await provider.connection.getBalance(maker.publicKey);
+    const rent = await rentOf(escrowPda(ids[0]));
+    await program.methods
+      .batchCancelEscrows()
+      .accounts({ maker: maker.publicKey, authority: maker.publicKey })
+      .remainingAccounts(cancelAccounts(ids.map(escrowPda)))
+      .signers([maker])
+      .rpc();
+
+    page = await program.account.escrowIndex.fetch(batchIndexPda);
+    assert.equal(page.escrows.length, 0);
+    for (const id of ids) {
+      assert.isNull(await provider.connection.getAccountInfo(escrowPda(id)));
+    }
+    // The provider wallet pays the transaction fee, so the refund and the rent are exact
+    assert.equal(await provider.connection.getBalance(maker.publicKey), makerBalanceBefore + 3 * (amountA.toNumber() + rent));
+  });
+
+  it("Transfers both positions with counterparty consent", async () => {
+    const id = new BN(32);
+    const pda = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.publicKey.toBuffer(), id.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+    await createEscrow({ escrowId: id, requireTransferConsent: true })
+      .accounts({ escrow: pda, maker: maker.publicKey, rentPayer: maker.publicKey, systemProgram: SystemProgram.programId })
+      .signers([maker])
+      .rpc();
+    const [newTakerIndexPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow_index"), otherUser.publicKey.toBuffer(), new BN(indexPage).toArrayLike(Buffer, "le", 4)],
+      program.programId
+    );
+
+    // The taker can't hand over alone once the escrow asks for consent
+    try {
+      await program.methods
+        .transferTakerRights(otherUser.publicKey, indexPage)
+        .accounts({ escrow: pda, escrowIndex: takerIndexPda, taker: taker.publicKey })
+        .signers([taker])
+        .rpc();
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert(err.message.includes("ConsentRequired"));
+    }
+    await program.methods
+      .transferTakerRights(otherUser.publicKey, indexPage)
+      .accounts({ escrow: pda, escrowIndex: takerIndexPda, taker: taker.publicKey, counterparty: maker.publicKey })
+      .signers([taker, maker])
+      .rpc();
+
+    let escrowAccount = await program.account.escrowAccount.fetch(pda);
+    assert(escrowAccount.taker.equals(otherUser.publicKey));
+    assert.notInclude(
+      (await program.account.escrowIndex.fetch(takerIndexPda)).escrows.map((e) => e.toBase58()),
+      pda.toBase58()
+    );
+    assert.include(
+      (await program.account.escrowIndex.fetch(newTakerIndexPda)).escrows.map((e) => e.toBase58()),
+      pda.toBase58()
+    );
+
+    // The maker side moves too, with the new taker's consent
+    const newMaker = Keypair.generate();
+    await program.methods
+      .transferMakerRights(newMaker.publicKey)
+      .accounts({ escrow: pda, maker: maker.publicKey, counterparty: otherUser.publicKey })
+      .signers([maker, otherUser])
+      .rpc();
+    escrowAccount = await program.account.escrowAccount.fetch(pda);
+    assert(escrowAccount.maker.equals(newMaker.publicKey));
+    assert(escrowAccount.creator.equals(maker.publicKey));
+
+    // Only the new maker can cancel now, and the refund goes to them
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(newMaker.publicKey, anchor.web3.LAMPORTS_PER_SOL)
+    );
+    const newMakerBalanceBefore = await provider.connection.getBalance(newMaker.publicKey);
+    await program.methods
+      .cancelEscrow()
+      .accounts({
+        escrow: pda,
+        escrowIndex: newTakerIndexPda,
+        maker: newMaker.publicKey,
+        authority: newMaker.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([newMaker])
+      .rpc();
+    assert.equal(await provider.connection.getBalance(newMaker.publicKey), newMakerBalanceBefore + amountA.toNumber());
+  });
+
+  it("Pays the maker's proceeds to whoever redeems the receipt", async () => {
+    const id = new BN(33);
+    const pda = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.publicKey.toBuffer(), id.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+    const [receiptMint] = PublicKey.findProgramAddressSync(
+      [Buffer.from("receipt"), pda.toBuffer(), Buffer.from([0])],
+      program.programId
+    );
+    const makerSide = { maker: {} };
+
+    // Deposit and tokenize in one transaction
+    const mintReceiptIx = await program.methods
+      .mintReceipt(makerSide)
+      .accounts({ escrow: pda, receiptMint, depositor: maker.publicKey })
+      .instruction();
+    await createEscrow({ escrowId: id })
+      .accounts({ escrow: pda, maker: maker.publicKey, rentPayer: maker.publicKey, systemProgram: SystemProgram.programId })
+      .postInstructions([mintReceiptIx])
+      .signers([maker])
+      .rpc();
+    const makerReceipt = getAssociatedTokenAddressSync(receiptMint, maker.publicKey);
+    assert.equal(Number((await getAccount(provider.connection, makerReceipt)).amount), 1);
+
+    await program.methods
+      .fundEscrow(noProof, noMaxPrice)
+      .accounts({ escrow: pda, taker: taker.publicKey, maker: maker.publicKey, systemProgram: SystemProgram.programId })
+      .signers([taker])
+      .rpc();
+    const makerBalanceBefore = await provider.connection.getBalance(maker.publicKey);
+    await program.methods
+      .completeSwap()
+      .accounts({
+        escrow: pda,
+        escrowIndex: takerIndexPda,
+        taker: taker.publicKey,
+        maker: maker.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([taker])
+      .rpc();
+
+    // The payment waits in the escrow instead of going to the maker
+    assert.equal(await provider.connection.getBalance(maker.publicKey), makerBalanceBefore);
+    assert.equal((await program.account.escrowAccount.fetch(pda)).makerClaim.toNumber(), amountB.toNumber());
+
+    // The maker sells the receipt; the buyer redeems it
+    const buyerReceipt = await getOrCreateAssociatedTokenAccount(provider.connection, maker, receiptMint, otherUser.publicKey);
+    await transfer(provider.connection, maker, makerReceipt, buyerReceipt.address, maker, 1);
+    try {
+      await program.methods
+        .redeemReceipt(makerSide)
+        .accounts({ escrow: pda, receiptMint, holderReceiptAccount: makerReceipt, holder: maker.publicKey })
+        .signers([maker])
+        .rpc();
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert(err.message.includes("insufficient funds") || err.message.includes("0x1"));
+    }
+
+    const buyerBalanceBefore = await provider.connection.getBalance(otherUser.publicKey);
+    await program.methods
+      .redeemReceipt(makerSide)
+      .accounts({ escrow: pda, receiptMint, holderReceiptAccount: buyerReceipt.address, holder: otherUser.publicKey })
+      .signers([otherUser])
+      .rpc();
+    assert.equal(await provider.connection.getBalance(otherUser.publicKey), buyerBalanceBefore + amountB.toNumber());
+    // Nothing is owed from the escrow any more, so the last redemption closed it
+    assert.isNull(await provider.connection.getAccountInfo(pda));
+  });
+
+  it("Lets the payee pull one elapsed period at a time and the payer cancel the rest", async () => {
+    const subscriptionId = new BN(0);
+    const [subscriptionPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("subscription"), maker.publicKey.toBuffer(), subscriptionId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const perPeriod = new BN(0.1 * anchor.web3.LAMPORTS_PER_SOL);
+    const periodSecs = new BN(2);
+
+    await program.methods
+      .createSubscription(subscriptionId, taker.publicKey, perPeriod, periodSecs, 3)
+      .accounts({ subscription: subscriptionPda, payer: maker.publicKey, systemProgram: SystemProgram.programId })
+      .signers([maker])
+      .rpc();
+    const subscription = await program.account.subscription.fetch(subscriptionPda);
+    assert.equal(subscription.periods, 3);
+    assert.equal(subscription.periodsCollected, 0);
+
+    // Nothing has elapsed yet
+    try {
+      await program.methods
+        .collectPeriod()
+        .accounts({ subscription: subscriptionPda, payee: taker.publicKey, payer: maker.publicKey })
+        .signers([taker])
+        .rpc();
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert.include(err.message, "PeriodNotDue");
+    }
+
+    await new Promise((resolve) => setTimeout(resolve, 2500));
+    const takerBalanceBefore = await provider.connection.getBalance(taker.publicKey);
+    await program.methods
+      .collectPeriod()
+      .accounts({ subscription: subscriptionPda, payee: taker.publicKey, payer: maker.publicKey })
+      .signers([taker])
+      .rpc();
+    assert.equal(await provider.connection.getBalance(taker.publicKey), takerBalanceBefore + perPeriod.toNumber());
+    assert.equal((await program.account.subscription.fetch(subscriptionPda)).periodsCollected, 1);
+
+    // Cancelling pays out anything already earned and refunds the rest to the payer
+    const makerBalanceBefore = await provider.connection.getBalance(maker.publicKey);
+    await program.methods
+      .cancelSubscription()
+      .accounts({ subscription: subscriptionPda, payer: maker.publicKey, payee: taker.publicKey })
+      .signers([maker])
+      .rpc();
+    assert.isNull(await provider.connection.getAccountInfo(subscriptionPda));
+    assert.isAtLeast(await provider.connection.getBalance(maker.publicKey), makerBalanceBefore + perPeriod.toNumber());
+  });
+
+  it("Charges a Dutch auction's current price, capped by the taker's maximum", async () => {
+    const id = new BN(34);
+    const pda = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.publicKey.toBuffer(), id.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+    const now = Math.floor(Date.now() / 1000);
+    const auctionExpiry = new BN(now + 3600);
+    // Halfway from 2000 down to 1000 lamports right now, in 60-second steps
+    const auction = { startPrice: new BN(2000), floorPrice: new BN(1000), startTs: new BN(now - 3600), stepSecs: 60 };
+
+    await createEscrow({ escrowId: id, amountB: new BN(0), expiryTs: auctionExpiry, dutchAuction: auction })
+      .accounts({ escrow: pda, maker: maker.publicKey, rentPayer: maker.publicKey, systemProgram: SystemProgram.programId })
+      .signers([maker])
+      .rpc();
+    assert.equal((await program.account.escrowAccount.fetch(pda)).amountBExpected.toNumber(), 2000);
+
+    // The floor isn't reached until expiry
+    try {
+      await program.methods
+        .fundEscrow(noProof, new BN(1000))
+        .accounts({ escrow: pda, taker: taker.publicKey, maker: maker.publicKey, systemProgram: SystemProgram.programId })
+        .signers([taker])
+        .rpc();
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert.include(err.message, "PriceAboveMax");
+    }
+
+    const escrowBalanceBefore = await provider.connection.getBalance(pda);
+    await program.methods
+      .fundEscrow(noProof, new BN(1600))
+      .accounts({ escrow: pda, taker: taker.publicKey, maker: maker.publicKey, systemProgram: SystemProgram.programId })
+      .signers([taker])
+      .rpc();
+    const charged = (await program.account.escrowAccount.fetch(pda)).amountBExpected.toNumber();
+    assert.isAtLeast(charged, 1400);
+    assert.isAtMost(charged, 1500);
+    assert.equal(await provider.connection.getBalance(pda), escrowBalanceBefore + charged);
+  });
+
+  const bidPda = (escrow: PublicKey, bidder: PublicKey) =>
+    PublicKey.findProgramAddressSync([Buffer.from("bid"), escrow.toBuffer(), bidder.toBuffer()], program.programId)[0];
+
+  it("Refunds outbid English bids and settles to the highest bidder", async () => {
+    const id = new BN(35);
+    const pda = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.publicKey.toBuffer(), id.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+    const auction = { kind: { english: {} }, reservePrice: new BN(1000), minIncrement: new BN(100), revealSecs: 0 };
+    const auctionExpiry = new BN(Math.floor(Date.now() / 1000) + 5);
+    await createEscrow({ escrowId: id, amountB: new BN(0), expiryTs: auctionExpiry, auction })
+      .accounts({ escrow: pda, maker: maker.publicKey, rentPayer: maker.publicKey, systemProgram: SystemProgram.programId })
+      .signers([maker])
+      .rpc();
+
+    await program.methods
+      .placeBid(new BN(1000))
+      .accounts({ escrow: pda, bid: bidPda(pda, otherUser.publicKey), bidder: otherUser.publicKey, previousBid: null, previousBidder: null })
+      .signers([otherUser])
+      .rpc();
+
+    // Must beat the leader by the increment
+    try {
+      await program.methods
+        .placeBid(new BN(1050))
+        .accounts({ escrow: pda, bid: bidPda(pda, taker.publicKey), bidder: taker.publicKey, previousBid: bidPda(pda, otherUser.publicKey), previousBidder: otherUser.publicKey })
+        .signers([taker])
+        .rpc();
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert.include(err.message, "BidTooLow");
+    }
+
+    const otherBalanceBefore = await provider.connection.getBalance(otherUser.publicKey);
+    await program.methods
+      .placeBid(new BN(1100))
+      .accounts({ escrow: pda, bid: bidPda(pda, taker.publicKey), bidder: taker.publicKey, previousBid: bidPda(pda, otherUser.publicKey), previousBidder: otherUser.publicKey })
+      .signers([taker])
+      .rpc();
+    assert.isNull(await provider.connection.getAccountInfo(bidPda(pda, otherUser.publicKey)));
+    assert.isAbove(await provider.connection.getBalance(otherUser.publicKey), otherBalanceBefore + 1000);
+
+    // The maker can't pull the lot out from under the bidders
+    try {
+      await program.methods
+        .cancelEscrow()
+        .accounts({ escrow: pda, escrowIndex: takerIndexPda, maker: maker.publicKey, authority: maker.publicKey, systemProgram: SystemProgram.programId })
+        .signers([maker])
+        .rpc();
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert.include(err.message, "AuctionHasBids");
+    }
+
+    await new Promise((resolve) => setTimeout(resolve, 6000));
+    const makerBalanceBefore = await provider.connection.getBalance(maker.publicKey);
+    const rent = await rentOf(pda);
+    await program.methods
+      .settleAuction()
+      .accounts({
+        escrow: pda,
+        escrowIndex: takerIndexPda,
+        winningBid: bidPda(pda, taker.publicKey),
+        winner: taker.publicKey,
+        maker: maker.publicKey,
+        settler: provider.wallet.publicKey,
+      })
+      .rpc();
+    assert.equal(await provider.connection.getBalance(maker.publicKey), makerBalanceBefore + 1100 + rent);
+    assert.isNull(await provider.connection.getAccountInfo(pda));
+  });
+
+  it("Runs a sealed-bid auction through commit, reveal, withdraw and settle", async () => {
+    const id = new BN(36);
+    const pda = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.publicKey.toBuffer(), id.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+    const auction = { kind: { sealedBid: {} }, reservePrice: new BN(1000), minIncrement: new BN(0), revealSecs: 5 };
+    const auctionExpiry = new BN(Math.floor(Date.now() / 1000) + 4);
+    await createEscrow({ escrowId: id, amountB: new BN(0), expiryTs: auctionExpiry, auction })
+      .accounts({ escrow: pda, maker: maker.publicKey, rentPayer: maker.publicKey, systemProgram: SystemProgram.programId })
+      .signers([maker])
+      .rpc();
+
+    // sha256(amount le u64 || salt || bidder); deposits hide the bids
+    const seal = (amount: BN, salt: Buffer, bidder: PublicKey) =>
+      Array.from(createHash("sha256").update(Buffer.concat([amount.toArrayLike(Buffer, "le", 8), salt, bidder.toBuffer()])).digest());
+    const bids = [
+      { wallet: otherUser, amount: new BN(1500), deposit: new BN(2000), salt: Buffer.alloc(32, 7) },
+      { wallet: taker, amount: new BN(1200), deposit: new BN(1500), salt: Buffer.alloc(32, 9) },
+    ];
+    for (const bid of bids) {
+      await program.methods
+        .commitBid(seal(bid.amount, bid.salt, bid.wallet.publicKey), bid.deposit)
+        .accounts({ escrow: pda, bid: bidPda(pda, bid.wallet.publicKey), bidder: bid.wallet.publicKey })
+        .signers([bid.wallet])
+        .rpc();
+    }
+
+    await new Promise((resolve) => setTimeout(resolve, 5000));
+    for (const bid of bids) {
+      await program.methods
+        .revealBid(bid.amount, Array.from(bid.salt))
+        .accounts({ escrow: pda, bid: bidPda(pda, bid.wallet.publicKey), bidder: bid.wallet.publicKey })
+        .signers([bid.wallet])
+        .rpc();
+    }
+    assert.equal(
+      (await program.account.escrowAccount.fetch(pda)).highestBidder.toBase58(),
+      otherUser.publicKey.toBase58()
+    );
+
+    // The loser takes their deposit back; the winner can't
+    await program.methods
+      .withdrawBid()
+      .accounts({ escrow: pda, bid: bidPda(pda, taker.publicKey), bidder: taker.publicKey })
+      .signers([taker])
+      .rpc();
+    try {
+      await program.methods
+        .withdrawBid()
+        .accounts({ escrow: pda, bid: bidPda(pda, otherUser.publicKey), bidder: otherUser.publicKey })
+        .signers([otherUser])
+        .rpc();
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert.include(err.message, "BidStillLeading");
+    }
+
+    await new Promise((resolve) => setTimeout(resolve, 5000));
+    const makerBalanceBefore = await provider.connection.getBalance(maker.publicKey);
+    const rent = await rentOf(pda);
+    await program.methods
+      .settleAuction()
+      .accounts({
+        escrow: pda,
+        escrowIndex: takerIndexPda,
+        winningBid: bidPda(pda, otherUser.publicKey),
+        winner: otherUser.publicKey,
+        maker: maker.publicKey,
+        settler: provider.wallet.publicKey,
+      })
+      .rpc();
+    // Every other bid was withdrawn, so settling closes the escrow
+    assert.equal(await provider.connection.getBalance(maker.publicKey), makerBalanceBefore + 1500 + rent);
+    assert.isNull(await provider.connection.getAccountInfo(pda));
+  });
+
+  it("Stores reference metadata and echoes it as a memo on settlement", async () => {
+    const id = new BN(37);
+    const pda = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.publicKey.toBuffer(), id.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+    const contentHash = createHash("sha256").update("INV-2024-0042").digest();
+    const metadata = { contentHash: Array.from(contentHash), uri: "https://erp.example.com/invoices/42" };
+
+    // URIs are bounded
+    try {
+      await createEscrow({ escrowId: id, metadata: { ...metadata, uri: "x".repeat(97) } })
+        .accounts({ escrow: pda, maker: maker.publicKey, rentPayer: maker.publicKey, systemProgram: SystemProgram.programId })
+        .signers([maker])
+        .rpc();
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert.include(err.message, "InvalidMetadata");
+    }
+
+    await createEscrow({ escrowId: id, metadata })
+      .accounts({ escrow: pda, maker: maker.publicKey, rentPayer: maker.publicKey, systemProgram: SystemProgram.programId })
+      .signers([maker])
+      .rpc();
+    const stored = (await program.account.escrowAccount.fetch(pda)).metadata;
+    assert.deepEqual(Buffer.from(stored.contentHash), contentHash);
+    assert.equal(stored.uri, metadata.uri);
+
+    await program.methods
+      .fundEscrow(noProof, noMaxPrice)
+      .accounts({ escrow: pda, taker: taker.publicKey, systemProgram: SystemProgram.programId })
+      .signers([taker])
+      .rpc();
+    const signature = await program.methods
+      .completeSwap()
+      .accounts({
+        escrow: pda,
+        escrowIndex: takerIndexPda,
+        taker: taker.publicKey,
+        maker: maker.publicKey,
+        memoProgram: new PublicKey("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr"),
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([taker])
+      .rpc({ commitment: "confirmed" });
+
+    const tx = await provider.connection.getTransaction(signature, { commitment: "confirmed", maxSupportedTransactionVersion: 0 });
+    const memo = `escrow ${pda.toBase58()} ref ${contentHash.toString("hex")} ${metadata.uri}`;
+    assert(tx.meta.logMessages.some((log) => log.includes(memo)));
+  });
+
+  it("Lets a sponsor pay the rent and refunds it when the escrow closes", async () => {
+    const sponsor = Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(sponsor.publicKey, anchor.web3.LAMPORTS_PER_SOL)
+    );
+    const id = new BN(38);
+    const pda = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.publicKey.toBuffer(), id.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+    const keeperTip = new BN(100);
+    const shortExpiry = new BN(Math.floor(Date.now() / 1000) + 5);
+
+    // The maker only parts with the offer and the tip; the sponsor pays the rent
+    const makerBalanceBefore = await provider.connection.getBalance(maker.publicKey);
+    const sponsorBalanceBefore = await provider.connection.getBalance(sponsor.publicKey);
+    await createEscrow({ escrowId: id, expiryTs: shortExpiry, keeperTip })
+      .accounts({ escrow: pda, maker: maker.publicKey, rentPayer: sponsor.publicKey, systemProgram: SystemProgram.programId })
+      .signers([maker, sponsor])
+      .rpc();
+
+    const rent = (await provider.connection.getBalance(pda)) - amountA.toNumber() - keeperTip.toNumber();
+    assert.equal(
+      await provider.connection.getBalance(maker.publicKey),
+      makerBalanceBefore - amountA.toNumber() - keeperTip.toNumber()
+    );
+    assert.equal(await provider.connection.getBalance(sponsor.publicKey), sponsorBalanceBefore - rent);
+    const escrowAccount = await program.account.escrowAccount.fetch(pda);
+    assert.equal(escrowAccount.rentPayer.toString(), sponsor.publicKey.toString());
+
+    // Closing the escrow returns the rent to the sponsor, not the maker
+    await new Promise(resolve => setTimeout(resolve, 10000));
+    await program.methods
+      .crankExpired()
+      .accounts({
+        escrow: pda,
+        escrowIndex: takerIndexPda,
+        maker: maker.publicKey,
+        rentPayer: sponsor.publicKey,
+        keeper: otherUser.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([otherUser])
+      .rpc();
+
+    assert.isNull(await provider.connection.getAccountInfo(pda));
+    assert.equal(await provider.connection.getBalance(sponsor.publicKey), sponsorBalanceBefore);
+    assert.equal(await provider.connection.getBalance(maker.publicKey), makerBalanceBefore - keeperTip.toNumber());
+  });
+
+  it("Slashes the bond of a taker who misses the settlement deadline", async () => {
+    const id = new BN(39);
+    const pda = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.publicKey.toBuffer(), id.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+    const bond = { amount: new BN(200), settleSecs: 2 };
+
+    // Only escrows the taker settles can ask for a bond
+    try {
+      await createEscrow({ escrowId: id, attester: otherUser.publicKey, takerBond: bond })
+        .accounts({ escrow: pda, maker: maker.publicKey, rentPayer: maker.publicKey, systemProgram: SystemProgram.programId })
+        .signers([maker])
+        .rpc();
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert.include(err.message, "InvalidTakerBond");
+    }
+
+    await createEscrow({ escrowId: id, takerBond: bond })
+      .accounts({ escrow: pda, maker: maker.publicKey, rentPayer: maker.publicKey, systemProgram: SystemProgram.programId })
+      .signers([maker])
+      .rpc();
+
+    // Funding takes the payment and the bond
+    const takerBalanceBefore = await provider.connection.getBalance(taker.publicKey);
+    await program.methods
+      .fundEscrow(noProof, noMaxPrice)
+      .accounts({ escrow: pda, taker: taker.publicKey, systemProgram: SystemProgram.programId })
+      .signers([taker])
+      .rpc();
+    assert.equal(
+      await provider.connection.getBalance(taker.publicKey),
+      takerBalanceBefore - amountB.toNumber() - bond.amount.toNumber()
+    );
+    const escrowAccount = await program.account.escrowAccount.fetch(pda);
+    assert(escrowAccount.settleByTs.toNumber() > 0);
+
+    const slash = () =>
+      program.methods
+        .slashTakerBond()
+        .accounts({
+          escrow: pda,
+          escrowIndex: takerIndexPda,
+          maker: maker.publicKey,
+          taker: taker.publicKey,
+          authority: maker.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([maker])
+        .rpc();
+
+    try {
+      await slash();
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert.include(err.message, "SettlementWindowOpen");
+    }
+
+    // Once the window has passed the maker unwinds the trade and keeps the bond
+    await new Promise(resolve => setTimeout(resolve, 4000));
+    const makerBalanceBefore = await provider.connection.getBalance(maker.publicKey);
+    const rent = await rentOf(pda);
+    await slash();
+
+    assert.equal(
+      await provider.connection.getBalance(maker.publicKey),
+      makerBalanceBefore + amountA.toNumber() + bond.amount.toNumber() + rent
+    );
+    assert.equal(
+      await provider.connection.getBalance(taker.publicKey),
+      takerBalanceBefore - bond.amount.toNumber()
+    );
+    assert.isNull(await provider.connection.getAccountInfo(pda));
+  });
+
+  it("Compensates the taker when the maker cancels a funded escrow", async () => {
+    const id = new BN(40);
+    const pda = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.publicKey.toBuffer(), id.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+    const penaltyBps = 1000; // 10% of amountA
+
+    await createEscrow({ escrowId: id, cancelPenaltyBps: penaltyBps })
+      .accounts({ escrow: pda, maker: maker.publicKey, rentPayer: maker.publicKey, systemProgram: SystemProgram.programId })
+      .signers([maker])
+      .rpc();
+    await program.methods
+      .fundEscrow(noProof, noMaxPrice)
+      .accounts({ escrow: pda, taker: taker.publicKey, systemProgram: SystemProgram.programId })
+      .signers([taker])
+      .rpc();
+
+    // An operator can cancel unfunded escrows, but can't spend the maker's penalty
+    await program.methods
+      .setOperator(otherUser.publicKey, OPERATOR_CAN_CANCEL)
+      .accounts({ escrow: pda, maker: maker.publicKey })
+      .signers([maker])
+      .rpc();
+    try {
+      await program.methods
+        .cancelEscrow()
+        .accounts({
+          escrow: pda,
+          escrowIndex: takerIndexPda,
+          maker: maker.publicKey,
+          taker: taker.publicKey,
+          authority: otherUser.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([otherUser])
+        .rpc();
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert(err.message.includes("Unauthorized"));
+    }
+
+    const makerBalanceBefore = await provider.connection.getBalance(maker.publicKey);
+    const takerBalanceBefore = await provider.connection.getBalance(taker.publicKey);
+    const rent = await rentOf(pda);
+    await program.methods
+      .cancelEscrow()
+      .accounts({
+        escrow: pda,
+        escrowIndex: takerIndexPda,
+        maker: maker.publicKey,
+        taker: taker.publicKey,
+        authority: maker.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker])
+      .rpc();
+
+    // The taker gets their payment back plus 10% of the offer; the maker the other 90%
+    const penalty = (amountA.toNumber() * penaltyBps) / 10_000;
+    assert.equal(await provider.connection.getBalance(taker.publicKey), takerBalanceBefore + amountB.toNumber() + penalty);
+    assert.equal(await provider.connection.getBalance(maker.publicKey), makerBalanceBefore + amountA.toNumber() - penalty + rent);
+    assert.isNull(await provider.connection.getAccountInfo(pda));
+  });
+
+  it("Only lets takers with enough settled swaps fund a reputation-gated escrow", async () => {
+    const escrowPdaFor = (id: BN) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), maker.publicKey.toBuffer(), id.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      )[0];
+    const createGated = async (id: BN, minCompleted: number) => {
+      await createEscrow({ escrowId: id, minTakerCompleted: minCompleted })
+        .accounts({ escrow: escrowPdaFor(id), maker: maker.publicKey, rentPayer: maker.publicKey, systemProgram: SystemProgram.programId })
+        .signers([maker])
+        .rpc();
+      return escrowPdaFor(id);
+    };
+    const takerStatsPda = userStatsPda(taker.publicKey);
+    const { completed } = await program.account.userStats.fetch(takerStatsPda);
+
+    // One more settled swap than the taker has is too many, with or without their stats
+    const tooHigh = await createGated(new BN(41), completed + 1);
+    for (const takerStats of [takerStatsPda, null]) {
+      try {
+        await program.methods
+          .fundEscrow(noProof, noMaxPrice)
+          .accounts({ escrow: tooHigh, taker: taker.publicKey, takerStats, systemProgram: SystemProgram.programId })
+          .signers([taker])
+          .rpc();
+        assert.fail("Should have thrown error");
+      } catch (err: any) {
+        assert(err.message.includes("TakerReputationTooLow"));
+      }
+    }
+
+    // Cancelling it goes on the maker's record
+    const { cancelled } = await program.account.userStats.fetch(userStatsPda(maker.publicKey));
+    await program.methods
+      .cancelEscrow()
+      .accounts({ escrow: tooHigh, escrowIndex: takerIndexPda, maker: maker.publicKey, authority: maker.publicKey, systemProgram: SystemProgram.programId })
+      .signers([maker])
+      .rpc();
+    assert.equal((await program.account.userStats.fetch(userStatsPda(maker.publicKey))).cancelled, cancelled + 1);
+
+    // Exactly the taker's count is enough
+    const reachable = await createGated(new BN(42), completed);
+    await program.methods
+      .fundEscrow(noProof, noMaxPrice)
+      .accounts({ escrow: reachable, taker: taker.publicKey, takerStats: takerStatsPda, systemProgram: SystemProgram.programId })
+      .signers([taker])
+      .rpc();
+
+    // ...but the position can't be passed on to a wallet without that record
+    try {
+      await program.methods
+        .transferTakerRights(otherUser.publicKey, indexPage)
+        .accounts({ escrow: reachable, escrowIndex: takerIndexPda, taker: taker.publicKey, newTakerStats: null })
+        .signers([taker])
+        .rpc();
+      assert.fail("Should have thrown error");
+    } catch (err: any) {
+      assert(err.message.includes("TakerReputationTooLow"));
+    }
+    const escrowAccount = await program.account.escrowAccount.fetch(reachable);
+    assert.equal(escrowAccount.isFunded, true);
+    assert.equal(escrowAccount.minTakerCompleted, completed);
   });
-});
\ No newline at end of file
+});